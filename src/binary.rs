@@ -0,0 +1,177 @@
+//! Read and write binary KeyValues.
+//!
+//! Steam stores `shortcuts.vdf`, the entries of `appinfo.vdf` and many caches in a binary form
+//! of KeyValues. Each entry is a type byte, a null-terminated key and a value whose encoding
+//! depends on the type:
+//!
+//! | Type   | Value                                    | Serialized from                   |
+//! |:------:|------------------------------------------|-----------------------------------|
+//! | `0x00` | Entries up to the next `0x08` (object)   | Maps, structs                     |
+//! | `0x01` | Null-terminated UTF-8 string             | Strings, `char`s, unit variants   |
+//! | `0x02` | Little-endian 32-bit integer             | `bool`, `i8`-`i32`, `u8`-`u32`    |
+//! | `0x03` | Little-endian 32-bit float               | `f32`, `f64`                      |
+//! | `0x04` | Little-endian 32-bit pointer             | [`Value::Pointer`]                |
+//! | `0x05` | Null-terminated UTF-16LE string          | -                                 |
+//! | `0x06` | 4-byte color                             | [`Value::Color`]                  |
+//! | `0x07` | Little-endian unsigned 64-bit integer    | `u64`                             |
+//! | `0x0A` | Little-endian signed 64-bit integer      | `i64`                             |
+//!
+//! A document is a list of entries ending with `0x08`. Unsigned 32-bit integers are stored in
+//! the bits of a signed one, the way Steam stores app IDs, and `f64`s lose precision since there
+//! is no 64-bit float type. When deserializing, an integer entry can be read as any integer type
+//! that can hold its value. The only exceptions are that a `u32` is read from the bits of an
+//! `int32` entry and a `u64` from the bits of an `int64` entry, so a negative `int32` is read as
+//! a large `u32`. Strings are also parsed if a number is expected.
+//!
+//! [`Value`] keeps the type of each entry, so a document read into a [`KeyValues`] is written
+//! back with the same types. The only exception is wide strings, which are read as
+//! [`Value::String`] and written back as UTF-8.
+//!
+//! [`Value`]: crate::Value
+//! [`Value::Pointer`]: crate::Value::Pointer
+//! [`Value::Color`]: crate::Value::Color
+//! [`Value::String`]: crate::Value::String
+//! [`KeyValues`]: crate::KeyValues
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Shortcut {
+//!     appid: u32,
+//!     #[serde(rename = "AppName")]
+//!     app_name: String,
+//!     #[serde(rename = "IsHidden")]
+//!     is_hidden: bool,
+//! }
+//!
+//! let shortcut = Shortcut {
+//!     appid: 3_000_000_000,
+//!     app_name: String::from("Game"),
+//!     is_hidden: false,
+//! };
+//! let bytes = vdflex::binary::to_vec(&shortcut).unwrap();
+//! assert_eq!(bytes[..7], *b"\x02appid\0");
+//! assert_eq!(bytes[7..11], 3_000_000_000u32.to_le_bytes());
+//!
+//! let read: Shortcut = vdflex::binary::from_slice(&bytes).unwrap();
+//! assert_eq!(read, shortcut);
+//! ```
+
+mod deserializer;
+mod formatter;
+
+use crate::ser::Serializer;
+use crate::Result;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+pub use deserializer::Deserializer;
+pub use formatter::BinaryFormatter;
+
+pub(crate) use deserializer::Node;
+
+const TYPE_OBJECT: u8 = 0x00;
+const TYPE_STRING: u8 = 0x01;
+const TYPE_INT32: u8 = 0x02;
+const TYPE_FLOAT32: u8 = 0x03;
+const TYPE_POINTER: u8 = 0x04;
+const TYPE_WIDE_STRING: u8 = 0x05;
+const TYPE_COLOR: u8 = 0x06;
+const TYPE_UINT64: u8 = 0x07;
+const TYPE_END: u8 = 0x08;
+const TYPE_INT64: u8 = 0x0A;
+
+/// Deserialize an instance of type `T` from binary KeyValues.
+///
+/// Strings are borrowed from `v`, so `T` may contain `&str` and `Cow<str>` fields.
+///
+/// # Errors
+///
+/// Deserialization can fail if the input is not valid binary KeyValues or does not match the
+/// structure expected by `T`. It can also fail if `T`'s implementation of `Deserialize` decides
+/// to fail.
+pub fn from_slice<'a, T: Deserialize<'a>>(v: &'a [u8]) -> Result<T> {
+    let mut deserializer = Deserializer::from_slice(v);
+    let value = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// Deserialize an instance of type `T` from binary KeyValues read from `reader`.
+///
+/// # Errors
+///
+/// See [`from_slice`].
+pub fn from_reader<R: Read, T: DeserializeOwned>(mut reader: R) -> Result<T> {
+    let mut v = Vec::new();
+    reader.read_to_end(&mut v)?;
+    from_slice(&v)
+}
+
+/// Serialize the given value as binary KeyValues into the specified writer. The value must be a
+/// map or a struct, whose entries become the root-level entries of the document.
+///
+/// `f64`s are written as 32-bit floats, since binary KeyValues has no 64-bit float type, so they
+/// may lose precision. See [`BinaryFormatter`].
+///
+/// # Errors
+///
+/// Serialization can fail if `T` cannot be represented as binary KeyValues or if `T`'s
+/// implementation of `Serialize` decides to fail.
+pub fn to_writer<W: Write, T: ?Sized + Serialize>(writer: W, value: &T) -> Result<()> {
+    let mut serializer = Serializer::new(writer, BinaryFormatter::new());
+    value.serialize(&mut serializer)
+}
+
+/// Serialize the given value as binary KeyValues. See [`to_writer`].
+///
+/// # Errors
+///
+/// See [`to_writer`].
+pub fn to_vec<T: ?Sized + Serialize>(value: &T) -> Result<Vec<u8>> {
+    let mut writer = Vec::new();
+    to_writer(&mut writer, value)?;
+    Ok(writer)
+}
+
+/// Writes `entries` followed by the end of their object, using `write_key` to write each key.
+pub(crate) fn write_object(
+    out: &mut Vec<u8>,
+    entries: &[(&str, Node)],
+    write_key: &mut impl FnMut(&mut Vec<u8>, &str),
+) {
+    for (key, node) in entries {
+        let ty = match node {
+            Node::Object(_) => TYPE_OBJECT,
+            Node::String(_) => TYPE_STRING,
+            Node::Int32(_) => TYPE_INT32,
+            Node::Float32(_) => TYPE_FLOAT32,
+            Node::Pointer(_) => TYPE_POINTER,
+            Node::WideString(_) => TYPE_WIDE_STRING,
+            Node::Color(_) => TYPE_COLOR,
+            Node::UInt64(_) => TYPE_UINT64,
+            Node::Int64(_) => TYPE_INT64,
+        };
+        out.push(ty);
+        write_key(out, key);
+        match node {
+            Node::Object(entries) => write_object(out, entries, write_key),
+            Node::String(s) => {
+                out.extend_from_slice(s.as_bytes());
+                out.push(0);
+            }
+            Node::WideString(s) => {
+                out.extend(s.encode_utf16().chain([0]).flat_map(u16::to_le_bytes));
+            }
+            Node::Int32(v) => out.extend_from_slice(&v.to_le_bytes()),
+            Node::Float32(v) => out.extend_from_slice(&v.to_le_bytes()),
+            Node::Pointer(v) => out.extend_from_slice(&v.to_le_bytes()),
+            Node::Color(rgba) => out.extend_from_slice(rgba),
+            Node::UInt64(v) => out.extend_from_slice(&v.to_le_bytes()),
+            Node::Int64(v) => out.extend_from_slice(&v.to_le_bytes()),
+        }
+    }
+    out.push(TYPE_END);
+}
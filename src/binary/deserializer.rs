@@ -0,0 +1,768 @@
+use super::{
+    TYPE_COLOR, TYPE_END, TYPE_FLOAT32, TYPE_INT32, TYPE_INT64, TYPE_OBJECT, TYPE_POINTER,
+    TYPE_STRING, TYPE_UINT64, TYPE_WIDE_STRING,
+};
+use crate::de::MAX_DEPTH;
+use crate::{
+    Error, Result, COLOR_TOKEN, FLOAT32_TOKEN, INT32_TOKEN, INT64_TOKEN, POINTER_TOKEN,
+    UINT64_TOKEN, VALUE_TOKEN,
+};
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{
+    self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, Unexpected, Visitor,
+};
+use serde::forward_to_deserialize_any;
+use std::collections::HashMap;
+use std::str;
+
+/// A value read from binary KeyValues.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Node<'de> {
+    String(&'de str),
+    WideString(String),
+    Int32(i32),
+    Float32(f32),
+    Pointer(u32),
+    Color([u8; 4]),
+    UInt64(u64),
+    Int64(i64),
+    Object(Vec<(&'de str, Node<'de>)>),
+}
+
+impl Node<'_> {
+    fn unexpected(&self) -> Unexpected<'_> {
+        match self {
+            Node::String(s) => Unexpected::Str(s),
+            Node::WideString(s) => Unexpected::Str(s),
+            Node::Int32(v) => Unexpected::Signed(i64::from(*v)),
+            Node::Float32(v) => Unexpected::Float(f64::from(*v)),
+            Node::Pointer(v) => Unexpected::Unsigned(u64::from(*v)),
+            Node::Color(_) => Unexpected::Other("color"),
+            Node::UInt64(v) => Unexpected::Unsigned(*v),
+            Node::Int64(v) => Unexpected::Signed(*v),
+            Node::Object(_) => Unexpected::Map,
+        }
+    }
+}
+
+/// Deserializes binary KeyValues into Rust types.
+pub struct Deserializer<'de> {
+    input: &'de [u8],
+    pos: usize,
+    /// The strings that keys refer to, if keys are written as indexes into a table instead of
+    /// as strings.
+    keys: Option<&'de [&'de str]>,
+    /// The number of objects that are currently being read, not counting the root object.
+    depth: usize,
+}
+
+impl<'de> Deserializer<'de> {
+    /// Creates a binary KeyValues deserializer from a byte slice.
+    pub fn from_slice(input: &'de [u8]) -> Self {
+        Self {
+            input,
+            pos: 0,
+            keys: None,
+            depth: 0,
+        }
+    }
+
+    /// Creates a deserializer for binary KeyValues whose keys are 32-bit indexes into `keys`,
+    /// like the entries of newer `appinfo.vdf` files.
+    pub(crate) fn with_keys(input: &'de [u8], keys: &'de [&'de str]) -> Self {
+        Self {
+            input,
+            pos: 0,
+            keys: Some(keys),
+            depth: 0,
+        }
+    }
+
+    /// Returns the number of bytes read so far.
+    pub(crate) fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Checks that the whole input has been read. Call this after deserializing a value to
+    /// reject trailing data.
+    ///
+    /// # Errors
+    ///
+    /// Fails if there is input left over.
+    pub fn end(&self) -> Result<()> {
+        if self.pos < self.input.len() {
+            Err(self.error("unexpected data after the end of the document"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Reads entries up to the end of the current object.
+    pub(crate) fn object(&mut self) -> Result<Vec<(&'de str, Node<'de>)>> {
+        let mut entries = Vec::new();
+        loop {
+            let start = self.pos;
+            let ty = self.byte()?;
+            if ty == TYPE_END {
+                return Ok(entries);
+            }
+
+            let key = self.key()?;
+            let node = match ty {
+                TYPE_OBJECT if self.depth == MAX_DEPTH => {
+                    self.pos = start;
+                    return Err(self.error("nested too deeply"));
+                }
+                TYPE_OBJECT => {
+                    self.depth += 1;
+                    let entries = self.object();
+                    self.depth -= 1;
+                    Node::Object(entries?)
+                }
+                TYPE_STRING => Node::String(self.string()?),
+                TYPE_INT32 => Node::Int32(i32::from_le_bytes(self.array()?)),
+                TYPE_FLOAT32 => Node::Float32(f32::from_le_bytes(self.array()?)),
+                TYPE_POINTER => Node::Pointer(u32::from_le_bytes(self.array()?)),
+                TYPE_WIDE_STRING => Node::WideString(self.wide_string()?),
+                TYPE_COLOR => Node::Color(self.array()?),
+                TYPE_UINT64 => Node::UInt64(u64::from_le_bytes(self.array()?)),
+                TYPE_INT64 => Node::Int64(i64::from_le_bytes(self.array()?)),
+                _ => {
+                    self.pos = start;
+                    return Err(self.error(format!("unknown type {ty:#04x}")));
+                }
+            };
+            entries.push((key, node));
+        }
+    }
+
+    fn error(&self, reason: impl Into<String>) -> Error {
+        Error::InvalidBinary {
+            offset: self.pos,
+            reason: reason.into(),
+        }
+    }
+
+    fn eof(&self) -> Error {
+        self.error("unexpected end of input")
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        let byte = *self.input.get(self.pos).ok_or_else(|| self.eof())?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let bytes = self
+            .input
+            .get(self.pos..self.pos + N)
+            .ok_or_else(|| self.eof())?;
+        self.pos += N;
+        Ok(bytes.try_into().expect("slice has the right length"))
+    }
+
+    fn key(&mut self) -> Result<&'de str> {
+        let Some(keys) = self.keys else {
+            return self.string();
+        };
+        let index = u32::from_le_bytes(self.array()?);
+        match keys.get(index as usize) {
+            Some(key) => Ok(key),
+            None => {
+                self.pos -= 4;
+                Err(self.error(format!("key index {index} is out of range")))
+            }
+        }
+    }
+
+    /// Reads a null-terminated UTF-8 string.
+    fn string(&mut self) -> Result<&'de str> {
+        let rest = &self.input[self.pos..];
+        let len = rest
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| self.eof())?;
+        let s = str::from_utf8(&rest[..len]).map_err(|_| self.error("invalid UTF-8"))?;
+        self.pos += len + 1;
+        Ok(s)
+    }
+
+    /// Reads a null-terminated UTF-16LE string.
+    fn wide_string(&mut self) -> Result<String> {
+        let start = self.pos;
+        let mut units = Vec::new();
+        loop {
+            match u16::from_le_bytes(self.array()?) {
+                0 => break,
+                unit => units.push(unit),
+            }
+        }
+        String::from_utf16(&units).map_err(|_| Error::InvalidBinary {
+            offset: start,
+            reason: String::from("invalid UTF-16"),
+        })
+    }
+
+    fn with_root<T>(
+        &mut self,
+        f: impl FnOnce(NodeDeserializer<'_, 'de>) -> Result<T>,
+    ) -> Result<T> {
+        let root = Node::Object(self.object()?);
+        f(NodeDeserializer::new(&root))
+    }
+}
+
+/// Implements deserializer methods by passing them on to the [`NodeDeserializer`] that the
+/// given method provides.
+macro_rules! forward_to_node {
+    ($with_node:ident: $($method:ident($($arg:ident: $ty:ty),*))*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value> {
+                self.$with_node(|node| node.$method($($arg,)* visitor))
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    forward_to_node! {
+        with_root:
+        deserialize_any()
+        deserialize_bool()
+        deserialize_i8()
+        deserialize_i16()
+        deserialize_i32()
+        deserialize_i64()
+        deserialize_u8()
+        deserialize_u16()
+        deserialize_u32()
+        deserialize_u64()
+        deserialize_f32()
+        deserialize_f64()
+        deserialize_char()
+        deserialize_str()
+        deserialize_string()
+        deserialize_bytes()
+        deserialize_byte_buf()
+        deserialize_option()
+        deserialize_unit()
+        deserialize_unit_struct(name: &'static str)
+        deserialize_newtype_struct(name: &'static str)
+        deserialize_seq()
+        deserialize_tuple(len: usize)
+        deserialize_tuple_struct(name: &'static str, len: usize)
+        deserialize_map()
+        deserialize_struct(name: &'static str, fields: &'static [&'static str])
+        deserialize_enum(name: &'static str, variants: &'static [&'static str])
+        deserialize_identifier()
+        deserialize_ignored_any()
+    }
+}
+
+/// Deserializes a single [`Node`].
+#[derive(Copy, Clone)]
+pub(crate) struct NodeDeserializer<'a, 'de> {
+    node: &'a Node<'de>,
+    /// Whether a [`crate::Value`] is being deserialized, in which case numbers are visited in a
+    /// way that keeps their binary types.
+    typed: bool,
+}
+
+impl<'a, 'de> NodeDeserializer<'a, 'de> {
+    pub(crate) fn new(node: &'a Node<'de>) -> Self {
+        Self { node, typed: false }
+    }
+
+    fn invalid_type<V: Visitor<'de>>(&self, visitor: V) -> Error {
+        de::Error::invalid_type(self.node.unexpected(), &visitor)
+    }
+}
+
+/// Implements integer and float deserializer methods. Besides the listed bit casts, strings are
+/// parsed so that documents that store every value as a string can still be read.
+macro_rules! deserialize_number_impl {
+    ($($ty:ident $({ $($pat:pat => $cast:expr),* })?)*) => {
+        paste::paste! {
+            $(
+                fn [<deserialize_ $ty>]<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                    let s = match self.node {
+                        $($($pat => return visitor.[<visit_ $ty>]($cast),)*)?
+                        Node::String(s) => *s,
+                        Node::WideString(s) => s.as_str(),
+                        _ => return self.deserialize_any(visitor),
+                    };
+                    match s.parse::<$ty>() {
+                        Ok(v) => visitor.[<visit_ $ty>](v),
+                        Err(_) => Err(de::Error::invalid_type(Unexpected::Str(s), &visitor)),
+                    }
+                }
+            )*
+        }
+    };
+}
+
+impl<'de> de::Deserializer<'de> for NodeDeserializer<'_, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.node {
+            Node::String(s) => visitor.visit_borrowed_str(s),
+            Node::WideString(s) => visitor.visit_str(s),
+            Node::Int32(v) if self.typed => visit_token(INT32_TOKEN, *v, visitor),
+            Node::Int32(v) => visitor.visit_i32(*v),
+            Node::Float32(v) if self.typed => visit_token(FLOAT32_TOKEN, *v, visitor),
+            Node::Float32(v) => visitor.visit_f32(*v),
+            Node::Pointer(v) => visit_token(POINTER_TOKEN, *v, visitor),
+            Node::Color(rgba) => visit_token(COLOR_TOKEN, u32::from_le_bytes(*rgba), visitor),
+            Node::UInt64(v) if self.typed => visit_token(UINT64_TOKEN, *v, visitor),
+            Node::UInt64(v) => visitor.visit_u64(*v),
+            Node::Int64(v) if self.typed => visit_token(INT64_TOKEN, *v, visitor),
+            Node::Int64(v) => visitor.visit_i64(*v),
+            Node::Object(entries) => visitor.visit_map(EntryAccess {
+                entries: entries.iter(),
+                value: None,
+            }),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.node {
+            Node::Int32(v) => visitor.visit_bool(*v != 0),
+            Node::String("1") => visitor.visit_bool(true),
+            Node::String("0") => visitor.visit_bool(false),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    deserialize_number_impl! {
+        i8 i16 i32 i64 u8 u16 f32 f64
+        // Steam stores unsigned IDs in signed fields.
+        u32 {
+            Node::Int32(v) => *v as u32,
+            Node::Pointer(v) => *v,
+            Node::Color(rgba) => u32::from_le_bytes(*rgba)
+        }
+        u64 { Node::Int64(v) => *v as u64, Node::Pointer(v) => u64::from(*v) }
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.node {
+            Node::String(s) => visitor.visit_borrowed_str(s),
+            Node::WideString(s) => visitor.visit_str(s),
+            Node::Int32(v) => visitor.visit_string(v.to_string()),
+            Node::Float32(v) => visitor.visit_string(v.to_string()),
+            Node::Pointer(v) => visitor.visit_string(v.to_string()),
+            Node::UInt64(v) => visitor.visit_string(v.to_string()),
+            Node::Int64(v) => visitor.visit_string(v.to_string()),
+            Node::Color(_) | Node::Object(_) => Err(self.invalid_type(visitor)),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::UnsupportedType("bytes".to_string()))
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::UnsupportedType("bytes".to_string()))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        // `None` is never written, so any value that is present must be `Some`.
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.node {
+            Node::String("") => visitor.visit_unit(),
+            _ => Err(self.invalid_type(visitor)),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        let typed = name == VALUE_TOKEN;
+        visitor.visit_newtype_struct(Self { typed, ..self })
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        // Sequences are represented by repeated keys, which are handled by `GroupDeserializer`.
+        Err(self.invalid_type(visitor))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let Node::Object(entries) = self.node else {
+            return Err(self.invalid_type(visitor));
+        };
+
+        visitor.visit_map(GroupAccess {
+            groups: group_entries(entries).into_iter(),
+            nodes: None,
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        // Unit variants are written as the name of the variant. Other variants are written as an
+        // object with a single key naming the variant. The key is repeated for each field of a
+        // tuple variant.
+        let groups = match self.node {
+            Node::String(s) => return visitor.visit_enum(s.into_deserializer()),
+            Node::Object(entries) => group_entries(entries),
+            _ => Vec::new(),
+        };
+        match <[_; 1]>::try_from(groups) {
+            Ok([(variant, nodes)]) => visitor.visit_enum(VariantAccess {
+                variant,
+                group: GroupDeserializer { nodes },
+            }),
+            Err(_) => Err(de::Error::invalid_value(
+                self.node.unexpected(),
+                &"a string or an object with a single key naming the variant",
+            )),
+        }
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+}
+
+/// Groups the values of repeated keys together, in the order each key first appeared.
+fn group_entries<'a, 'de>(
+    entries: &'a [(&'de str, Node<'de>)],
+) -> Vec<(&'de str, Vec<&'a Node<'de>>)> {
+    let mut groups: Vec<(&'de str, Vec<&Node<'de>>)> = Vec::with_capacity(entries.len());
+    let mut indices: HashMap<&'de str, usize> = HashMap::with_capacity(entries.len());
+    for (key, node) in entries {
+        match indices.get(key) {
+            Some(&index) => groups[index].1.push(node),
+            None => {
+                indices.insert(key, groups.len());
+                groups.push((key, vec![node]));
+            }
+        }
+    }
+    groups
+}
+
+/// Visits a pointer, color or typed number as a map with a single private key, so that
+/// [`crate::Value`] can tell it apart from other numbers.
+fn visit_token<'de, T, V>(token: &'static str, v: T, visitor: V) -> Result<V::Value>
+where
+    T: IntoDeserializer<'de, Error>,
+    V: Visitor<'de>,
+{
+    let mut map = MapDeserializer::<_, Error>::new(std::iter::once((token, v)));
+    let value = visitor.visit_map(&mut map)?;
+    map.end()?;
+    Ok(value)
+}
+
+impl<'a, 'de> IntoDeserializer<'de, Error> for NodeDeserializer<'a, 'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Visits the entries of an object in document order, without grouping repeated keys.
+struct EntryAccess<'a, 'de> {
+    entries: std::slice::Iter<'a, (&'de str, Node<'de>)>,
+    value: Option<&'a Node<'de>>,
+}
+
+impl<'a, 'de> MapAccess<'de> for EntryAccess<'a, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        let Some((key, node)) = self.entries.next() else {
+            return Ok(None);
+        };
+        self.value = Some(node);
+        seed.deserialize(KeyDeserializer { key }).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let node = self.value.take().expect("value requested before key");
+        seed.deserialize(NodeDeserializer::new(node))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// Visits the keys of an object with the values of repeated keys grouped together.
+struct GroupAccess<'a, 'de> {
+    groups: std::vec::IntoIter<(&'de str, Vec<&'a Node<'de>>)>,
+    nodes: Option<Vec<&'a Node<'de>>>,
+}
+
+impl<'a, 'de> MapAccess<'de> for GroupAccess<'a, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        let Some((key, nodes)) = self.groups.next() else {
+            return Ok(None);
+        };
+        self.nodes = Some(nodes);
+        seed.deserialize(KeyDeserializer { key }).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let nodes = self.nodes.take().expect("value requested before key");
+        seed.deserialize(GroupDeserializer { nodes })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.groups.len())
+    }
+}
+
+/// Deserializes every value of a (possibly repeated) key. Sequences receive every value, and
+/// anything else receives the last one, like the Source engine does.
+struct GroupDeserializer<'a, 'de> {
+    nodes: Vec<&'a Node<'de>>,
+}
+
+impl<'a, 'de> GroupDeserializer<'a, 'de> {
+    fn with_last<T>(self, f: impl FnOnce(NodeDeserializer<'a, 'de>) -> Result<T>) -> Result<T> {
+        let last = self.nodes.last().expect("groups are never empty");
+        f(NodeDeserializer::new(last))
+    }
+}
+
+impl<'de> de::Deserializer<'de> for GroupDeserializer<'_, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.nodes.as_slice() {
+            [node] => NodeDeserializer::new(node).deserialize_any(visitor),
+            _ => self.deserialize_seq(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.nodes.as_slice() {
+            [node] => NodeDeserializer::new(node).deserialize_newtype_struct(name, visitor),
+            _ => visitor.visit_newtype_struct(self),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let nodes = self.nodes.into_iter().map(NodeDeserializer::new);
+        let mut seq = SeqDeserializer::new(nodes);
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    forward_to_node! {
+        with_last:
+        deserialize_bool()
+        deserialize_i8()
+        deserialize_i16()
+        deserialize_i32()
+        deserialize_i64()
+        deserialize_u8()
+        deserialize_u16()
+        deserialize_u32()
+        deserialize_u64()
+        deserialize_f32()
+        deserialize_f64()
+        deserialize_char()
+        deserialize_str()
+        deserialize_string()
+        deserialize_bytes()
+        deserialize_byte_buf()
+        deserialize_unit()
+        deserialize_unit_struct(name: &'static str)
+        deserialize_map()
+        deserialize_struct(name: &'static str, fields: &'static [&'static str])
+        deserialize_enum(name: &'static str, variants: &'static [&'static str])
+        deserialize_identifier()
+    }
+}
+
+/// Deserializes an object key, parsing it if a number or `bool` is expected.
+struct KeyDeserializer<'de> {
+    key: &'de str,
+}
+
+macro_rules! deserialize_key_impl {
+    ($($ty:ident)*) => {
+        paste::paste! {
+            $(
+                fn [<deserialize_ $ty>]<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                    match self.key.parse::<$ty>() {
+                        Ok(v) => visitor.[<visit_ $ty>](v),
+                        Err(_) => Err(de::Error::invalid_type(Unexpected::Str(self.key), &visitor)),
+                    }
+                }
+            )*
+        }
+    };
+}
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_borrowed_str(self.key)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.key {
+            "1" => visitor.visit_bool(true),
+            "0" => visitor.visit_bool(false),
+            _ => Err(de::Error::invalid_type(Unexpected::Str(self.key), &visitor)),
+        }
+    }
+
+    deserialize_key_impl!(i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char);
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_enum(self.key.into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+/// Deserializes the variant of an enum that is represented by an object with a single key.
+struct VariantAccess<'a, 'de> {
+    variant: &'de str,
+    /// Every value of the key naming the variant.
+    group: GroupDeserializer<'a, 'de>,
+}
+
+impl<'a, 'de> EnumAccess<'de> for VariantAccess<'a, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let variant = seed.deserialize(KeyDeserializer { key: self.variant })?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess<'_, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        de::Deserialize::deserialize(self.group)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self.group)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_tuple(self.group, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_map(self.group, visitor)
+    }
+}
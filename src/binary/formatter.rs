@@ -0,0 +1,200 @@
+use super::{
+    TYPE_COLOR, TYPE_END, TYPE_FLOAT32, TYPE_INT32, TYPE_INT64, TYPE_OBJECT, TYPE_POINTER,
+    TYPE_STRING, TYPE_UINT64,
+};
+use crate::ser::Formatter;
+use std::io::{self, Write};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum State {
+    /// Between key-value pairs.
+    Idle,
+    /// Between `begin_key` and `begin_value`.
+    Key,
+    /// Between `begin_value` and `end_value`.
+    Value,
+}
+
+/// A [`Formatter`] that writes binary KeyValues.
+///
+/// Since the type of an entry is written before its key, the key is held back until its value
+/// arrives. Numbers are written with their binary types instead of as strings. Comments are
+/// skipped, and conditional tags are rejected since binary KeyValues cannot represent them.
+///
+/// Binary KeyValues has no 64-bit float type, so `f64`s are narrowed to 32-bit floats and lose
+/// any precision that does not fit. For example, `0.1f64` is read back as
+/// `0.10000000149011612`.
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use vdflex::binary::BinaryFormatter;
+/// use vdflex::ser::Serializer;
+/// use serde::Serialize;
+///
+/// let mut bytes = Vec::new();
+/// let mut serializer = Serializer::new(&mut bytes, BinaryFormatter::new());
+/// BTreeMap::from([("size", 4u64)]).serialize(&mut serializer).unwrap();
+/// assert_eq!(bytes, b"\x07size\0\x04\0\0\0\0\0\0\0\x08");
+/// ```
+#[derive(Clone, Debug)]
+pub struct BinaryFormatter {
+    state: State,
+    key: Vec<u8>,
+    depth: usize,
+}
+
+impl BinaryFormatter {
+    /// Creates a new [`BinaryFormatter`].
+    pub fn new() -> Self {
+        Self {
+            state: State::Idle,
+            key: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    /// Writes the type and key of an entry, followed by `value`.
+    fn write_entry<W: ?Sized + Write>(
+        &mut self,
+        writer: &mut W,
+        ty: u8,
+        value: &[u8],
+    ) -> io::Result<()> {
+        if self.state != State::Value {
+            return Err(invalid("values must belong to a key"));
+        }
+        writer.write_all(&[ty])?;
+        writer.write_all(&self.key)?;
+        writer.write_all(&[0])?;
+        writer.write_all(value)
+    }
+}
+
+impl Default for BinaryFormatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn invalid(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+fn c_string(s: &str) -> io::Result<&[u8]> {
+    if s.contains('\0') {
+        Err(invalid(
+            "binary KeyValues strings cannot contain null characters",
+        ))
+    } else {
+        Ok(s.as_bytes())
+    }
+}
+
+impl Formatter for BinaryFormatter {
+    fn begin_object<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        match self.state {
+            State::Value => self.write_entry(writer, TYPE_OBJECT, &[])?,
+            State::Idle if self.depth == 0 => {}
+            _ => return Err(invalid("objects must belong to a key")),
+        }
+        self.state = State::Idle;
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn end_object<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.depth = self
+            .depth
+            .checked_sub(1)
+            .ok_or_else(|| invalid("unbalanced end_object"))?;
+        // The entry that holds this object ends with it.
+        self.state = State::Value;
+        writer.write_all(&[TYPE_END])
+    }
+
+    fn begin_key<W: ?Sized + Write>(&mut self, _writer: &mut W) -> io::Result<()> {
+        self.state = State::Key;
+        self.key.clear();
+        Ok(())
+    }
+
+    fn end_key<W: ?Sized + Write>(&mut self, _writer: &mut W) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn begin_value<W: ?Sized + Write>(&mut self, _writer: &mut W) -> io::Result<()> {
+        self.state = State::Value;
+        Ok(())
+    }
+
+    fn end_value<W: ?Sized + Write>(&mut self, _writer: &mut W) -> io::Result<()> {
+        self.state = State::Idle;
+        Ok(())
+    }
+
+    fn write_string<W: ?Sized + Write>(&mut self, writer: &mut W, s: &str) -> io::Result<()> {
+        let s = c_string(s)?;
+        if self.state == State::Key {
+            self.key.extend_from_slice(s);
+            return Ok(());
+        }
+
+        let mut value = Vec::with_capacity(s.len() + 1);
+        value.extend_from_slice(s);
+        value.push(0);
+        self.write_entry(writer, TYPE_STRING, &value)
+    }
+
+    fn write_bool<W: ?Sized + Write>(&mut self, writer: &mut W, v: bool) -> io::Result<()> {
+        self.write_i32(writer, i32::from(v))
+    }
+
+    fn write_i32<W: ?Sized + Write>(&mut self, writer: &mut W, v: i32) -> io::Result<()> {
+        self.write_entry(writer, TYPE_INT32, &v.to_le_bytes())
+    }
+
+    fn write_u32<W: ?Sized + Write>(&mut self, writer: &mut W, v: u32) -> io::Result<()> {
+        self.write_entry(writer, TYPE_INT32, &v.to_le_bytes())
+    }
+
+    fn write_i64<W: ?Sized + Write>(&mut self, writer: &mut W, v: i64) -> io::Result<()> {
+        self.write_entry(writer, TYPE_INT64, &v.to_le_bytes())
+    }
+
+    fn write_u64<W: ?Sized + Write>(&mut self, writer: &mut W, v: u64) -> io::Result<()> {
+        self.write_entry(writer, TYPE_UINT64, &v.to_le_bytes())
+    }
+
+    fn write_f32<W: ?Sized + Write>(&mut self, writer: &mut W, v: f32) -> io::Result<()> {
+        self.write_entry(writer, TYPE_FLOAT32, &v.to_le_bytes())
+    }
+
+    fn write_f64<W: ?Sized + Write>(&mut self, writer: &mut W, v: f64) -> io::Result<()> {
+        // Lossy on purpose; see the documentation of `BinaryFormatter`.
+        self.write_f32(writer, v as f32)
+    }
+
+    fn write_pointer<W: ?Sized + Write>(&mut self, writer: &mut W, v: u32) -> io::Result<()> {
+        self.write_entry(writer, TYPE_POINTER, &v.to_le_bytes())
+    }
+
+    fn write_color<W: ?Sized + Write>(&mut self, writer: &mut W, rgba: [u8; 4]) -> io::Result<()> {
+        self.write_entry(writer, TYPE_COLOR, &rgba)
+    }
+
+    fn write_conditional<W: ?Sized + Write>(
+        &mut self,
+        _writer: &mut W,
+        _condition: &str,
+    ) -> io::Result<()> {
+        Err(invalid("binary KeyValues cannot contain conditional tags"))
+    }
+
+    fn write_line_comment<W: ?Sized + Write>(
+        &mut self,
+        _writer: &mut W,
+        _comment: &str,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+}
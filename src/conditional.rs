@@ -0,0 +1,376 @@
+//! Parse and evaluate conditional tags.
+//!
+//! Entries in KeyValues files may be followed by a conditional tag such as `[$WIN32]` or
+//! `[!$X360 && ($OSX || $LINUX)]`, which causes the entry to be ignored unless the condition is
+//! true. A [`Condition`] is an expression of symbols combined with `!`, `&&`, `||` and
+//! parentheses. `!` binds tightest, followed by `&&` and then `||`.
+//!
+//! ```
+//! use vdflex::conditional::Condition;
+//!
+//! let condition: Condition = "!$X360 && ($OSX || $LINUX)".parse().unwrap();
+//! assert!(condition.evaluate(|symbol| symbol == "$LINUX"));
+//! assert!(!condition.evaluate(|symbol| symbol == "$WIN32"));
+//! ```
+//!
+//! To filter entries while deserializing, see [`DeserializeOpts::symbols`].
+//!
+//! [`DeserializeOpts::symbols`]: crate::de::DeserializeOpts::symbols
+
+use crate::de::MAX_DEPTH;
+use crate::{Error, Result};
+use std::fmt;
+use std::iter::Peekable;
+use std::str::{CharIndices, FromStr};
+
+/// A parsed conditional tag, without its brackets.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Condition {
+    /// A symbol such as `$WIN32`, which is true if the symbol is defined.
+    Symbol(String),
+    /// `!condition`
+    Not(Box<Condition>),
+    /// `left && right`
+    And(Box<Condition>, Box<Condition>),
+    /// `left || right`
+    Or(Box<Condition>, Box<Condition>),
+}
+
+impl Condition {
+    /// Evaluates the condition, using `is_defined` to determine if each symbol is defined.
+    pub fn evaluate<F: Fn(&str) -> bool>(&self, is_defined: F) -> bool {
+        self.evaluate_dyn(&is_defined)
+    }
+
+    fn evaluate_dyn(&self, is_defined: &dyn Fn(&str) -> bool) -> bool {
+        match self {
+            Condition::Symbol(symbol) => is_defined(symbol),
+            Condition::Not(condition) => !condition.evaluate_dyn(is_defined),
+            Condition::And(left, right) => {
+                left.evaluate_dyn(is_defined) && right.evaluate_dyn(is_defined)
+            }
+            Condition::Or(left, right) => {
+                left.evaluate_dyn(is_defined) || right.evaluate_dyn(is_defined)
+            }
+        }
+    }
+
+    /// Returns how tightly this condition binds, for deciding where parentheses are needed.
+    fn precedence(&self) -> u8 {
+        match self {
+            Condition::Or(..) => 0,
+            Condition::And(..) => 1,
+            Condition::Not(_) | Condition::Symbol(_) => 2,
+        }
+    }
+
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, precedence: u8) -> fmt::Result {
+        if self.precedence() < precedence {
+            write!(f, "({self})")
+        } else {
+            write!(f, "{self}")
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::Symbol(symbol) => f.write_str(symbol),
+            Condition::Not(condition) => {
+                f.write_str("!")?;
+                condition.fmt_operand(f, 2)
+            }
+            Condition::And(left, right) => {
+                left.fmt_operand(f, 1)?;
+                f.write_str(" && ")?;
+                right.fmt_operand(f, 2)
+            }
+            Condition::Or(left, right) => {
+                left.fmt_operand(f, 0)?;
+                f.write_str(" || ")?;
+                right.fmt_operand(f, 1)
+            }
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = Error;
+
+    /// Parses a condition. Surrounding brackets are optional, so both `$WIN32` and `[$WIN32]`
+    /// are accepted.
+    fn from_str(s: &str) -> Result<Self> {
+        let expr = match s.trim().strip_prefix('[') {
+            Some(rest) => rest.strip_suffix(']').unwrap_or(rest),
+            None => s,
+        };
+
+        let mut parser = Parser {
+            source: s,
+            expr,
+            chars: expr.char_indices().peekable(),
+            depth: 0,
+        };
+        let (condition, _) = parser.or()?;
+        match parser.peek() {
+            None => Ok(condition),
+            Some(')') => Err(parser.error("unmatched `)`")),
+            Some(_) => Err(parser.error("expected `&&` or `||`")),
+        }
+    }
+}
+
+/// A parsed condition and the height of its tree.
+type Parsed = (Condition, usize);
+
+/// A recursive descent parser for conditions.
+///
+/// Parentheses and the height of the parsed tree are both limited to [`MAX_DEPTH`], so that
+/// neither parsing nor evaluating, formatting or dropping the condition can overflow the stack.
+struct Parser<'a> {
+    source: &'a str,
+    expr: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    /// The number of parentheses that are currently open.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    // or := and ('||' and)*
+    fn or(&mut self) -> Result<Parsed> {
+        let mut condition = self.and()?;
+        while self.eat("||")? {
+            let right = self.and()?;
+            condition = self.combine(Condition::Or, condition, right)?;
+        }
+        Ok(condition)
+    }
+
+    // and := not ('&&' not)*
+    fn and(&mut self) -> Result<Parsed> {
+        let mut condition = self.not()?;
+        while self.eat("&&")? {
+            let right = self.not()?;
+            condition = self.combine(Condition::And, condition, right)?;
+        }
+        Ok(condition)
+    }
+
+    // not := '!'* ('(' or ')' | symbol)
+    fn not(&mut self) -> Result<Parsed> {
+        let mut nots = 0;
+        while self.peek() == Some('!') {
+            self.chars.next();
+            nots += 1;
+        }
+
+        let (condition, height) = self.operand()?;
+        let height = self.check_height(height + nots)?;
+        let condition = (0..nots).fold(condition, |condition, _| {
+            Condition::Not(Box::new(condition))
+        });
+        Ok((condition, height))
+    }
+
+    fn operand(&mut self) -> Result<Parsed> {
+        match self.peek() {
+            Some('(') => {
+                self.chars.next();
+                if self.depth == MAX_DEPTH {
+                    return Err(self.error("nested too deeply"));
+                }
+                self.depth += 1;
+                let condition = self.or()?;
+                self.depth -= 1;
+                match self.peek() {
+                    Some(')') => {
+                        self.chars.next();
+                        Ok(condition)
+                    }
+                    _ => Err(self.error("expected `)`")),
+                }
+            }
+            Some(c) if is_symbol_char(c) => {
+                let (start, _) = *self.chars.peek().expect("peeked a symbol");
+                let mut end = self.expr.len();
+                while let Some(&(i, c)) = self.chars.peek() {
+                    if !is_symbol_char(c) {
+                        end = i;
+                        break;
+                    }
+                    self.chars.next();
+                }
+                Ok((Condition::Symbol(self.expr[start..end].to_string()), 1))
+            }
+            _ => Err(self.error("expected a symbol")),
+        }
+    }
+
+    /// Joins two conditions with a binary operator.
+    fn combine(
+        &self,
+        op: fn(Box<Condition>, Box<Condition>) -> Condition,
+        (left, left_height): Parsed,
+        (right, right_height): Parsed,
+    ) -> Result<Parsed> {
+        let height = self.check_height(left_height.max(right_height) + 1)?;
+        Ok((op(Box::new(left), Box::new(right)), height))
+    }
+
+    fn check_height(&self, height: usize) -> Result<usize> {
+        if height > MAX_DEPTH {
+            Err(self.error("nested too deeply"))
+        } else {
+            Ok(height)
+        }
+    }
+
+    /// Consumes the two-character operator `op` if it comes next.
+    fn eat(&mut self, op: &str) -> Result<bool> {
+        let first = op.chars().next().expect("operators are not empty");
+        if self.peek() != Some(first) {
+            return Ok(false);
+        }
+        self.chars.next();
+        match self.chars.next() {
+            Some((_, c)) if c == first => Ok(true),
+            _ => Err(self.error(if first == '&' {
+                "expected `&&`"
+            } else {
+                "expected `||`"
+            })),
+        }
+    }
+
+    /// Returns the next character that is not whitespace.
+    fn peek(&mut self) -> Option<char> {
+        while let Some(&(_, c)) = self.chars.peek() {
+            if !c.is_whitespace() {
+                return Some(c);
+            }
+            self.chars.next();
+        }
+        None
+    }
+
+    fn error(&self, reason: &'static str) -> Error {
+        Error::InvalidCondition {
+            condition: self.source.to_string(),
+            reason,
+        }
+    }
+}
+
+fn is_symbol_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '!' | '&' | '|' | '(' | ')' | '[' | ']')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(s: &str) -> Box<Condition> {
+        Box::new(Condition::Symbol(s.to_string()))
+    }
+
+    #[test]
+    fn parse() -> Result<()> {
+        assert_eq!("$WIN32".parse::<Condition>()?, *symbol("$WIN32"));
+        assert_eq!("[$WIN32]".parse::<Condition>()?, *symbol("$WIN32"));
+        assert!(format!("{}$A", "!".repeat(127))
+            .parse::<Condition>()
+            .is_ok());
+        assert!(format!("{}$A{}", "(".repeat(128), ")".repeat(128))
+            .parse::<Condition>()
+            .is_ok());
+        assert_eq!(
+            "!$X360 && $OSX".parse::<Condition>()?,
+            Condition::And(Box::new(Condition::Not(symbol("$X360"))), symbol("$OSX"))
+        );
+        assert_eq!(
+            "$A || $B && !$C".parse::<Condition>()?,
+            Condition::Or(
+                symbol("$A"),
+                Box::new(Condition::And(
+                    symbol("$B"),
+                    Box::new(Condition::Not(symbol("$C")))
+                ))
+            )
+        );
+        assert_eq!(
+            "!($A||$B)&&$C".parse::<Condition>()?,
+            Condition::And(
+                Box::new(Condition::Not(Box::new(Condition::Or(
+                    symbol("$A"),
+                    symbol("$B")
+                )))),
+                symbol("$C")
+            )
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_errors() {
+        for (input, reason) in [
+            ("", "expected a symbol"),
+            ("$A &&", "expected a symbol"),
+            ("$A & $B", "expected `&&`"),
+            ("$A | $B", "expected `||`"),
+            ("($A || $B", "expected `)`"),
+            ("$A)", "unmatched `)`"),
+            ("$A $B", "expected `&&` or `||`"),
+            (&format!("{}$A", "!".repeat(200_000)), "nested too deeply"),
+            (
+                &format!("{}$A{}", "(".repeat(200), ")".repeat(200)),
+                "nested too deeply",
+            ),
+            (&"$A && ".repeat(200_000)[..], "nested too deeply"),
+        ] {
+            match input.parse::<Condition>() {
+                Err(Error::InvalidCondition { reason: actual, .. }) => {
+                    assert_eq!(actual, reason, "{input:?}")
+                }
+                other => panic!("expected error for {input:?}, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn display() -> Result<()> {
+        for input in [
+            "$WIN32",
+            "!$X360 && $OSX",
+            "$LINUX || $POSIX",
+            "$A || $B && !$C",
+            "($A || $B) && $C",
+            "!($A && $B)",
+            "$A && ($B || $C)",
+        ] {
+            assert_eq!(input.parse::<Condition>()?.to_string(), input);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn evaluate() -> Result<()> {
+        let defined = |symbol: &str| matches!(symbol, "$WIN32" | "$WINDOWS");
+
+        let cases = [
+            ("$WIN32", true),
+            ("$OSX", false),
+            ("!$X360", true),
+            ("$WIN32 && $OSX", false),
+            ("$LINUX || $WINDOWS", true),
+            ("!($OSX || $LINUX) && $WIN32", true),
+            ("!!$WIN32", true),
+        ];
+        for (input, expected) in cases {
+            let condition: Condition = input.parse()?;
+            assert_eq!(condition.evaluate(defined), expected, "{input}");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,809 @@
+//! Edit KeyValues files without changing their formatting.
+//!
+//! A [`Document`] is a concrete syntax tree: it keeps every character of the input, including
+//! comments, quoting, indentation, brace style, conditional tags and blank lines. Writing an
+//! unmodified document produces exactly the text it was parsed from, and edits only change the
+//! entries they touch.
+//!
+//! ```
+//! use vdflex::cst::Document;
+//!
+//! let input = "\"AppState\"\n{\n\t\"appid\"\t\t\"440\"\n\t// 4 = fully installed\n\t\"StateFlags\"\t\t\"4\"\n}\n";
+//! let mut doc: Document = input.parse().unwrap();
+//! assert_eq!(doc.to_string(), input);
+//!
+//! doc.find_mut("AppState.StateFlags").unwrap().set_value("6");
+//! let state = doc.find_mut("AppState").unwrap().as_block_mut().unwrap();
+//! state.insert("AutoUpdateBehavior", "1");
+//! assert_eq!(
+//!     doc.to_string(),
+//!     "\"AppState\"\n{\n\t\"appid\"\t\t\"440\"\n\t// 4 = fully installed\n\t\"StateFlags\"\t\t\"6\"\n\t\"AutoUpdateBehavior\"\t\t\"1\"\n}\n"
+//! );
+//! ```
+//!
+//! New entries are indented like their siblings. Their quoting, the separator between their key
+//! and value and the placement of their braces follow the first entries of the document, or a
+//! [`FormatOpts`] passed to [`Document::set_format`].
+
+use crate::de::{parse_segment, Event, Reader, MAX_DEPTH};
+use crate::lex::{self, Span, TokenKind};
+use crate::ser::{write_string_element, BraceStyle, FormatOpts, Quoting};
+use crate::{Error, Result};
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
+
+/// A KeyValues document that remembers its exact formatting.
+///
+/// A document dereferences to its root [`Block`], which holds the root-level entries.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Document {
+    root: Block,
+}
+
+impl Document {
+    /// Parses a document, keeping all of its formatting.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `s` is not valid KeyValues or if objects are nested too deeply. Conditional tags
+    /// are kept but not evaluated.
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut parser = Parser {
+            input: s,
+            events: Reader::new(s),
+            peeked: None,
+            pos: 0,
+            depth: 0,
+        };
+        let mut root = parser.block(true)?;
+
+        let defaults = FormatOpts::default();
+        let unit = infer_unit(&root, "")
+            .unwrap_or(&defaults.indent)
+            .to_string();
+        layout(&mut root, "", &unit);
+        let style = Style::infer(&root, unit);
+        root.set_style(&style);
+        Ok(Self { root })
+    }
+
+    /// Formats entries that are added from now on according to `opts`, instead of the style
+    /// found in the document. New entries are still indented like their siblings, if they have
+    /// any.
+    pub fn set_format(&mut self, opts: &FormatOpts) {
+        let style = Style::from(opts);
+        relayout(&mut self.root, "", &style.indent);
+        self.root.set_style(&style);
+    }
+}
+
+impl FromStr for Document {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl Deref for Document {
+    type Target = Block;
+
+    fn deref(&self) -> &Block {
+        &self.root
+    }
+}
+
+impl DerefMut for Document {
+    fn deref_mut(&mut self) -> &mut Block {
+        &mut self.root
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.root.fmt(f)
+    }
+}
+
+/// The entries of the root of a [`Document`] or of an object.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    entries: Vec<Entry>,
+    /// Everything after the last entry, up to the closing brace or the end of the input.
+    trailing: String,
+    /// The indentation of new entries if there are no others to copy it from.
+    indent: String,
+    /// The indentation of the line that contains the closing brace.
+    outer: String,
+    root: bool,
+    style: Style,
+}
+
+impl Block {
+    /// Returns the number of entries in this block.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if this block has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns an iterator over the entries of this block, in document order.
+    pub fn entries(&self) -> std::slice::Iter<'_, Entry> {
+        self.entries.iter()
+    }
+
+    /// Returns an iterator that allows modifying each entry of this block.
+    pub fn entries_mut(&mut self) -> std::slice::IterMut<'_, Entry> {
+        self.entries.iter_mut()
+    }
+
+    /// Returns the first entry with the given key.
+    pub fn get(&self, key: &str) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.key() == key)
+    }
+
+    /// Returns the first entry with the given key.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Entry> {
+        self.entries.iter_mut().find(|entry| entry.key() == key)
+    }
+
+    /// Returns the entry at `path`, which uses the same syntax as
+    /// [`from_str_at`](crate::de::from_str_at), such as `AppState.UserConfig.language` or
+    /// `Depot.FileMapping[1]`.
+    pub fn find(&self, path: &str) -> Option<&Entry> {
+        let mut segments = path.split('.');
+        let mut entry = self.nth(segments.next()?)?;
+        for segment in segments {
+            entry = entry.as_block()?.nth(segment)?;
+        }
+        Some(entry)
+    }
+
+    /// Returns the entry at `path`. See [`Block::find`].
+    pub fn find_mut(&mut self, path: &str) -> Option<&mut Entry> {
+        let mut segments = path.split('.');
+        let mut entry = self.nth_mut(segments.next()?)?;
+        for segment in segments {
+            entry = entry.as_block_mut()?.nth_mut(segment)?;
+        }
+        Some(entry)
+    }
+
+    fn nth(&self, segment: &str) -> Option<&Entry> {
+        let (key, index) = parse_segment(segment);
+        self.entries
+            .iter()
+            .filter(|entry| entry.key() == key)
+            .nth(index)
+    }
+
+    fn nth_mut(&mut self, segment: &str) -> Option<&mut Entry> {
+        let (key, index) = parse_segment(segment);
+        self.entries
+            .iter_mut()
+            .filter(|entry| entry.key() == key)
+            .nth(index)
+    }
+
+    /// Adds an entry with a string value after the last entry of this block.
+    pub fn insert(&mut self, key: &str, value: &str) -> &mut Entry {
+        let value = Node::String(encode(value, self.style.quote_values));
+        let middle = self.style.separator.clone();
+        self.push(key, middle, value)
+    }
+
+    /// Adds an entry with an empty object value after the last entry of this block, and returns
+    /// the object so that entries can be added to it.
+    pub fn insert_block(&mut self, key: &str) -> &mut Block {
+        let middle = match self.style.brace_style {
+            BraceStyle::Allman => format!("\n{}", self.line_indent()),
+            BraceStyle::KAndR => String::from(" "),
+        };
+        let outer = self.line_indent().to_string();
+        let block = Block {
+            entries: Vec::new(),
+            trailing: format!("\n{outer}"),
+            indent: format!("{outer}{}", self.style.indent),
+            outer,
+            root: false,
+            style: self.style.clone(),
+        };
+        match &mut self.push(key, middle, Node::Block(block)).value {
+            Node::Block(block) => block,
+            Node::String(_) => unreachable!("inserted a block"),
+        }
+    }
+
+    /// Removes the first entry with the given key, together with the comments and blank lines
+    /// before it.
+    pub fn remove(&mut self, key: &str) -> Option<Entry> {
+        let index = self.entries.iter().position(|entry| entry.key() == key)?;
+        let mut removed = self.entries.remove(index);
+        if index == 0 {
+            if let Some(next) = self.entries.first_mut() {
+                // Keep the whitespace at the start of the block rather than the whitespace that
+                // separated the two entries.
+                if removed.leading.trim().is_empty() && next.leading.trim().is_empty() {
+                    std::mem::swap(&mut removed.leading, &mut next.leading);
+                }
+            }
+        }
+        Some(removed)
+    }
+
+    /// Returns the indentation of the line that the entries of this block start on.
+    fn line_indent(&self) -> &str {
+        self.entries
+            .iter()
+            .rev()
+            .find_map(|entry| Some(&entry.leading[entry.leading.rfind('\n')? + 1..]))
+            .unwrap_or(&self.indent)
+    }
+
+    fn push(&mut self, key: &str, middle: String, value: Node) -> &mut Entry {
+        let leading = match self.entries.last() {
+            Some(last) if !last.leading.is_empty() => {
+                match last.leading.rfind('\n') {
+                    Some(i) => format!("\n{}", &last.leading[i + 1..]),
+                    // Entries on a single line, such as `{ "x" "1" "y" "2" }`.
+                    None => last.leading.clone(),
+                }
+            }
+            Some(_) => format!("\n{}", self.indent),
+            None => {
+                // Put the entry before the closing brace, after any comments.
+                let (before, after) = match self.trailing.rfind('\n') {
+                    Some(i) => self.trailing.split_at(i),
+                    None if self.root => (self.trailing.trim_end(), "\n"),
+                    None => ("", ""),
+                };
+                let leading = match before {
+                    "" if self.root => String::new(),
+                    before => format!("{before}\n{}", self.indent),
+                };
+                self.trailing = match after {
+                    "" => format!("\n{}", self.outer),
+                    after => after.to_string(),
+                };
+                leading
+            }
+        };
+
+        self.entries.push(Entry {
+            leading,
+            key: encode(key, self.style.quote_keys),
+            middle,
+            value,
+            trailing: String::new(),
+        });
+        self.entries.last_mut().expect("pushed an entry")
+    }
+
+    fn set_style(&mut self, style: &Style) {
+        self.style = style.clone();
+        for entry in &mut self.entries {
+            if let Node::Block(block) = &mut entry.value {
+                block.set_style(style);
+            }
+        }
+    }
+}
+
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            entry.fmt(f)?;
+        }
+        f.write_str(&self.trailing)
+    }
+}
+
+/// A key-value pair in a [`Block`], together with the comments and whitespace before it and
+/// anything that follows its value on the same line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    /// Whitespace and comments before the key.
+    leading: String,
+    /// The key exactly as written, including quotes.
+    key: String,
+    /// Everything between the key and the value.
+    middle: String,
+    value: Node,
+    /// Conditional tags and comments after the value.
+    trailing: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    /// A string value exactly as written, including quotes.
+    String(String),
+    Block(Block),
+}
+
+impl Entry {
+    /// Returns the key of this entry, with quotes removed and escape sequences processed.
+    pub fn key(&self) -> Cow<'_, str> {
+        decode(&self.key)
+    }
+
+    /// Returns the value of this entry if it is a string, with quotes removed and escape
+    /// sequences processed.
+    pub fn as_str(&self) -> Option<Cow<'_, str>> {
+        match &self.value {
+            Node::String(raw) => Some(decode(raw)),
+            Node::Block(_) => None,
+        }
+    }
+
+    /// Returns the value of this entry if it is an object.
+    pub fn as_block(&self) -> Option<&Block> {
+        match &self.value {
+            Node::Block(block) => Some(block),
+            Node::String(_) => None,
+        }
+    }
+
+    /// Returns the value of this entry if it is an object.
+    pub fn as_block_mut(&mut self) -> Option<&mut Block> {
+        match &mut self.value {
+            Node::Block(block) => Some(block),
+            Node::String(_) => None,
+        }
+    }
+
+    /// Returns the text of the conditional tag of this entry without brackets, such as
+    /// `$WIN32`.
+    pub fn condition(&self) -> Option<&str> {
+        lex::Lexer::new(&self.middle)
+            .chain(lex::Lexer::new(&self.trailing))
+            .find(|token| token.kind == TokenKind::Conditional)
+            .map(|token| &token.text[1..token.text.len() - 1])
+    }
+
+    /// Renames this entry, keeping its quoting style.
+    pub fn set_key(&mut self, key: &str) {
+        self.key = encode(key, quoting(&self.key));
+    }
+
+    /// Replaces the value of this entry with a string, keeping its quoting style.
+    ///
+    /// If the value was an object, it is quoted like the key and put on the same line.
+    pub fn set_value(&mut self, value: &str) {
+        let quoting = match &self.value {
+            Node::String(raw) => quoting(raw),
+            Node::Block(_) => {
+                if self.middle.contains('\n') {
+                    self.middle = String::from(" ");
+                }
+                quoting(&self.key)
+            }
+        };
+        self.value = Node::String(encode(value, quoting));
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.leading)?;
+        f.write_str(&self.key)?;
+        f.write_str(&self.middle)?;
+        match &self.value {
+            Node::String(raw) => f.write_str(raw)?,
+            Node::Block(block) => write!(f, "{{{block}}}")?,
+        }
+        f.write_str(&self.trailing)
+    }
+}
+
+/// How new entries are formatted.
+#[derive(Clone, Debug, PartialEq)]
+struct Style {
+    indent: String,
+    separator: String,
+    brace_style: BraceStyle,
+    quote_keys: Quoting,
+    quote_values: Quoting,
+}
+
+impl Style {
+    /// Copies the style of the first entries of a document.
+    fn infer(root: &Block, indent: String) -> Self {
+        let mut style = Style {
+            indent,
+            ..Style::from(&FormatOpts::default())
+        };
+
+        // Directives are usually written differently from the entries around them.
+        let mut entries = Vec::new();
+        collect(root, &mut entries);
+        entries.retain(|entry| !lex::is_directive(&entry.key()));
+        if let Some(entry) = entries.first() {
+            style.quote_keys = quoting(&entry.key);
+        }
+        if let Some(entry) = entries
+            .iter()
+            .find(|entry| matches!(entry.value, Node::String(_)) && is_separator(&entry.middle))
+        {
+            style.separator = entry.middle.clone();
+            if let Node::String(raw) = &entry.value {
+                style.quote_values = quoting(raw);
+            }
+        }
+        if let Some(entry) = entries
+            .iter()
+            .find(|entry| matches!(entry.value, Node::Block(_)))
+        {
+            style.brace_style = if entry.middle.contains('\n') {
+                BraceStyle::Allman
+            } else {
+                BraceStyle::KAndR
+            };
+        }
+        style
+    }
+}
+
+impl From<&FormatOpts> for Style {
+    fn from(opts: &FormatOpts) -> Self {
+        Style {
+            indent: opts.indent.clone(),
+            separator: opts.separator.clone(),
+            brace_style: opts.brace_style,
+            quote_keys: opts.quote_keys,
+            quote_values: opts.quote_values,
+        }
+    }
+}
+
+/// Collects the entries of a block and its children, in document order.
+fn collect<'a>(block: &'a Block, entries: &mut Vec<&'a Entry>) {
+    for entry in &block.entries {
+        entries.push(entry);
+        if let Node::Block(child) = &entry.value {
+            collect(child, entries);
+        }
+    }
+}
+
+fn is_separator(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c == ' ' || c == '\t')
+}
+
+/// Returns the indentation of the first entry of `block` that starts on its own line.
+fn first_indent(block: &Block) -> Option<&str> {
+    block
+        .entries
+        .iter()
+        .find_map(|entry| Some(&entry.leading[entry.leading.rfind('\n')? + 1..]))
+}
+
+/// Finds the indentation that is added for each level of nesting, by comparing the first nested
+/// object whose entries are on their own lines with its parent.
+fn infer_unit<'a>(block: &'a Block, indent: &str) -> Option<&'a str> {
+    block.entries.iter().find_map(|entry| {
+        let Node::Block(child) = &entry.value else {
+            return None;
+        };
+        match first_indent(child) {
+            Some(inner) if inner.len() > indent.len() && inner.starts_with(indent) => {
+                Some(&inner[indent.len()..])
+            }
+            Some(inner) => infer_unit(child, inner),
+            None => infer_unit(child, indent),
+        }
+    })
+}
+
+/// Records the indentation of each block, which is used when a new entry has no siblings.
+fn layout(block: &mut Block, outer: &str, unit: &str) {
+    block.outer = outer.to_string();
+    block.indent = match first_indent(block) {
+        Some(indent) => indent.to_string(),
+        None if block.root => String::new(),
+        None => format!("{outer}{unit}"),
+    };
+    let indent = block.indent.clone();
+    for entry in &mut block.entries {
+        if let Node::Block(child) = &mut entry.value {
+            layout(child, &indent, unit);
+        }
+    }
+}
+
+/// Like [`layout`], but only changes blocks without entries.
+fn relayout(block: &mut Block, outer: &str, unit: &str) {
+    if block.entries.is_empty() && !block.root {
+        block.indent = format!("{outer}{unit}");
+    }
+    let indent = block.indent.clone();
+    for entry in &mut block.entries {
+        if let Node::Block(child) = &mut entry.value {
+            relayout(child, &indent, unit);
+        }
+    }
+}
+
+/// Returns how a string that was written as `raw` is quoted.
+fn quoting(raw: &str) -> Quoting {
+    if raw.starts_with('"') {
+        Quoting::Always
+    } else {
+        Quoting::WhenRequired
+    }
+}
+
+fn decode(raw: &str) -> Cow<'_, str> {
+    match raw.strip_prefix('"') {
+        Some(inner) => lex::unescape(inner.strip_suffix('"').unwrap_or(inner)),
+        None => lex::unescape(raw),
+    }
+}
+
+fn encode(s: &str, quoting: Quoting) -> String {
+    let mut buf = Vec::with_capacity(s.len() + 2);
+    write_string_element(&mut buf, s, quoting).expect("writing to a Vec cannot fail");
+    String::from_utf8(buf).expect("escaping keeps the string valid")
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    events: Reader<'a>,
+    peeked: Option<(Event<'a>, Span)>,
+    /// The offset of the first character that is not part of the tree yet.
+    pos: usize,
+    /// The number of objects around the current block.
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&mut self) -> Result<Option<&(Event<'a>, Span)>> {
+        if self.peeked.is_none() {
+            self.peeked = self.events.next().transpose()?;
+        }
+        Ok(self.peeked.as_ref())
+    }
+
+    fn bump(&mut self) -> Result<(Event<'a>, Span)> {
+        self.peek()?;
+        Ok(self.peeked.take().expect("peeked an event"))
+    }
+
+    /// Reads entries up to the end of the input or of the current object. The reader has
+    /// already checked the structure, so only keys and directives start entries.
+    fn block(&mut self, root: bool) -> Result<Block> {
+        let mut entries = Vec::new();
+        let end = loop {
+            match self.peek()? {
+                None => break self.input.len(),
+                Some((Event::EndObject, span)) => break span.start.offset,
+                Some((Event::Key(_) | Event::Directive { .. }, _)) => entries.push(self.entry()?),
+                // Stray comments and conditionals are kept as part of the whitespace.
+                Some(_) => {
+                    self.bump()?;
+                }
+            }
+        };
+
+        let trailing = self.input[self.pos..end].to_string();
+        self.pos = end;
+        Ok(Block {
+            entries,
+            trailing,
+            indent: String::new(),
+            outer: String::new(),
+            root,
+            style: Style::from(&FormatOpts::default()),
+        })
+    }
+
+    fn entry(&mut self) -> Result<Entry> {
+        let (event, span) = self.bump()?;
+        let leading = self.input[self.pos..span.start.offset].to_string();
+
+        let (key, middle, value) = if let Event::Directive { .. } = event {
+            // Split the directive into its name and path.
+            let text = &self.input[span.range()];
+            let mut tokens = lex::Lexer::new(text).filter(|token| !token.is_trivia());
+            let name = tokens.next().expect("directive has a name");
+            let path = tokens.last().expect("directive has a path");
+            self.pos = span.end.offset;
+            (
+                name.text.to_string(),
+                text[name.span.end.offset..path.span.start.offset].to_string(),
+                Node::String(path.text.to_string()),
+            )
+        } else {
+            let key = self.input[span.range()].to_string();
+            let (value_event, value_span) = loop {
+                match self.bump()? {
+                    (Event::Comment(_) | Event::Conditional(_), _) => {}
+                    event => break event,
+                }
+            };
+            let middle = self.input[span.end.offset..value_span.start.offset].to_string();
+            self.pos = value_span.end.offset;
+            let value = match value_event {
+                Event::Value(_) => Node::String(self.input[value_span.range()].to_string()),
+                Event::BeginObject => {
+                    if self.depth == MAX_DEPTH {
+                        return Err(Error::NestedTooDeeply(MAX_DEPTH).at(value_span.start));
+                    }
+                    self.depth += 1;
+                    let block = self.block(false)?;
+                    self.depth -= 1;
+                    let (_, close) = self.bump()?;
+                    self.pos = close.end.offset;
+                    Node::Block(block)
+                }
+                _ => unreachable!("the reader only allows values after a key"),
+            };
+            (key, middle, value)
+        };
+
+        // Conditional tags and comments after the value belong to this entry.
+        let value_end = self.pos;
+        loop {
+            let (input, pos) = (self.input, self.pos);
+            match self.peek()? {
+                Some((Event::Conditional(_), span)) => {
+                    self.pos = span.end.offset;
+                }
+                Some((Event::Comment(_), span))
+                    if !input[pos..span.start.offset].contains('\n') =>
+                {
+                    self.pos = span.end.offset;
+                }
+                _ => break,
+            }
+            self.bump()?;
+        }
+
+        Ok(Entry {
+            leading,
+            key,
+            middle,
+            value,
+            trailing: self.input[value_end..self.pos].to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RES: &str = r#"// HUD layout
+#base "base.res"
+
+"Resource/HudLayout"
+{
+	// Health cross
+	HudPlayerHealth
+	{
+		"ControlName"		"EditablePanel"
+		xpos		"c-250"	[$WIN32]
+		"ypos"		"r150" // from the bottom
+		"visible" "1" [!$X360]
+	}
+
+	"PlayerStatusAnim" { }
+}
+"#;
+
+    #[test]
+    fn round_trip() {
+        for input in [
+            RES,
+            "",
+            "\n\n",
+            "Key Value",
+            "A { B C } D { } E\r\n{\r\n\tF \"G\\\"\"\r\n}",
+            "\"Key\" [$OSX] \"Value\" // comment",
+        ] {
+            assert_eq!(Document::parse(input).unwrap().to_string(), input);
+        }
+    }
+
+    #[test]
+    fn query() {
+        let doc = Document::parse(RES).unwrap();
+        assert_eq!(doc.len(), 2);
+        assert_eq!(doc.entries().next().unwrap().key(), "#base");
+
+        let panel = "Resource/HudLayout.HudPlayerHealth";
+        assert_eq!(doc.find(panel).unwrap().as_block().unwrap().len(), 4);
+        let xpos = doc.find(&format!("{panel}.xpos")).unwrap();
+        assert_eq!(xpos.as_str().unwrap(), "c-250");
+        assert_eq!(xpos.condition(), Some("$WIN32"));
+        assert_eq!(
+            doc.find(&format!("{panel}.visible")).unwrap().condition(),
+            Some("!$X360")
+        );
+        assert!(doc.find("Resource/HudLayout.xpos").is_none());
+    }
+
+    #[test]
+    fn edit_keeps_formatting() {
+        let mut doc = Document::parse(RES).unwrap();
+        let panel = doc
+            .find_mut("Resource/HudLayout.HudPlayerHealth")
+            .unwrap()
+            .as_block_mut()
+            .unwrap();
+        panel.get_mut("xpos").unwrap().set_value("c-200");
+        panel.get_mut("ypos").unwrap().set_value("r 100");
+        panel.get_mut("ControlName").unwrap().set_key("controlName");
+        panel.remove("visible").unwrap();
+        panel.insert("wide", "120");
+
+        let expected = RES
+            .replace("\"ControlName\"", "\"controlName\"")
+            .replace("\"c-250\"", "\"c-200\"")
+            .replace("\"r150\"", "\"r 100\"")
+            .replace(
+                "\n\t\t\"visible\" \"1\" [!$X360]",
+                "\n\t\t\"wide\"\t\t\"120\"",
+            );
+        assert_eq!(doc.to_string(), expected);
+    }
+
+    #[test]
+    fn insert_into_empty_blocks() {
+        let mut doc = Document::parse(RES).unwrap();
+        let root = doc.get_mut("Resource/HudLayout").unwrap();
+        let anim = root.as_block_mut().unwrap().get_mut("PlayerStatusAnim");
+        let anim = anim.unwrap().as_block_mut().unwrap();
+        anim.insert("xpos", "0");
+        anim.insert_block("Frames").insert("Count", "4");
+        assert!(doc.to_string().ends_with(
+            "\t\"PlayerStatusAnim\" {\n\t\t\"xpos\"\t\t\"0\"\n\t\t\"Frames\"\n\t\t{\n\t\t\t\"Count\"\t\t\"4\"\n\t\t}\n\t}\n}\n"
+        ));
+
+        let mut doc = Document::parse("").unwrap();
+        doc.insert_block("Root").insert("Key", "Value");
+        assert_eq!(doc.to_string(), "\"Root\"\n{\n    \"Key\" \"Value\"\n}\n");
+
+        let mut doc = Document::parse("// Header\nA { B c }").unwrap();
+        doc.get_mut("A").unwrap().set_value("x y");
+        doc.insert("D", "e");
+        doc.find_mut("A").unwrap().set_key("a b");
+        assert_eq!(doc.to_string(), "// Header\n\"a b\" \"x y\"\nD e");
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let nested = |depth| "a {".repeat(depth) + &"}".repeat(depth);
+        assert!(Document::parse(&nested(MAX_DEPTH)).is_ok());
+
+        let err = Document::parse(&nested(100_000)).unwrap_err();
+        assert!(matches!(err.inner(), Error::NestedTooDeeply(MAX_DEPTH)));
+        assert_eq!(err.position().unwrap().offset, MAX_DEPTH * 3 + 2);
+    }
+
+    #[test]
+    fn set_format() {
+        let mut doc = Document::parse("Root\n{\n\tKey Value\n\tEmpty {}\n}\n").unwrap();
+        doc.set_format(&FormatOpts {
+            indent: String::from("  "),
+            brace_style: BraceStyle::KAndR,
+            quote_keys: Quoting::Always,
+            ..Default::default()
+        });
+        let root = doc.get_mut("Root").unwrap().as_block_mut().unwrap();
+        root.insert_block("New").insert("A", "b");
+        root.get_mut("Empty")
+            .unwrap()
+            .as_block_mut()
+            .unwrap()
+            .insert("C", "d");
+        assert_eq!(
+            doc.to_string(),
+            "Root\n{\n\tKey Value\n\tEmpty {\n\t  \"C\" \"d\"\n\t}\n\t\"New\" {\n\t  \"A\" \"b\"\n\t}\n}\n"
+        );
+    }
+}
@@ -0,0 +1,1156 @@
+use super::lenient;
+use super::lexer::{Lexer, Token};
+use crate::conditional::Condition;
+use crate::lex::Position;
+use crate::{Error, Result};
+use serde::de::{
+    self, DeserializeSeed, Expected, IntoDeserializer, MapAccess, SeqAccess, Unexpected, Visitor,
+};
+use serde::forward_to_deserialize_any;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::{fmt, iter, vec};
+
+/// Options for [`Deserializer`].
+#[derive(Clone, Debug)]
+pub struct DeserializeOpts {
+    /// The symbols (such as `$WIN32` or `$LINUX`) that are defined when evaluating conditional
+    /// tags. Entries whose condition is false are ignored. Symbols are compared
+    /// case-insensitively. If this is `None`, conditional tags are ignored and every entry is kept
+    /// (default: `None`).
+    pub symbols: Option<HashSet<String>>,
+    /// What to do when a key that should have a single value is repeated (default:
+    /// [`DuplicateKeys::Error`]).
+    pub duplicate_keys: DuplicateKeys,
+    /// Whether keys are compared ignoring ASCII case, like the Source engine does (default:
+    /// `false`).
+    ///
+    /// Keys that differ only in case are treated as repeats of the same key, which keeps the
+    /// spelling of its first occurrence. Struct fields and enum variants match keys of any case,
+    /// so a field renamed to `"AppID"` also accepts `"appid"` and `"APPID"`. A key that matches a
+    /// field exactly is always preferred. The keys of [`Entries`](crate::Entries) are an
+    /// exception: they must match the names of their variants exactly.
+    pub case_insensitive: bool,
+    /// Whether `bool`s may also be written as `true` or `false` (ignoring ASCII case), in
+    /// addition to `1` and `0` (default: `false`).
+    pub bool_literals: bool,
+    /// Whether numbers and `bool`s are parsed like the Source engine's `KeyValues::GetInt` and
+    /// `KeyValues::GetFloat`, which use C's `atoi` and `atof` (default: `false`).
+    ///
+    /// Leading whitespace is skipped and anything after the leading number is ignored, so
+    /// `" 12abc"` is `12` and `"1.5"` is `1` when read as an integer. Strings without a leading
+    /// number are `0`. Integers that do not fit in their type saturate to its minimum or maximum
+    /// value. A `bool` is true if it reads as a non-zero integer.
+    pub lenient_numbers: bool,
+    /// Whether strings that look like numbers are visited as numbers when the type being
+    /// deserialized does not say what it expects (default: `true`).
+    ///
+    /// Serde buffers the values of `#[serde(flatten)]` fields and `#[serde(untagged)]` enums
+    /// before it knows their type, and cannot parse numbers from buffered strings. Inferring
+    /// numbers lets such fields and variants be numbers, but prevents them from being strings
+    /// that look like numbers. A string is only treated as a number if it is written exactly as
+    /// Rust would format the number, so `"12"` and `"1.5"` are numbers but `"007"` and `"1e3"` are
+    /// not. [`crate::Value`] converts numbers back into the same strings, so a flattened
+    /// `HashMap<String, Value>` keeps every value; disable this to flatten into a
+    /// `HashMap<String, String>` instead.
+    pub infer_numbers: bool,
+}
+
+impl Default for DeserializeOpts {
+    fn default() -> Self {
+        Self {
+            symbols: None,
+            duplicate_keys: DuplicateKeys::default(),
+            case_insensitive: false,
+            bool_literals: false,
+            lenient_numbers: false,
+            infer_numbers: true,
+        }
+    }
+}
+
+/// Controls how repeated keys are deserialized when a single value is expected, such as for a
+/// struct field of type `i32`.
+///
+/// Fields that are sequences (such as `Vec<T>`) always receive every value of a repeated key, in
+/// document order. [`crate::Value`] always stores every value as well.
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub enum DuplicateKeys {
+    /// Fail with [`Error::DuplicateKey`].
+    #[default]
+    Error,
+    /// Use the first value and ignore the rest.
+    FirstWins,
+    /// Use the last value and ignore the rest. This matches the behavior of the Source engine.
+    LastWins,
+    /// Visit every value as a sequence, like `deserialize_any` does. This is only useful for
+    /// types that accept either a single value or a sequence.
+    Collect,
+}
+
+/// Deserializes KeyValues text into Rust types.
+pub struct Deserializer<'de> {
+    input: &'de str,
+    opts: DeserializeOpts,
+    /// The end of each object that has been skipped over, keyed by the offset of its contents.
+    ends: RefCell<HashMap<usize, Position>>,
+}
+
+impl<'de> Deserializer<'de> {
+    /// Creates a KeyValues deserializer from a `&str`.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(input: &'de str) -> Self {
+        Self::with_opts(input, DeserializeOpts::default())
+    }
+
+    /// Creates a KeyValues deserializer from a `&str` with the specified `opts`.
+    pub fn with_opts(input: &'de str, opts: DeserializeOpts) -> Self {
+        Self {
+            input,
+            opts,
+            ends: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Reads the root of the document. This is usually an object containing every root-level
+    /// key, but may also be a single string if the document consists of nothing else.
+    fn root(&self) -> Result<RawValue<'de>> {
+        let mut lexer = Lexer::new(self.input, Position::START, 0);
+        if let Some(Token::String(s)) = lexer.next_token()? {
+            let position = lexer.start();
+            if lexer.next_token()?.is_none() {
+                return Ok(RawValue {
+                    position,
+                    kind: RawKind::String(s),
+                });
+            }
+        }
+        Ok(RawValue {
+            position: Position::START,
+            kind: RawKind::Object {
+                start: Position::START,
+                root: true,
+            },
+        })
+    }
+
+    fn root_value(&self) -> Result<ValueDeserializer<'_, 'de>> {
+        Ok(ValueDeserializer::new(self, self.root()?))
+    }
+
+    /// Reads the only root-level key-value pair of the document.
+    pub(crate) fn root_entry(&self) -> Result<(Cow<'de, str>, ValueDeserializer<'_, 'de>)> {
+        let mut entries = self.entries(Position::START, true)?.into_iter();
+        match (entries.next(), entries.next()) {
+            (Some(entry), None) => Ok((entry.key, ValueDeserializer::new(self, entry.value))),
+            (Some(_), Some(_)) => Err(Error::MultipleRootKeys),
+            (None, _) => Err(Error::Eof),
+        }
+    }
+
+    /// Reads the first root-level key-value pair of the document that is not excluded by a
+    /// conditional.
+    pub(crate) fn first_root_entry(
+        &self,
+    ) -> Result<Option<(Cow<'de, str>, ValueDeserializer<'_, 'de>)>> {
+        let mut first = None;
+        self.scan(Position::START, true, |entry| {
+            first = Some(entry);
+            false
+        })?;
+        Ok(first.map(|entry| (entry.key, ValueDeserializer::new(self, entry.value))))
+    }
+
+    /// Finds the value at `path`, such as `items_game.items.5023` or `Depots.FileMapping[1]`,
+    /// without parsing anything that comes after it.
+    pub(crate) fn value_at(&self, path: &str) -> Result<ValueDeserializer<'_, 'de>> {
+        let mut value = self.root()?;
+        if path.is_empty() {
+            return Ok(ValueDeserializer::new(self, value));
+        }
+
+        let mut end = 0;
+        for segment in path.split('.') {
+            end += segment.len();
+            let (key, index) = parse_segment(segment);
+            let found = match value.kind {
+                RawKind::Object { start, root } => self.find(start, root, key, index)?,
+                RawKind::String(_) => None,
+            };
+            value = found.ok_or_else(|| Error::PathNotFound(path[..end].to_string()))?;
+            end += 1;
+        }
+        Ok(ValueDeserializer::new(self, value))
+    }
+
+    /// Finds the value of the `index`th occurrence of `key` in an object.
+    fn find(
+        &self,
+        start: Position,
+        root: bool,
+        key: &str,
+        mut index: usize,
+    ) -> Result<Option<RawValue<'de>>> {
+        let mut found = None;
+        self.scan(start, root, |entry| {
+            let matches = if self.opts.case_insensitive {
+                entry.key.eq_ignore_ascii_case(key)
+            } else {
+                entry.key == key
+            };
+            match index {
+                0 if matches => {
+                    found = Some(entry.value);
+                    false
+                }
+                _ if matches => {
+                    index -= 1;
+                    true
+                }
+                _ => true,
+            }
+        })?;
+        Ok(found)
+    }
+
+    /// Reads every key-value pair of an object. Nested objects are skipped over and only
+    /// parsed once they are deserialized.
+    fn entries(&self, start: Position, root: bool) -> Result<Vec<RawEntry<'de>>> {
+        let mut entries = Vec::new();
+        self.scan(start, root, |entry| {
+            entries.push(entry);
+            true
+        })?;
+        Ok(entries)
+    }
+
+    /// Passes each key-value pair of an object to `f` until it returns `false`. Nested objects
+    /// are skipped over without being parsed, and their ends are remembered so that scanning
+    /// them later does not skip over their own nested objects again.
+    fn scan<F: FnMut(RawEntry<'de>) -> bool>(
+        &self,
+        start: Position,
+        root: bool,
+        mut f: F,
+    ) -> Result<()> {
+        let mut lexer = Lexer::new(self.input, start, if root { 0 } else { 1 });
+
+        loop {
+            let key = match lexer.next_token()? {
+                Some(Token::String(key)) => key,
+                Some(Token::CloseBrace) if !root => break,
+                None if root => break,
+                None => return Err(lexer.eof()),
+                Some(token) => return Err(lexer.unexpected("a key", token)),
+            };
+            let key_position = lexer.start();
+
+            // Conditional tags may follow either the key or the value.
+            let mut included = self.eat_conditional(&mut lexer)?;
+            let token = lexer.next_token()?;
+            let position = lexer.start();
+            let kind = match token {
+                Some(Token::String(s)) => {
+                    included &= self.eat_conditional(&mut lexer)?;
+                    RawKind::String(s)
+                }
+                Some(Token::OpenBrace) => {
+                    let start = lexer.position();
+                    lexer.skip_object(&mut self.ends.borrow_mut())?;
+                    included &= self.eat_conditional(&mut lexer)?;
+                    RawKind::Object { start, root: false }
+                }
+                None => return Err(lexer.eof()),
+                Some(token) => return Err(lexer.unexpected("a value", token)),
+            };
+            let entry = RawEntry {
+                key,
+                key_position,
+                value: RawValue { position, kind },
+            };
+            if included && !f(entry) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Consumes a conditional tag if one comes next, returning `false` if the entry it belongs to
+    /// should be ignored.
+    fn eat_conditional(&self, lexer: &mut Lexer<'de>) -> Result<bool> {
+        let Some(condition) = lexer.eat_conditional()? else {
+            return Ok(true);
+        };
+        let Some(symbols) = &self.opts.symbols else {
+            return Ok(true);
+        };
+
+        let condition: Condition = condition
+            .parse()
+            .map_err(|err: Error| err.at(lexer.start()))?;
+        Ok(condition.evaluate(|symbol| {
+            symbols
+                .iter()
+                .any(|defined| defined.eq_ignore_ascii_case(symbol))
+        }))
+    }
+}
+
+/// Splits a segment of a key path into a key and the index of the occurrence of that key, such
+/// as `FileMapping[1]`. The index defaults to 0.
+pub(crate) fn parse_segment(segment: &str) -> (&str, usize) {
+    segment
+        .strip_suffix(']')
+        .and_then(|rest| rest.rsplit_once('['))
+        .and_then(|(key, index)| Some((key, index.parse().ok()?)))
+        .unwrap_or((segment, 0))
+}
+
+/// A key-value pair whose value has not been deserialized yet.
+struct RawEntry<'de> {
+    key: Cow<'de, str>,
+    key_position: Position,
+    value: RawValue<'de>,
+}
+
+/// A value that has not been deserialized yet.
+struct RawValue<'de> {
+    /// The position of the first token of the value, which is used to locate errors.
+    position: Position,
+    kind: RawKind<'de>,
+}
+
+enum RawKind<'de> {
+    String(Cow<'de, str>),
+    /// An object whose contents start at `start`. The root object has no braces and ends at
+    /// the end of the input instead of at a `}`.
+    Object {
+        start: Position,
+        root: bool,
+    },
+}
+
+impl RawValue<'_> {
+    fn unexpected(&self) -> Unexpected<'_> {
+        match &self.kind {
+            RawKind::String(s) => Unexpected::Str(s),
+            RawKind::Object { .. } => Unexpected::Map,
+        }
+    }
+}
+
+/// Every value of a (possibly repeated) key, in document order.
+struct RawGroup<'de> {
+    key: Cow<'de, str>,
+    /// The position of each occurrence of the key.
+    key_positions: Vec<Position>,
+    values: Vec<RawValue<'de>>,
+}
+
+/// Groups the values of repeated keys together, in the order each key first appeared. If
+/// `ignore_case` is true, keys that refer to the same field of `fields`, or that otherwise differ
+/// only in ASCII case, are grouped together.
+fn group_entries<'de>(
+    entries: Vec<RawEntry<'de>>,
+    ignore_case: bool,
+    fields: &'static [&'static str],
+) -> Vec<RawGroup<'de>> {
+    let mut groups: Vec<RawGroup> = Vec::with_capacity(entries.len());
+    let mut indices: HashMap<Cow<str>, usize> = HashMap::with_capacity(entries.len());
+
+    for RawEntry {
+        key,
+        key_position,
+        value,
+    } in entries
+    {
+        let index_key = if ignore_case {
+            match match_field(key.clone(), fields) {
+                field if fields.contains(&field.as_ref()) => field,
+                _ => Cow::Owned(key.to_ascii_lowercase()),
+            }
+        } else {
+            key.clone()
+        };
+        match indices.get(&index_key) {
+            Some(&index) => {
+                groups[index].key_positions.push(key_position);
+                groups[index].values.push(value);
+            }
+            None => {
+                indices.insert(index_key, groups.len());
+                groups.push(RawGroup {
+                    key,
+                    key_positions: vec![key_position],
+                    values: vec![value],
+                });
+            }
+        }
+    }
+
+    groups
+}
+
+/// Returns the name of the struct field that `key` refers to. A field spelled exactly like `key`
+/// is preferred over one that only matches when ignoring ASCII case. Keys that do not match any
+/// field are returned unchanged.
+fn match_field<'de>(key: Cow<'de, str>, fields: &'static [&'static str]) -> Cow<'de, str> {
+    if fields.contains(&key.as_ref()) {
+        return key;
+    }
+    match fields.iter().find(|field| field.eq_ignore_ascii_case(&key)) {
+        Some(field) => Cow::Borrowed(field),
+        None => key,
+    }
+}
+
+/// Parses a `bool` written as `1` or `0`, or as `true` or `false` if
+/// [`DeserializeOpts::bool_literals`] is set. With [`DeserializeOpts::lenient_numbers`], any
+/// other string is true if it starts with a non-zero integer.
+fn parse_bool(s: &str, opts: &DeserializeOpts) -> Option<bool> {
+    match s {
+        "1" => Some(true),
+        "0" => Some(false),
+        _ if opts.bool_literals && s.eq_ignore_ascii_case("true") => Some(true),
+        _ if opts.bool_literals && s.eq_ignore_ascii_case("false") => Some(false),
+        _ if opts.lenient_numbers => Some(lenient::atoi(s) != 0),
+        _ => None,
+    }
+}
+
+/// Visits a string as a number if formatting the number would produce the same string, or as a
+/// string otherwise.
+fn visit_inferred<'de, V: Visitor<'de>>(s: Cow<'de, str>, visitor: V) -> Result<V::Value> {
+    if let Some(v) = s.parse::<u64>().ok().filter(|v| v.to_string() == s) {
+        visitor.visit_u64(v)
+    } else if let Some(v) = s.parse::<i64>().ok().filter(|v| v.to_string() == s) {
+        visitor.visit_i64(v)
+    } else if let Some(v) = s
+        .parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite() && v.to_string() == s)
+    {
+        visitor.visit_f64(v)
+    } else {
+        visit_cow_str(s, visitor)
+    }
+}
+
+/// Visits a string, borrowing it from the input if it contained no escape sequences.
+fn visit_cow_str<'de, V: Visitor<'de>>(s: Cow<'de, str>, visitor: V) -> Result<V::Value> {
+    match s {
+        Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+        Cow::Owned(s) => visitor.visit_string(s),
+    }
+}
+
+/// Visits a string for `deserialize_str`. If the visitor rejects a string with escape sequences
+/// because it can only borrow strings from the input, the error is reported as
+/// [`Error::BorrowedEscapedString`].
+fn deserialize_cow_str<'de, V: Visitor<'de>>(s: Cow<'de, str>, visitor: V) -> Result<V::Value> {
+    match s {
+        Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+        Cow::Owned(s) => visitor
+            .visit_string::<UnescapedStrError>(s)
+            .map_err(|err| err.0),
+    }
+}
+
+/// The error of a visitor that was given a string with escape sequences by `deserialize_str`.
+#[derive(Debug)]
+struct UnescapedStrError(Error);
+
+impl fmt::Display for UnescapedStrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for UnescapedStrError {}
+
+impl de::Error for UnescapedStrError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(de::Error::custom(msg))
+    }
+
+    fn invalid_type(unexp: Unexpected, exp: &dyn Expected) -> Self {
+        match unexp {
+            // Serde's visitor for `&str` only accepts strings that are borrowed from the input.
+            Unexpected::Str(s) if exp.to_string() == "a borrowed string" => {
+                Self(Error::BorrowedEscapedString(s.to_string()))
+            }
+            unexp => Self(de::Error::invalid_type(unexp, exp)),
+        }
+    }
+}
+
+/// Generates `deserialize_*` methods that forward to the [`ValueDeserializer`] returned by the
+/// given method. Errors without a position are located at the value.
+macro_rules! forward_to_value_impl {
+    ($into_value:ident; $($method:ident($($arg:ident: $ty:ty),*))*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value> {
+                let value = self.$into_value()?;
+                let position = value.position();
+                value.$method($($arg,)* visitor).map_err(|err| err.at(position))
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    forward_to_value_impl! {
+        root_value;
+        deserialize_any()
+        deserialize_bool()
+        deserialize_i8()
+        deserialize_i16()
+        deserialize_i32()
+        deserialize_i64()
+        deserialize_i128()
+        deserialize_u8()
+        deserialize_u16()
+        deserialize_u32()
+        deserialize_u64()
+        deserialize_u128()
+        deserialize_f32()
+        deserialize_f64()
+        deserialize_char()
+        deserialize_str()
+        deserialize_string()
+        deserialize_bytes()
+        deserialize_byte_buf()
+        deserialize_option()
+        deserialize_unit()
+        deserialize_unit_struct(name: &'static str)
+        deserialize_newtype_struct(name: &'static str)
+        deserialize_seq()
+        deserialize_tuple(len: usize)
+        deserialize_tuple_struct(name: &'static str, len: usize)
+        deserialize_map()
+        deserialize_struct(name: &'static str, fields: &'static [&'static str])
+        deserialize_enum(name: &'static str, variants: &'static [&'static str])
+        deserialize_identifier()
+        deserialize_ignored_any()
+    }
+}
+
+/// Deserializes a single KeyValues value.
+pub(crate) struct ValueDeserializer<'a, 'de> {
+    de: &'a Deserializer<'de>,
+    value: RawValue<'de>,
+}
+
+impl<'a, 'de> ValueDeserializer<'a, 'de> {
+    fn new(de: &'a Deserializer<'de>, value: RawValue<'de>) -> Self {
+        Self { de, value }
+    }
+
+    /// Returns the position of the value in the input.
+    pub(crate) fn position(&self) -> Position {
+        self.value.position
+    }
+
+    fn into_str<E: de::Expected>(self, exp: &E) -> Result<Cow<'de, str>> {
+        match self.value.kind {
+            RawKind::String(s) => Ok(s),
+            RawKind::Object { .. } => Err(de::Error::invalid_type(Unexpected::Map, exp)),
+        }
+    }
+
+    /// Visits an object with repeated keys grouped together. `fields` are the fields of the
+    /// struct being deserialized, or `&[]` for maps.
+    fn deserialize_object<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.value.kind {
+            RawKind::Object { start, root } => {
+                let entries = self.de.entries(start, root)?;
+                visitor.visit_map(GroupMapAccess {
+                    de: self.de,
+                    groups: group_entries(entries, self.de.opts.case_insensitive, fields)
+                        .into_iter(),
+                    group: None,
+                    fields,
+                })
+            }
+            _ => Err(de::Error::invalid_type(self.value.unexpected(), &visitor)),
+        }
+    }
+}
+
+macro_rules! deserialize_from_str_impl {
+    ($ty:ident) => {
+        paste::paste! {
+            fn [<deserialize_ $ty>]<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                let lenient = self.de.opts.lenient_numbers;
+                let s = self.into_str(&visitor)?;
+                match lenient::parse::<$ty>(&s, lenient) {
+                    Some(v) => visitor.[<visit_ $ty>](v),
+                    None => Err(de::Error::invalid_value(Unexpected::Str(&s), &visitor)),
+                }
+            }
+        }
+    };
+    ($first:ident, $($rest:ident),+) => {
+        deserialize_from_str_impl!($first);
+        deserialize_from_str_impl!($($rest),+);
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'_, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value.kind {
+            RawKind::String(s) if self.de.opts.infer_numbers => visit_inferred(s, visitor),
+            RawKind::String(s) => visit_cow_str(s, visitor),
+            RawKind::Object { start, root } => visitor.visit_map(EntryMapAccess {
+                de: self.de,
+                entries: self.de.entries(start, root)?.into_iter(),
+                value: None,
+            }),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let de = self.de;
+        let s = self.into_str(&visitor)?;
+        match parse_bool(&s, &de.opts) {
+            Some(b) => visitor.visit_bool(b),
+            None => Err(de::Error::invalid_value(Unexpected::Str(&s), &visitor)),
+        }
+    }
+
+    deserialize_from_str_impl!(i8, i16, i32, i64, i128, u8, u16, u32, u64, u128, f32, f64, char);
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let s = self.into_str(&visitor)?;
+        deserialize_cow_str(s, visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let s = self.into_str(&visitor)?;
+        visit_cow_str(s, visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::UnsupportedType("bytes".to_string()))
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::UnsupportedType("bytes".to_string()))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        // `None` is never written, so any value that is present must be `Some`.
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match &self.value.kind {
+            RawKind::String(s) if s.is_empty() => visitor.visit_unit(),
+            _ => Err(de::Error::invalid_type(self.value.unexpected(), &visitor)),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        // Sequences are represented by repeated keys, which are handled by `GroupDeserializer`.
+        Err(de::Error::invalid_type(self.value.unexpected(), &visitor))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_object(&[], visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_object(fields, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let ignore_case = self.de.opts.case_insensitive;
+        match self.value.kind {
+            // Unit variants are written as the name of the variant.
+            RawKind::String(s) if ignore_case => {
+                visitor.visit_enum(match_field(s, variants).into_deserializer())
+            }
+            RawKind::String(s) => visitor.visit_enum(s.into_deserializer()),
+            // Other variants are written as an object with a single key naming the variant. The
+            // key is repeated for each field of a tuple variant.
+            RawKind::Object { start, root } => {
+                let entries = self.de.entries(start, root)?;
+                let mut groups = group_entries(entries, ignore_case, variants).into_iter();
+                let expected = &"an object with a single key naming the variant";
+                match (groups.next(), groups.next()) {
+                    (Some(group), None) => visitor.visit_enum(VariantAccess {
+                        de: self.de,
+                        group,
+                        variants,
+                    }),
+                    (Some(_), Some(extra)) => {
+                        let err: Error = de::Error::invalid_value(Unexpected::Map, expected);
+                        Err(err.at(extra.key_positions[0]))
+                    }
+                    (None, _) => Err(de::Error::invalid_value(Unexpected::Map, expected)),
+                }
+            }
+        }
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    /// Nested objects are skipped over when the entries of their parent are read, so ignoring a
+    /// value never parses it.
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+}
+
+/// Deserializes every value of a (possibly repeated) key.
+struct GroupDeserializer<'a, 'de> {
+    de: &'a Deserializer<'de>,
+    group: RawGroup<'de>,
+}
+
+impl<'a, 'de> GroupDeserializer<'a, 'de> {
+    /// Returns `true` if the key is repeated and every value should be visited as a sequence,
+    /// even though a single value was requested.
+    fn collect(&self) -> bool {
+        self.group.values.len() > 1 && self.de.opts.duplicate_keys == DuplicateKeys::Collect
+    }
+
+    /// Picks the value to use when a single value was requested, according to the
+    /// [`DuplicateKeys`] policy.
+    fn into_value(self) -> Result<ValueDeserializer<'a, 'de>> {
+        let RawGroup {
+            key,
+            key_positions,
+            mut values,
+        } = self.group;
+
+        let index = match self.de.opts.duplicate_keys {
+            _ if values.len() == 1 => 0,
+            DuplicateKeys::FirstWins => 0,
+            DuplicateKeys::LastWins => values.len() - 1,
+            DuplicateKeys::Error | DuplicateKeys::Collect => {
+                let err = Error::DuplicateKey {
+                    key: key.into_owned(),
+                    first: key_positions[0],
+                    second: key_positions[1],
+                };
+                return Err(err.at(key_positions[1]));
+            }
+        };
+        Ok(ValueDeserializer::new(self.de, values.swap_remove(index)))
+    }
+}
+
+/// Generates `deserialize_*` methods for [`GroupDeserializer`] that deserialize a single value,
+/// unless the values of a repeated key are collected into a sequence.
+macro_rules! forward_to_group_value_impl {
+    ($($method:ident($($arg:ident: $ty:ty),*))*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value> {
+                if self.collect() {
+                    return self.deserialize_seq(visitor);
+                }
+                let value = self.into_value()?;
+                let position = value.position();
+                value.$method($($arg,)* visitor).map_err(|err| err.at(position))
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for GroupDeserializer<'_, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.de.opts.duplicate_keys {
+            _ if self.group.values.len() == 1 => self.into_value()?.deserialize_any(visitor),
+            DuplicateKeys::FirstWins | DuplicateKeys::LastWins => {
+                self.into_value()?.deserialize_any(visitor)
+            }
+            DuplicateKeys::Error | DuplicateKeys::Collect => self.deserialize_seq(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(ValueSeqAccess {
+            de: self.de,
+            values: self.group.values.into_iter().enumerate(),
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    /// Ignored values were already skipped over without being parsed, so repeated keys are never
+    /// an error here.
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    forward_to_group_value_impl! {
+        deserialize_bool()
+        deserialize_i8()
+        deserialize_i16()
+        deserialize_i32()
+        deserialize_i64()
+        deserialize_i128()
+        deserialize_u8()
+        deserialize_u16()
+        deserialize_u32()
+        deserialize_u64()
+        deserialize_u128()
+        deserialize_f32()
+        deserialize_f64()
+        deserialize_char()
+        deserialize_str()
+        deserialize_string()
+        deserialize_bytes()
+        deserialize_byte_buf()
+        deserialize_unit()
+        deserialize_unit_struct(name: &'static str)
+        deserialize_map()
+        deserialize_struct(name: &'static str, fields: &'static [&'static str])
+        deserialize_enum(name: &'static str, variants: &'static [&'static str])
+        deserialize_identifier()
+    }
+}
+
+/// Deserializes an object key. Keys are always strings, but may be parsed into other scalars
+/// such as integers.
+struct KeyDeserializer<'a, 'de> {
+    de: &'a Deserializer<'de>,
+    key: Cow<'de, str>,
+}
+
+impl<'de> KeyDeserializer<'_, 'de> {
+    fn into_str<E: de::Expected>(self, _exp: &E) -> Result<Cow<'de, str>> {
+        Ok(self.key)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer<'_, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visit_cow_str(self.key, visitor)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match parse_bool(&self.key, &self.de.opts) {
+            Some(b) => visitor.visit_bool(b),
+            None => Err(de::Error::invalid_value(
+                Unexpected::Str(&self.key),
+                &visitor,
+            )),
+        }
+    }
+
+    deserialize_from_str_impl!(i8, i16, i32, i64, i128, u8, u16, u32, u64, u128, f32, f64, char);
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        if self.de.opts.case_insensitive {
+            visitor.visit_enum(match_field(self.key, variants).into_deserializer())
+        } else {
+            visitor.visit_enum(self.key.into_deserializer())
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        deserialize_cow_str(self.key, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        string bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier
+        ignored_any
+    }
+}
+
+/// Visits the entries of an object in document order, including repeated keys.
+struct EntryMapAccess<'a, 'de> {
+    de: &'a Deserializer<'de>,
+    entries: vec::IntoIter<RawEntry<'de>>,
+    value: Option<(Cow<'de, str>, RawValue<'de>)>,
+}
+
+impl<'de> MapAccess<'de> for EntryMapAccess<'_, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.entries.next() {
+            Some(RawEntry {
+                key,
+                key_position,
+                value,
+            }) => {
+                self.value = Some((key.clone(), value));
+                seed.deserialize(KeyDeserializer {
+                    de: self.de,
+                    key: key.clone(),
+                })
+                .map(Some)
+                .map_err(|err| err.at(key_position).in_key(&key))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let (key, value) = self
+            .value
+            .take()
+            .expect("next_value_seed called before next_key_seed");
+        let position = value.position;
+        seed.deserialize(ValueDeserializer::new(self.de, value))
+            .map_err(|err| err.at(position).in_key(&key))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// Visits the entries of an object with the values of repeated keys grouped together.
+struct GroupMapAccess<'a, 'de> {
+    de: &'a Deserializer<'de>,
+    groups: vec::IntoIter<RawGroup<'de>>,
+    group: Option<RawGroup<'de>>,
+    /// The fields of the struct being deserialized, or `&[]` for maps.
+    fields: &'static [&'static str],
+}
+
+impl<'de> MapAccess<'de> for GroupMapAccess<'_, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.groups.next() {
+            Some(group) => {
+                let key = group.key.clone();
+                let key_position = group.key_positions[0];
+                self.group = Some(group);
+                let name = if self.de.opts.case_insensitive {
+                    match_field(key.clone(), self.fields)
+                } else {
+                    key.clone()
+                };
+                seed.deserialize(KeyDeserializer {
+                    de: self.de,
+                    key: name,
+                })
+                .map(Some)
+                .map_err(|err| err.at(key_position).in_key(&key))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let group = self
+            .group
+            .take()
+            .expect("next_value_seed called before next_key_seed");
+        let key = group.key.clone();
+        let position = group.values[0].position;
+        seed.deserialize(GroupDeserializer { de: self.de, group })
+            .map_err(|err| err.at(position).in_key(&key))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.groups.len())
+    }
+}
+
+/// Visits an enum variant written as an object, such as `{ "Variant" "value" }`.
+struct VariantAccess<'a, 'de> {
+    de: &'a Deserializer<'de>,
+    /// Every value of the key naming the variant.
+    group: RawGroup<'de>,
+    variants: &'static [&'static str],
+}
+
+impl<'a, 'de> VariantAccess<'a, 'de> {
+    /// Deserializes the contents of the variant with `f`, locating any errors.
+    fn contents<T>(self, f: impl FnOnce(GroupDeserializer<'a, 'de>) -> Result<T>) -> Result<T> {
+        let key = self.group.key.clone();
+        let position = self.group.values[0].position;
+        f(GroupDeserializer {
+            de: self.de,
+            group: self.group,
+        })
+        .map_err(|err| err.at(position).in_key(&key))
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for VariantAccess<'_, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let key = self.group.key.clone();
+        let name = if self.de.opts.case_insensitive {
+            match_field(key.clone(), self.variants)
+        } else {
+            key.clone()
+        };
+        let variant = seed
+            .deserialize(KeyDeserializer {
+                de: self.de,
+                key: name,
+            })
+            .map_err(|err| err.at(self.group.key_positions[0]).in_key(&key))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess<'_, 'de> {
+    type Error = Error;
+
+    /// Unit variants written as an object must have an empty value, such as `{ "Variant" "" }`
+    /// or `{ "Variant" {} }`.
+    fn unit_variant(self) -> Result<()> {
+        let deserializer = self.de;
+        self.contents(|group| {
+            let value = group.into_value()?.value;
+            let is_empty = match &value.kind {
+                RawKind::String(s) => s.is_empty(),
+                RawKind::Object { start, root } => deserializer.entries(*start, *root)?.is_empty(),
+            };
+            if is_empty {
+                Ok(())
+            } else {
+                Err(de::Error::invalid_type(value.unexpected(), &"unit variant"))
+            }
+        })
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        self.contents(|group| seed.deserialize(group))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        self.contents(|group| de::Deserializer::deserialize_tuple(group, len, visitor))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.contents(|group| de::Deserializer::deserialize_struct(group, "", fields, visitor))
+    }
+}
+
+/// Visits every value of a repeated key.
+struct ValueSeqAccess<'a, 'de> {
+    de: &'a Deserializer<'de>,
+    values: iter::Enumerate<vec::IntoIter<RawValue<'de>>>,
+}
+
+impl<'de> SeqAccess<'de> for ValueSeqAccess<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        match self.values.next() {
+            Some((index, value)) => {
+                let position = value.position;
+                seed.deserialize(ValueDeserializer::new(self.de, value))
+                    .map(Some)
+                    .map_err(|err| err.at(position).in_index(index))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len())
+    }
+}
@@ -0,0 +1,165 @@
+use std::str::FromStr;
+
+/// A scalar that can be parsed from a KeyValues string.
+pub(crate) trait Scalar: FromStr {
+    /// Parses `s` the way the Source engine does, ignoring anything that does not make sense.
+    fn parse_lenient(s: &str) -> Option<Self>;
+}
+
+/// Parses `s` as a `T`. If `lenient` is true, invalid input is interpreted like C's `atoi` and
+/// `atof` would.
+pub(crate) fn parse<T: Scalar>(s: &str, lenient: bool) -> Option<T> {
+    if lenient {
+        T::parse_lenient(s)
+    } else {
+        s.parse().ok()
+    }
+}
+
+macro_rules! impl_scalar_for_int {
+    ($($ty:ident)*) => {
+        $(
+            impl Scalar for $ty {
+                fn parse_lenient(s: &str) -> Option<Self> {
+                    let value = atoi(s);
+                    Some($ty::try_from(value).unwrap_or(if value < 0 { $ty::MIN } else { $ty::MAX }))
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar_for_int!(i8 i16 i32 i64 i128 u8 u16 u32 u64 u128);
+
+impl Scalar for f32 {
+    fn parse_lenient(s: &str) -> Option<Self> {
+        Some(atof(s) as f32)
+    }
+}
+
+impl Scalar for f64 {
+    fn parse_lenient(s: &str) -> Option<Self> {
+        Some(atof(s))
+    }
+}
+
+impl Scalar for char {
+    fn parse_lenient(s: &str) -> Option<Self> {
+        s.parse().ok()
+    }
+}
+
+/// Removes leading whitespace, as defined by C's `isspace`.
+fn trim_start(s: &str) -> &str {
+    s.trim_start_matches([' ', '\t', '\n', '\x0b', '\x0c', '\r'])
+}
+
+/// Parses the integer at the start of `s` like C's `atoi`. Leading whitespace is skipped,
+/// parsing stops at the first character that is not a digit and `0` is returned if there are no
+/// digits. Values that are too large saturate instead of overflowing.
+pub(crate) fn atoi(s: &str) -> i128 {
+    let s = trim_start(s);
+    let (negative, digits) = match s.as_bytes().first() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+
+    let mut value: i128 = 0;
+    for digit in digits.bytes().take_while(u8::is_ascii_digit) {
+        let digit = i128::from(digit - b'0');
+        value = if negative {
+            value.saturating_mul(10).saturating_sub(digit)
+        } else {
+            value.saturating_mul(10).saturating_add(digit)
+        };
+    }
+    value
+}
+
+/// Parses the decimal number at the start of `s` like C's `atof`. Leading whitespace is skipped,
+/// parsing stops at the first character that cannot continue the number and `0.0` is returned if
+/// there is no number.
+pub(crate) fn atof(s: &str) -> f64 {
+    let s = trim_start(s);
+    let bytes = s.as_bytes();
+    let skip_digits = |mut i: usize| {
+        while bytes.get(i).is_some_and(u8::is_ascii_digit) {
+            i += 1;
+        }
+        i
+    };
+
+    let mut end = usize::from(matches!(bytes.first(), Some(b'+' | b'-')));
+    let int_end = skip_digits(end);
+    let mut digits = int_end - end;
+    end = int_end;
+    if bytes.get(end) == Some(&b'.') {
+        let frac_end = skip_digits(end + 1);
+        digits += frac_end - (end + 1);
+        end = frac_end;
+    }
+    if digits == 0 {
+        return 0.0;
+    }
+
+    // The exponent is only part of the number if it has digits.
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let mut exp = end + 1;
+        if matches!(bytes.get(exp), Some(b'+' | b'-')) {
+            exp += 1;
+        }
+        if bytes.get(exp).is_some_and(u8::is_ascii_digit) {
+            end = skip_digits(exp);
+        }
+    }
+
+    s[..end].parse().unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atoi_matches_c() {
+        for (input, expected) in [
+            ("42", 42),
+            ("12abc", 12),
+            ("", 0),
+            ("abc", 0),
+            ("1.5", 1),
+            (" \t3", 3),
+            ("-17", -17),
+            ("+8", 8),
+            ("- 8", 0),
+            ("99999999999999999999999999999999999999999", i128::MAX),
+        ] {
+            assert_eq!(atoi(input), expected, "{input:?}");
+        }
+
+        assert_eq!(parse::<u8>("300", true), Some(u8::MAX));
+        assert_eq!(parse::<u8>("-1", true), Some(0));
+        assert_eq!(parse::<i32>("12abc", false), None);
+    }
+
+    #[test]
+    fn atof_matches_c() {
+        for (input, expected) in [
+            ("1.5", 1.5),
+            ("  -2.25xyz", -2.25),
+            (".5", 0.5),
+            ("7.", 7.0),
+            ("1e3", 1000.0),
+            ("2E-2", 0.02),
+            ("3e", 3.0),
+            ("4e+", 4.0),
+            ("", 0.0),
+            (".", 0.0),
+            ("-", 0.0),
+            ("abc", 0.0),
+        ] {
+            assert_eq!(atof(input), expected, "{input:?}");
+        }
+    }
+}
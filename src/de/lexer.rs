@@ -0,0 +1,172 @@
+use super::MAX_DEPTH;
+use crate::lex::{self, LexError, Position, TokenKind};
+use crate::{Error, Result};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A significant KeyValues token.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Token<'de> {
+    /// A quoted or unquoted string (or directive), with escape sequences already processed.
+    String(Cow<'de, str>),
+    /// A conditional tag such as `[$WIN32]`. Only the text between the brackets is stored.
+    Conditional(&'de str),
+    /// The `{` character.
+    OpenBrace,
+    /// The `}` character.
+    CloseBrace,
+}
+
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::String(s) => write!(f, "string \"{s}\""),
+            Token::Conditional(c) => write!(f, "conditional `[{c}]`"),
+            Token::OpenBrace => f.write_str("`{`"),
+            Token::CloseBrace => f.write_str("`}`"),
+        }
+    }
+}
+
+/// Reads significant [`Token`]s from a [`lex::Lexer`], skipping over trivia.
+pub(crate) struct Lexer<'de> {
+    input: &'de str,
+    tokens: lex::Lexer<'de>,
+    peeked: Option<lex::Token<'de>>,
+    start: Position,
+}
+
+impl<'de> Lexer<'de> {
+    /// Creates a lexer that starts reading `input` at `pos`, inside `depth` levels of objects.
+    pub fn new(input: &'de str, pos: Position, depth: usize) -> Self {
+        Self {
+            input,
+            tokens: lex::Lexer::resume(input, pos, depth),
+            peeked: None,
+            start: pos,
+        }
+    }
+
+    /// Returns the next token, or `None` if the end of the input was reached.
+    pub fn next_token(&mut self) -> Result<Option<Token<'de>>> {
+        let Some(token) = self.next_raw() else {
+            return Ok(None);
+        };
+
+        let token = match token.kind {
+            TokenKind::QuotedString | TokenKind::UnquotedString | TokenKind::Directive => {
+                Token::String(token.value())
+            }
+            TokenKind::Conditional => Token::Conditional(&token.text[1..token.text.len() - 1]),
+            TokenKind::OpenBrace => Token::OpenBrace,
+            TokenKind::CloseBrace => Token::CloseBrace,
+            TokenKind::Error(error) => return Err(lex_error(error).at(token.span.start)),
+            TokenKind::Whitespace | TokenKind::Newline | TokenKind::Comment => {
+                unreachable!("trivia is skipped")
+            }
+        };
+
+        Ok(Some(token))
+    }
+
+    /// Returns the position of the next token, or the position just after the last token if
+    /// nothing has been peeked.
+    pub fn position(&self) -> Position {
+        match self.peeked {
+            Some(token) => token.span.start,
+            None => self.tokens.position(),
+        }
+    }
+
+    /// Returns the position where the last token returned by [`Lexer::next_token`] started.
+    pub fn start(&self) -> Position {
+        self.start
+    }
+
+    /// Returns an error for a token that was just read but is not allowed here.
+    pub fn unexpected(&self, expected: &'static str, found: Token) -> Error {
+        Error::UnexpectedToken {
+            expected,
+            found: found.to_string(),
+        }
+        .at(self.start)
+    }
+
+    /// Returns an error for reaching the end of the input too early.
+    pub fn eof(&self) -> Error {
+        Error::Eof.at(self.tokens.position())
+    }
+
+    /// Consumes a conditional tag if one comes next.
+    pub fn eat_conditional(&mut self) -> Result<Option<&'de str>> {
+        match self.peek_raw() {
+            Some(token) if token.kind == TokenKind::Conditional => match self.next_token()? {
+                Some(Token::Conditional(condition)) => Ok(Some(condition)),
+                _ => unreachable!("peeked a conditional"),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    /// Skips the contents of an object whose `{` was just consumed, including its closing `}`.
+    ///
+    /// This only looks at the structure of the input. The end of every object that is skipped
+    /// over is recorded in `ends` (keyed by the offset of its contents), so that each object is
+    /// only ever skipped once.
+    pub fn skip_object(&mut self, ends: &mut HashMap<usize, Position>) -> Result<()> {
+        debug_assert!(self.peeked.is_none(), "tried to skip object after peeking");
+        let depth = self.tokens.depth() - 1;
+        if let Some(&end) = ends.get(&self.tokens.position().offset) {
+            self.tokens = lex::Lexer::resume(self.input, end, depth);
+            return Ok(());
+        }
+
+        let mut starts = vec![self.tokens.position()];
+        while let Some(token) = self.next_raw() {
+            match token.kind {
+                TokenKind::OpenBrace if self.tokens.depth() > MAX_DEPTH => {
+                    return Err(Error::NestedTooDeeply(MAX_DEPTH).at(token.span.start));
+                }
+                TokenKind::OpenBrace => starts.push(token.span.end),
+                TokenKind::CloseBrace => {
+                    let start = starts.pop().expect("braces are balanced");
+                    ends.insert(start.offset, token.span.end);
+                    if self.tokens.depth() == depth {
+                        return Ok(());
+                    }
+                }
+                TokenKind::Error(error) => return Err(lex_error(error).at(token.span.start)),
+                _ => {}
+            }
+        }
+        Err(self.eof())
+    }
+
+    fn next_raw(&mut self) -> Option<lex::Token<'de>> {
+        let token = self
+            .peeked
+            .take()
+            .or_else(|| self.tokens.find(|token| !token.is_trivia()))?;
+        self.start = token.span.start;
+        Some(token)
+    }
+
+    fn peek_raw(&mut self) -> Option<lex::Token<'de>> {
+        if self.peeked.is_none() {
+            self.peeked = self.tokens.find(|token| !token.is_trivia());
+        }
+        self.peeked
+    }
+}
+
+pub(crate) fn lex_error(error: LexError) -> Error {
+    match error {
+        LexError::UnterminatedString => Error::UnterminatedString,
+        LexError::UnterminatedConditional => Error::UnterminatedConditional,
+        LexError::UnmatchedCloseBrace => Error::UnexpectedToken {
+            expected: "a key",
+            found: Token::CloseBrace.to_string(),
+        },
+    }
+}
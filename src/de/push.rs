@@ -0,0 +1,220 @@
+use super::reader::{Checkpoint, Event, Reader};
+use crate::lex::{Position, Span};
+use crate::{Error, Result};
+use std::{io, str};
+
+/// An incremental parser for KeyValues text that arrives in chunks, such as from a pipe or a
+/// socket.
+///
+/// Each call to [`PushParser::feed`] returns the [`Event`]s that were completed by the new
+/// chunk. A token may be split across any number of chunks, including in the middle of a UTF-8
+/// character; it is returned once the rest of it has been fed. Only the text that has not been
+/// turned into events yet is kept in memory.
+///
+/// ```
+/// use vdflex::de::{Event, PushParser};
+///
+/// let mut parser = PushParser::new();
+/// assert_eq!(parser.feed(b"\"Ga").unwrap(), []);
+///
+/// let events = parser.feed(b"me\" { Name \"Half-Life 2\" }\n").unwrap();
+/// let events: Vec<_> = events.into_iter().map(|(event, _span)| event).collect();
+/// assert_eq!(
+///     events,
+///     [
+///         Event::Key("Game".into()),
+///         Event::BeginObject,
+///         Event::Key("Name".into()),
+///         Event::Value("Half-Life 2".into()),
+///         Event::EndObject,
+///     ]
+/// );
+///
+/// parser.feed(b"Truncated {").unwrap();
+/// assert!(parser.finish().is_err());
+/// ```
+#[derive(Clone, Debug)]
+pub struct PushParser {
+    /// The text that has not been turned into events yet.
+    buffer: Vec<u8>,
+    /// Where `buffer` starts in the whole input.
+    checkpoint: Checkpoint,
+}
+
+impl PushParser {
+    /// Creates a parser that expects the start of a KeyValues document.
+    pub fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            checkpoint: Checkpoint::START,
+        }
+    }
+
+    /// Adds the next chunk of input and returns the events that it completed, along with their
+    /// spans in the whole input.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the input read so far is not valid KeyValues or not valid UTF-8. The parser
+    /// should not be used after an error.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<(Event<'static>, Span)>> {
+        self.buffer.extend_from_slice(chunk);
+        let len = match str::from_utf8(&self.buffer) {
+            Ok(_) => self.buffer.len(),
+            // The last character may be completed by the next chunk.
+            Err(error) if error.error_len().is_none() => error.valid_up_to(),
+            Err(_) => return Err(invalid_utf8()),
+        };
+        self.read(len, true)
+    }
+
+    /// Signals the end of the input and returns the remaining events.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the input is truncated, such as in the middle of an object or after a key
+    /// without a value, or if the remaining input is otherwise invalid.
+    pub fn finish(mut self) -> Result<Vec<(Event<'static>, Span)>> {
+        if str::from_utf8(&self.buffer).is_err() {
+            return Err(invalid_utf8());
+        }
+        self.read(self.buffer.len(), false)
+    }
+
+    fn read(&mut self, len: usize, partial: bool) -> Result<Vec<(Event<'static>, Span)>> {
+        let text = str::from_utf8(&self.buffer[..len]).expect("input was validated");
+        let base = self.checkpoint.pos;
+        let mut reader = Reader::resume(
+            text,
+            Checkpoint {
+                pos: Position { offset: 0, ..base },
+                ..self.checkpoint
+            },
+            partial,
+        );
+
+        let mut events = Vec::new();
+        for event in reader.by_ref() {
+            let (event, span) =
+                event.map_err(|error| error.map_position(|pos| shift(pos, base.offset)))?;
+            let span = Span {
+                start: shift(span.start, base.offset),
+                end: shift(span.end, base.offset),
+            };
+            events.push((event.into_owned(), span));
+        }
+
+        let checkpoint = reader.checkpoint();
+        self.buffer.drain(..checkpoint.pos.offset);
+        self.checkpoint = Checkpoint {
+            pos: shift(checkpoint.pos, base.offset),
+            ..checkpoint
+        };
+        Ok(events)
+    }
+}
+
+impl Default for PushParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn invalid_utf8() -> Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "stream did not contain valid UTF-8",
+    )
+    .into()
+}
+
+fn shift(pos: Position, offset: usize) -> Position {
+    Position {
+        offset: pos.offset + offset,
+        ..pos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "#base \"base.res\"\n// Comment\r\n\"Käse\" [$WIN32]\n{\n\tKey \"A \\\"quoted\\\" value\"\n}\n";
+
+    #[test]
+    fn chunk_boundaries_do_not_matter() {
+        let expected: Vec<_> = Reader::new(INPUT)
+            .map(|event| event.map(|(event, span)| (event.into_owned(), span)))
+            .collect::<Result<_>>()
+            .unwrap();
+
+        for size in 1..=INPUT.len() {
+            let mut parser = PushParser::new();
+            let mut events = Vec::new();
+            for chunk in INPUT.as_bytes().chunks(size) {
+                events.extend(parser.feed(chunk).unwrap());
+            }
+            events.extend(parser.finish().unwrap());
+            assert_eq!(events, expected, "chunk size {size}");
+        }
+    }
+
+    #[test]
+    fn closing_braces_are_not_held_back() {
+        let mut parser = PushParser::new();
+        let events: Vec<_> = parser
+            .feed(b"a { b 1 }")
+            .unwrap()
+            .into_iter()
+            .map(|(event, _)| event)
+            .collect();
+        assert_eq!(
+            events,
+            [
+                Event::Key("a".into()),
+                Event::BeginObject,
+                Event::Key("b".into()),
+                Event::Value("1".into()),
+                Event::EndObject,
+            ]
+        );
+        assert_eq!(parser.finish().unwrap(), []);
+    }
+
+    #[test]
+    fn multi_line_strings_may_be_split() {
+        let mut parser = PushParser::new();
+        let mut events = parser.feed(b"Key \"multi\n").unwrap();
+        events.extend(parser.feed(b"line\"").unwrap());
+        events.extend(parser.finish().unwrap());
+        let events: Vec<_> = events.into_iter().map(|(event, _)| event).collect();
+        assert_eq!(
+            events,
+            [Event::Key("Key".into()), Event::Value("multi\nline".into())]
+        );
+    }
+
+    #[test]
+    fn finish_reports_truncation() {
+        for input in ["Key", "Key {", "Key { A B", "\"Key", "#include"] {
+            let mut parser = PushParser::new();
+            parser.feed(input.as_bytes()).unwrap();
+            assert!(parser.finish().is_err(), "{input:?}");
+        }
+
+        let mut parser = PushParser::new();
+        parser
+            .feed("K\u{e9}".as_bytes().split_last().unwrap().1)
+            .unwrap();
+        assert!(matches!(parser.finish(), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn errors_are_located_in_the_whole_input() {
+        let mut parser = PushParser::new();
+        parser.feed(b"A B\nC D\n").unwrap();
+        let err = parser.feed(b"{ }\n").unwrap_err();
+        let pos = err.position().unwrap();
+        assert_eq!((pos.offset, pos.line, pos.column), (8, 3, 1));
+    }
+}
@@ -0,0 +1,389 @@
+use super::lexer::{lex_error, Token};
+use crate::lex::{self, Position, Span, TokenKind};
+use crate::{Error, Result};
+use std::borrow::Cow;
+
+/// Something that was read from KeyValues text by a [`Reader`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Event<'a> {
+    /// The key of a key-value pair. Always followed by [`Event::Value`] or
+    /// [`Event::BeginObject`], possibly with comments and conditionals in between.
+    Key(Cow<'a, str>),
+    /// A string value.
+    Value(Cow<'a, str>),
+    /// The `{` that starts an object value.
+    BeginObject,
+    /// The `}` that ends an object value.
+    EndObject,
+    /// A conditional tag. Only the text between the brackets is stored, such as `$WIN32`.
+    Conditional(Cow<'a, str>),
+    /// A line comment. Only the text after the `//` is stored.
+    Comment(Cow<'a, str>),
+    /// A `#base` or `#include` directive at the root of the document, together with its path.
+    Directive {
+        /// The kind of directive.
+        kind: DirectiveKind,
+        /// The path of the file to include.
+        path: Cow<'a, str>,
+    },
+}
+
+impl Event<'_> {
+    /// Copies any borrowed text so that the event no longer refers to the input.
+    pub fn into_owned(self) -> Event<'static> {
+        let owned = |s: Cow<str>| Cow::Owned(s.into_owned());
+        match self {
+            Event::Key(key) => Event::Key(owned(key)),
+            Event::Value(value) => Event::Value(owned(value)),
+            Event::BeginObject => Event::BeginObject,
+            Event::EndObject => Event::EndObject,
+            Event::Conditional(condition) => Event::Conditional(owned(condition)),
+            Event::Comment(comment) => Event::Comment(owned(comment)),
+            Event::Directive { kind, path } => Event::Directive {
+                kind,
+                path: owned(path),
+            },
+        }
+    }
+}
+
+/// The kind of an [`Event::Directive`].
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum DirectiveKind {
+    /// `#base`, which fills in keys that are missing from the including file.
+    Base,
+    /// `#include`, which appends the entries of the included file.
+    Include,
+}
+
+/// A pull parser that reads KeyValues text as a sequence of [`Event`]s.
+///
+/// Unlike [`Deserializer`](super::Deserializer), a reader never builds a tree of the input, so
+/// it uses the same small amount of memory no matter how large the input is. Each event is
+/// returned with the [`Span`] of the text it was read from.
+///
+/// The structure of the input is checked as it is read: every key has a value and every `{`
+/// has a matching `}`. The first error ends the iteration.
+///
+/// ```
+/// use vdflex::de::{Event, Reader};
+///
+/// let input = r#"
+///     "Shader" // a comment
+///     {
+///         "$basetexture" "gravel01" [$WIN32]
+///     }
+/// "#;
+/// let events = Reader::new(input)
+///     .map(|event| event.map(|(event, _span)| event))
+///     .collect::<vdflex::Result<Vec<_>>>()
+///     .unwrap();
+/// assert_eq!(
+///     events,
+///     [
+///         Event::Key("Shader".into()),
+///         Event::Comment(" a comment".into()),
+///         Event::BeginObject,
+///         Event::Key("$basetexture".into()),
+///         Event::Value("gravel01".into()),
+///         Event::Conditional("$WIN32".into()),
+///         Event::EndObject,
+///     ]
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct Reader<'a> {
+    input: &'a str,
+    tokens: lex::Lexer<'a>,
+    peeked: Option<lex::Token<'a>>,
+    expect_value: bool,
+    partial: bool,
+    stalled: Option<Checkpoint>,
+    done: bool,
+}
+
+/// The state a [`Reader`] needs to continue reading where another one stopped.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Checkpoint {
+    /// The position of the first token that was not read.
+    pub pos: Position,
+    /// The number of objects that are open at `pos`.
+    pub depth: usize,
+    /// Whether a value is expected at `pos`.
+    pub expect_value: bool,
+}
+
+impl Checkpoint {
+    pub const START: Checkpoint = Checkpoint {
+        pos: Position::START,
+        depth: 0,
+        expect_value: false,
+    };
+}
+
+impl<'a> Reader<'a> {
+    /// Creates a reader over the given input.
+    pub fn new(input: &'a str) -> Self {
+        Self::resume(input, Checkpoint::START, false)
+    }
+
+    /// Creates a reader that continues from `checkpoint`.
+    ///
+    /// If `partial` is true, `input` may be cut off at any point. The reader then stops at the end
+    /// of the input without reporting it, and before a last token that might continue in text
+    /// that has not been received yet. [`Reader::checkpoint`] tells where to continue.
+    pub(crate) fn resume(input: &'a str, checkpoint: Checkpoint, partial: bool) -> Self {
+        Self {
+            input,
+            tokens: lex::Lexer::resume(input, checkpoint.pos, checkpoint.depth),
+            peeked: None,
+            expect_value: checkpoint.expect_value,
+            partial,
+            stalled: None,
+            done: false,
+        }
+    }
+
+    /// Returns the number of objects that are currently open.
+    pub fn depth(&self) -> usize {
+        self.tokens.depth()
+    }
+
+    /// Returns where to continue reading once a partial reader has stopped.
+    pub(crate) fn checkpoint(&self) -> Checkpoint {
+        self.stalled.unwrap_or(Checkpoint {
+            pos: self.tokens.position(),
+            depth: self.depth(),
+            expect_value: self.expect_value,
+        })
+    }
+
+    /// Returns the next token that is not whitespace or a line break.
+    fn next_token(&mut self) -> Option<lex::Token<'a>> {
+        if let Some(token) = self.peeked.take() {
+            return Some(token);
+        }
+
+        loop {
+            let Some(token) = self.tokens.next() else {
+                if self.partial {
+                    // Whatever follows has not been received yet.
+                    self.stall(self.tokens.position(), self.depth());
+                }
+                return None;
+            };
+            if self.partial && self.may_continue(&token) {
+                let depth = match token.kind {
+                    TokenKind::OpenBrace => self.depth() - 1,
+                    TokenKind::CloseBrace => self.depth() + 1,
+                    _ => self.depth(),
+                };
+                self.stall(token.span.start, depth);
+                return None;
+            }
+            if !matches!(token.kind, TokenKind::Whitespace | TokenKind::Newline) {
+                return Some(token);
+            }
+        }
+    }
+
+    /// Returns `true` if `token` might turn out differently once the rest of a partial input has
+    /// been received.
+    fn may_continue(&self, token: &lex::Token) -> bool {
+        let rest = &self.input[token.span.end.offset..];
+        match token.kind {
+            // A `\"` only closes a string if no other `"` follows it on the same line.
+            TokenKind::QuotedString | TokenKind::Directive if token.text.ends_with("\\\"") => {
+                !rest.contains('\n')
+            }
+            // Quoted strings may span lines, so the closing quote may not have arrived yet.
+            TokenKind::Error(lex::LexError::UnterminatedString) => true,
+            // These tokens may be longer than the part of them that has been received.
+            TokenKind::UnquotedString
+            | TokenKind::Directive
+            | TokenKind::Conditional
+            | TokenKind::Comment
+            | TokenKind::Error(lex::LexError::UnterminatedConditional) => rest.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Stops a partial reader so that it continues at `pos` next time.
+    fn stall(&mut self, pos: Position, depth: usize) {
+        self.stalled = Some(Checkpoint {
+            pos,
+            depth,
+            expect_value: self.expect_value,
+        });
+    }
+
+    /// Reads the path of a directive if it comes next, or leaves the next token for later.
+    fn directive_path(&mut self) -> Option<lex::Token<'a>> {
+        let token = self.next_token()?;
+        match token.kind {
+            TokenKind::QuotedString | TokenKind::UnquotedString | TokenKind::Directive => {
+                Some(token)
+            }
+            _ => {
+                self.peeked = Some(token);
+                None
+            }
+        }
+    }
+
+    fn fail(&mut self, error: Error) -> Option<Result<(Event<'a>, Span)>> {
+        self.done = true;
+        Some(Err(error))
+    }
+
+    fn unexpected(
+        &mut self,
+        expected: &'static str,
+        found: Token,
+        pos: Position,
+    ) -> Option<Result<(Event<'a>, Span)>> {
+        self.fail(
+            Error::UnexpectedToken {
+                expected,
+                found: found.to_string(),
+            }
+            .at(pos),
+        )
+    }
+}
+
+impl<'a> Iterator for Reader<'a> {
+    type Item = Result<(Event<'a>, Span)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let Some(token) = self.next_token() else {
+            if !self.partial && (self.expect_value || self.depth() > 0) {
+                return self.fail(Error::Eof.at(self.tokens.position()));
+            }
+            self.done = true;
+            return None;
+        };
+
+        let event = match token.kind {
+            TokenKind::Comment => Event::Comment(Cow::Borrowed(&token.text[2..])),
+            TokenKind::Conditional => {
+                Event::Conditional(Cow::Borrowed(&token.text[1..token.text.len() - 1]))
+            }
+            TokenKind::QuotedString | TokenKind::UnquotedString | TokenKind::Directive
+                if self.expect_value =>
+            {
+                self.expect_value = false;
+                Event::Value(token.value())
+            }
+            TokenKind::Directive if self.depth() == 0 => match self.directive_path() {
+                Some(path) => {
+                    let kind = if token.value().eq_ignore_ascii_case("#base") {
+                        DirectiveKind::Base
+                    } else {
+                        DirectiveKind::Include
+                    };
+                    let span = Span {
+                        start: token.span.start,
+                        end: path.span.end,
+                    };
+                    let path = path.value();
+                    return Some(Ok((Event::Directive { kind, path }, span)));
+                }
+                None if self.stalled.is_some() => {
+                    // The path may still be on its way, so read the directive again later.
+                    self.stall(token.span.start, 0);
+                    self.done = true;
+                    return None;
+                }
+                None => {
+                    self.expect_value = true;
+                    Event::Key(token.value())
+                }
+            },
+            TokenKind::QuotedString | TokenKind::UnquotedString | TokenKind::Directive => {
+                self.expect_value = true;
+                Event::Key(token.value())
+            }
+            TokenKind::OpenBrace if self.expect_value => {
+                self.expect_value = false;
+                Event::BeginObject
+            }
+            TokenKind::OpenBrace => {
+                return self.unexpected("a key", Token::OpenBrace, token.span.start)
+            }
+            TokenKind::CloseBrace if self.expect_value => {
+                return self.unexpected("a value", Token::CloseBrace, token.span.start)
+            }
+            TokenKind::CloseBrace => Event::EndObject,
+            TokenKind::Error(error) => return self.fail(lex_error(error).at(token.span.start)),
+            TokenKind::Whitespace | TokenKind::Newline => unreachable!("whitespace is skipped"),
+        };
+
+        Some(Ok((event, token.span)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(input: &str) -> Result<Vec<Event<'_>>> {
+        Reader::new(input)
+            .map(|event| event.map(|(event, _)| event))
+            .collect()
+    }
+
+    #[test]
+    fn read_directives() {
+        let input = "#base \"base.res\"\n\"#include\"\n\"extra.vdf\"\nKey { #base nested }";
+        let mut reader = Reader::new(input);
+        let (event, span) = reader.next().unwrap().unwrap();
+        assert_eq!(
+            event,
+            Event::Directive {
+                kind: DirectiveKind::Base,
+                path: "base.res".into()
+            }
+        );
+        assert_eq!(&input[span.range()], "#base \"base.res\"");
+
+        assert_eq!(
+            events(input).unwrap()[1..],
+            [
+                Event::Directive {
+                    kind: DirectiveKind::Include,
+                    path: "extra.vdf".into()
+                },
+                Event::Key("Key".into()),
+                Event::BeginObject,
+                Event::Key("#base".into()),
+                Event::Value("nested".into()),
+                Event::EndObject,
+            ]
+        );
+    }
+
+    #[test]
+    fn read_errors() {
+        for (input, line, column) in [
+            ("Key", 1, 4),
+            ("Key {", 1, 6),
+            ("{ }", 1, 1),
+            ("Key { A }", 1, 9),
+            ("Key Value }", 1, 11),
+            ("Key \"Value", 1, 5),
+        ] {
+            let err = events(input).unwrap_err();
+            let pos = err.position().unwrap();
+            assert_eq!((pos.line, pos.column), (line, column), "{input:?}: {err}");
+        }
+
+        let mut reader = Reader::new("{ Key Value");
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+}
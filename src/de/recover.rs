@@ -0,0 +1,279 @@
+use super::MAX_DEPTH;
+use crate::lex::{self, LexError, Position, Span, TokenKind};
+use crate::{KeyValues, Object, Value};
+use std::collections::VecDeque;
+use std::fmt;
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Severity {
+    /// The input is probably fine, but some of it was ignored.
+    Warning,
+    /// The input is malformed. The parser guessed what was meant, but the result may not match
+    /// what the author intended.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => f.write_str("warning"),
+            Severity::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// A problem found by [`parse_with_diagnostics`](super::parse_with_diagnostics).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    /// How serious the problem is.
+    pub severity: Severity,
+    /// The region of the input that caused the problem.
+    pub span: Span,
+    /// A description of the problem.
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at {}: {}",
+            self.severity, self.span.start, self.message
+        )
+    }
+}
+
+/// A KeyValues parser that keeps going after malformed input, recording a [`Diagnostic`] for
+/// every problem it recovers from.
+pub(crate) struct Parser<'a> {
+    input: &'a str,
+    tokens: lex::Lexer<'a>,
+    lookahead: VecDeque<lex::Token<'a>>,
+    diagnostics: Vec<Diagnostic>,
+    /// The number of objects that are currently being parsed, not counting the root object.
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            tokens: lex::Lexer::new(input),
+            lookahead: VecDeque::new(),
+            diagnostics: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    /// Parses the whole input, returning whatever could be salvaged.
+    pub fn parse(mut self) -> (KeyValues, Vec<Diagnostic>) {
+        let root = self.object(None);
+        (KeyValues { root }, self.diagnostics)
+    }
+
+    /// Parses the entries of an object up to its closing brace. `open` is the span of the
+    /// object's opening brace, or `None` for the root object.
+    fn object(&mut self, open: Option<Span>) -> Object {
+        let mut object = Object::new();
+
+        loop {
+            let Some(token) = self.next() else {
+                if let Some(open) = open {
+                    self.error(open, "object is never closed (missing `}`)");
+                }
+                break;
+            };
+
+            let key = match token.kind {
+                TokenKind::QuotedString | TokenKind::UnquotedString | TokenKind::Directive => {
+                    token.value().into_owned()
+                }
+                TokenKind::Error(LexError::UnterminatedString) => self.unterminated(token),
+                TokenKind::CloseBrace => break,
+                TokenKind::Error(LexError::UnmatchedCloseBrace) => {
+                    self.error(token.span, "unmatched `}`");
+                    continue;
+                }
+                TokenKind::OpenBrace => {
+                    self.error(token.span, "expected a key, but found `{`");
+                    self.nested(token.span);
+                    continue;
+                }
+                TokenKind::Conditional => {
+                    self.warning(token.span, "conditional tag has no key and was ignored");
+                    continue;
+                }
+                TokenKind::Error(LexError::UnterminatedConditional) => {
+                    self.warning(token.span, "unterminated conditional tag was ignored");
+                    continue;
+                }
+                TokenKind::Whitespace | TokenKind::Newline | TokenKind::Comment => {
+                    unreachable!("trivia is skipped")
+                }
+            };
+
+            if let Some(value) = self.value(&key, token.span) {
+                object.entry(key).or_default().push(value);
+            }
+        }
+
+        object
+    }
+
+    /// Parses a nested object whose opening brace at `open` was just consumed, or skips it and
+    /// returns `None` if objects are nested too deeply.
+    fn nested(&mut self, open: Span) -> Option<Object> {
+        if self.depth == MAX_DEPTH {
+            self.error(
+                open,
+                format!("objects are nested more than {MAX_DEPTH} levels deep (skipped)"),
+            );
+            let mut depth = 1;
+            while depth > 0 {
+                match self.next().map(|token| token.kind) {
+                    Some(TokenKind::OpenBrace) => depth += 1,
+                    Some(TokenKind::CloseBrace) => depth -= 1,
+                    Some(_) => {}
+                    None => {
+                        self.error(open, "object is never closed (missing `}`)");
+                        break;
+                    }
+                }
+            }
+            return None;
+        }
+
+        self.depth += 1;
+        let object = self.object(Some(open));
+        self.depth -= 1;
+        Some(object)
+    }
+
+    /// Parses the value of the key at `key_span`, or returns `None` if it has no value (or its
+    /// value was skipped).
+    fn value(&mut self, key: &str, key_span: Span) -> Option<Value> {
+        self.skip_conditionals();
+
+        let missing_value = match self.peek(0) {
+            None => true,
+            Some(token) => match token.kind {
+                TokenKind::CloseBrace | TokenKind::Error(LexError::UnmatchedCloseBrace) => true,
+                // A string on a later line that is followed by a value on its own line is
+                // probably the next key rather than this key's value.
+                _ if is_string(token.kind) && token.span.start.line > key_span.start.line => {
+                    self.peek(1).is_some_and(|next| {
+                        (is_string(next.kind) || next.kind == TokenKind::OpenBrace)
+                            && next.span.start.line == token.span.start.line
+                    })
+                }
+                _ => false,
+            },
+        };
+        if missing_value {
+            self.error(key_span, format!("key `{key}` has no value"));
+            return None;
+        }
+
+        let token = self.next()?;
+        let value = match token.kind {
+            TokenKind::OpenBrace => self.nested(token.span).map(Value::Object),
+            TokenKind::Error(LexError::UnterminatedString) => {
+                Some(Value::String(self.unterminated(token)))
+            }
+            _ => Some(Value::String(token.value().into_owned())),
+        };
+
+        self.skip_conditionals();
+        value
+    }
+
+    /// Recovers the contents of an unterminated string, which extends to the end of the line.
+    fn unterminated(&mut self, token: lex::Token) -> String {
+        self.error(token.span, "unterminated string (missing `\"`)");
+        lex::unescape(&token.text[1..]).into_owned()
+    }
+
+    fn skip_conditionals(&mut self) {
+        while let Some(token) = self.peek(0) {
+            match token.kind {
+                TokenKind::Conditional => {}
+                TokenKind::Error(LexError::UnterminatedConditional) => {
+                    self.warning(token.span, "unterminated conditional tag was ignored");
+                }
+                _ => break,
+            }
+            self.next();
+        }
+    }
+
+    fn next(&mut self) -> Option<lex::Token<'a>> {
+        self.lookahead.pop_front().or_else(|| self.lex())
+    }
+
+    /// Returns the `n`th significant token after the current one without consuming it.
+    fn peek(&mut self, n: usize) -> Option<lex::Token<'a>> {
+        while self.lookahead.len() <= n {
+            let token = self.lex()?;
+            self.lookahead.push_back(token);
+        }
+        Some(self.lookahead[n])
+    }
+
+    /// Lexes the next significant token.
+    ///
+    /// A quoted string that spans several lines is far more likely to be missing its closing
+    /// quote than to contain a line break on purpose, so it is cut off at the end of its first
+    /// line and lexing resumes on the next line.
+    fn lex(&mut self) -> Option<lex::Token<'a>> {
+        let token = self.tokens.find(|token| !token.is_trivia())?;
+        if token.kind != TokenKind::QuotedString {
+            return Some(token);
+        }
+        let Some(len) = token.text.find(['\r', '\n']) else {
+            return Some(token);
+        };
+
+        let text = &token.text[..len];
+        let end = Position {
+            offset: token.span.start.offset + len,
+            line: token.span.start.line,
+            column: token.span.start.column + text.chars().count(),
+        };
+        self.tokens = lex::Lexer::resume(self.input, end, self.tokens.depth());
+        Some(lex::Token {
+            kind: TokenKind::Error(LexError::UnterminatedString),
+            text,
+            span: Span {
+                start: token.span.start,
+                end,
+            },
+        })
+    }
+
+    fn error(&mut self, span: Span, message: impl Into<String>) {
+        self.diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            span,
+            message: message.into(),
+        });
+    }
+
+    fn warning(&mut self, span: Span, message: impl Into<String>) {
+        self.diagnostics.push(Diagnostic {
+            severity: Severity::Warning,
+            span,
+            message: message.into(),
+        });
+    }
+}
+
+fn is_string(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::QuotedString
+            | TokenKind::UnquotedString
+            | TokenKind::Directive
+            | TokenKind::Error(LexError::UnterminatedString)
+    )
+}
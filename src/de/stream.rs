@@ -0,0 +1,324 @@
+use super::reader::{Checkpoint, Event, Reader};
+use super::{DeserializeOpts, Deserializer};
+use crate::lex::Position;
+use crate::{Error, Result};
+use serde::de::DeserializeOwned;
+use std::io::{self, Read};
+use std::marker::PhantomData;
+use std::str;
+
+/// The smallest number of bytes to read from the reader at once.
+const MIN_READ: usize = 8 * 1024;
+
+/// An iterator that deserializes the root-level key-value pairs of a KeyValues document one at a
+/// time.
+///
+/// Documents such as VMF files have many root keys. Instead of reading the whole document into
+/// memory, this reads just enough of `R` to find the end of the next root entry and
+/// deserializes its value as some type `T`. Repeated keys are returned separately, in document
+/// order, and entries excluded by a conditional tag are skipped.
+///
+/// ```
+/// use serde::Deserialize;
+/// use vdflex::de::StreamDeserializer;
+///
+/// #[derive(Deserialize)]
+/// struct Entity {
+///     classname: String,
+/// }
+///
+/// let input = r#"
+///     entity { classname light }
+///     entity { classname info_player_start }
+///     entity { classname prop_static }
+/// "#;
+/// let spawn = StreamDeserializer::<_, Entity>::new(input.as_bytes())
+///     .map(|entry| entry.unwrap().1)
+///     .find(|entity| entity.classname == "info_player_start");
+/// assert!(spawn.is_some());
+/// ```
+pub struct StreamDeserializer<R, T> {
+    reader: R,
+    opts: DeserializeOpts,
+    /// The input that has been read but not deserialized yet.
+    buffer: Vec<u8>,
+    /// Where `buffer` starts in the whole input.
+    offset: usize,
+    /// Where to continue looking for the end of the current entry. The offset is relative to
+    /// `buffer`.
+    checkpoint: Checkpoint,
+    /// Where the current entry starts and ends, if that is known yet.
+    start: Option<Position>,
+    end: Option<Position>,
+    eof: bool,
+    done: bool,
+    output: PhantomData<fn() -> T>,
+}
+
+impl<R: Read, T: DeserializeOwned> StreamDeserializer<R, T> {
+    /// Creates a stream deserializer that reads KeyValues text from `reader`.
+    pub fn new(reader: R) -> Self {
+        Self::with_opts(reader, DeserializeOpts::default())
+    }
+
+    /// Creates a stream deserializer that reads KeyValues text from `reader` with the specified
+    /// `opts`.
+    pub fn with_opts(reader: R, opts: DeserializeOpts) -> Self {
+        Self {
+            reader,
+            opts,
+            buffer: Vec::new(),
+            offset: 0,
+            checkpoint: Checkpoint::START,
+            start: None,
+            end: None,
+            eof: false,
+            done: false,
+            output: PhantomData,
+        }
+    }
+
+    /// Returns the number of bytes that have been deserialized so far.
+    pub fn byte_offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Reads the next root entry from the input, or `None` if there are no more entries.
+    fn next_entry(&mut self) -> Result<Option<(String, T)>> {
+        loop {
+            match self.find_end()? {
+                Some((start, end)) => {
+                    if let Some(entry) = self.deserialize(start, end)? {
+                        return Ok(Some(entry));
+                    }
+                }
+                None if self.eof => return Ok(None),
+                None => self.fill()?,
+            }
+        }
+    }
+
+    /// Looks for the end of the current entry in the input read so far. Returns `None` if the
+    /// input ended first.
+    fn find_end(&mut self) -> Result<Option<(Position, Position)>> {
+        let text = match str::from_utf8(&self.buffer) {
+            Ok(text) => text,
+            // The last character may be completed by the next read.
+            Err(error) if !self.eof && error.error_len().is_none() => {
+                str::from_utf8(&self.buffer[..error.valid_up_to()]).expect("input is valid")
+            }
+            Err(_) => return Err(invalid_utf8()),
+        };
+
+        let offset = self.offset;
+        let mut reader = Reader::resume(text, self.checkpoint, !self.eof);
+        while let Some(event) = reader.next() {
+            let (event, span) = event.map_err(|error| {
+                error.map_position(|pos| Position {
+                    offset: pos.offset + offset,
+                    ..pos
+                })
+            })?;
+            match (event, self.end) {
+                (Event::Comment(_), _) => {}
+                // Conditional tags may follow either the key or the value.
+                (Event::Conditional(_), Some(_)) => self.end = Some(span.end),
+                // The next entry has started, so the current one is complete.
+                (_, Some(end)) => {
+                    let start = self.start.expect("entry has a start");
+                    return Ok(Some((start, end)));
+                }
+                (Event::Directive { .. }, None) => {
+                    self.start = Some(span.start);
+                    self.end = Some(span.end);
+                }
+                (Event::Value(_) | Event::EndObject, None) if reader.depth() == 0 => {
+                    self.end = Some(span.end);
+                }
+                _ => {
+                    self.start.get_or_insert(span.start);
+                }
+            }
+        }
+
+        self.checkpoint = reader.checkpoint();
+        match (self.start, self.end) {
+            (Some(start), Some(end)) if self.eof => Ok(Some((start, end))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Deserializes the entry between `start` and `end` and removes it from the buffer. Returns
+    /// `None` if the entry is excluded by a conditional tag.
+    fn deserialize(&mut self, start: Position, end: Position) -> Result<Option<(String, T)>> {
+        let text = str::from_utf8(&self.buffer[start.offset..end.offset]).expect("input is valid");
+        let base = Position {
+            offset: start.offset + self.offset,
+            ..start
+        };
+        let locate = |error: Error| error.map_position(|pos| translate(pos, base));
+
+        let deserializer = Deserializer::with_opts(text, self.opts.clone());
+        let entry = match deserializer.first_root_entry().map_err(locate)? {
+            Some((key, value)) => {
+                let position = value.position();
+                let value =
+                    T::deserialize(value).map_err(|err| locate(err.at(position)).in_key(&key))?;
+                Some((key.into_owned(), value))
+            }
+            None => None,
+        };
+
+        self.buffer.drain(..end.offset);
+        self.offset += end.offset;
+        self.checkpoint = Checkpoint {
+            pos: Position { offset: 0, ..end },
+            ..Checkpoint::START
+        };
+        self.start = None;
+        self.end = None;
+        Ok(entry)
+    }
+
+    /// Reads more input into the buffer.
+    fn fill(&mut self) -> Result<()> {
+        let len = self.buffer.len();
+        self.buffer.resize(len + len.max(MIN_READ), 0);
+        let read = loop {
+            match self.reader.read(&mut self.buffer[len..]) {
+                Ok(read) => break read,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => {
+                    self.buffer.truncate(len);
+                    return Err(error.into());
+                }
+            }
+        };
+        self.buffer.truncate(len + read);
+        self.eof = read == 0;
+        Ok(())
+    }
+}
+
+impl<R: Read, T: DeserializeOwned> Iterator for StreamDeserializer<R, T> {
+    type Item = Result<(String, T)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let entry = self.next_entry();
+        self.done = !matches!(entry, Ok(Some(_)));
+        entry.transpose()
+    }
+}
+
+fn invalid_utf8() -> Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "stream did not contain valid UTF-8",
+    )
+    .into()
+}
+
+/// Translates a position in text that starts at `base` into a position in the whole input.
+fn translate(pos: Position, base: Position) -> Position {
+    Position {
+        offset: base.offset + pos.offset,
+        line: base.line + pos.line - 1,
+        column: if pos.line == 1 {
+            base.column + pos.column - 1
+        } else {
+            pos.column
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Value;
+    use std::collections::{HashMap, HashSet};
+
+    /// A reader that returns at most one byte at a time.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let Some((first, rest)) = self.0.split_first() else {
+                return Ok(0);
+            };
+            buf[0] = *first;
+            self.0 = rest;
+            Ok(1)
+        }
+    }
+
+    const VMF: &str = r#"
+        versioninfo { "editorversion" "400" } // comment
+        "visgroups" { }
+        entity [$WIN32] { "classname" "light" }
+        entity { "classname" "prop_static" } [$LINUX]
+        #include "extra.vmf"
+        "cameras"
+        {
+            "activecamera" "-1"
+        }
+    "#;
+
+    fn keys(stream: impl Iterator<Item = Result<(String, Value)>>) -> Vec<String> {
+        stream.map(|entry| entry.unwrap().0).collect()
+    }
+
+    #[test]
+    fn stream_root_entries() {
+        let all = [
+            "versioninfo",
+            "visgroups",
+            "entity",
+            "entity",
+            "#include",
+            "cameras",
+        ];
+        assert_eq!(keys(StreamDeserializer::new(VMF.as_bytes())), all);
+        assert_eq!(keys(StreamDeserializer::new(Trickle(VMF.as_bytes()))), all);
+
+        let opts = DeserializeOpts {
+            symbols: Some(HashSet::from([String::from("$LINUX")])),
+            ..Default::default()
+        };
+        let stream = StreamDeserializer::with_opts(Trickle(VMF.as_bytes()), opts);
+        assert_eq!(
+            keys(stream),
+            ["versioninfo", "visgroups", "entity", "#include", "cameras"]
+        );
+    }
+
+    #[test]
+    fn stream_multi_line_strings() {
+        let input = "a { k \"multi\nline\" }\nb { k v }";
+        let stream =
+            StreamDeserializer::<_, HashMap<String, String>>::new(Trickle(input.as_bytes()));
+        let entries: Vec<_> = stream.map(|entry| entry.unwrap()).collect();
+        assert_eq!(entries[0].1["k"], "multi\nline");
+        assert_eq!(entries[1].1["k"], "v");
+    }
+
+    #[test]
+    fn stream_errors_are_located_in_the_whole_input() {
+        let input = "a { b 1 }\nc { d x }\ne {";
+        let mut stream =
+            StreamDeserializer::<_, HashMap<String, u8>>::new(Trickle(input.as_bytes()));
+        assert_eq!(stream.next().unwrap().unwrap().1["b"], 1);
+
+        let err = stream.next().unwrap().unwrap_err();
+        let pos = err.position().unwrap();
+        assert_eq!((pos.offset, pos.line, pos.column), (16, 2, 7));
+        assert_eq!(err.path(), Some("c.d"));
+        assert!(stream.next().is_none());
+
+        let mut stream = StreamDeserializer::<_, Value>::new(&input.as_bytes()[20..]);
+        assert!(matches!(stream.next(), Some(Err(_))));
+    }
+}
@@ -0,0 +1,474 @@
+use serde::de::value::StringDeserializer;
+use serde::de::{
+    self, DeserializeSeed, Deserializer, EnumAccess, IntoDeserializer, MapAccess, VariantAccess,
+    Visitor,
+};
+use serde::ser::{
+    self, Impossible, SerializeMap, SerializeStruct, SerializeStructVariant, Serializer,
+};
+use serde::{forward_to_deserialize_any, Deserialize, Serialize};
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::result;
+
+/// Every entry of an object in document order, where each key names a variant of `T`.
+///
+/// Structs and maps group the values of repeated keys together, which loses the order of keys
+/// that are interleaved with each other. `Entries` keeps that order. `T` is usually an enum whose
+/// variants are named after the keys, using the same representation as a newtype or struct
+/// variant. A `#[serde(other)]` unit variant can be used to skip unknown keys.
+///
+/// Keys are matched against variant names exactly, even when
+/// [`DeserializeOpts::case_insensitive`](crate::de::DeserializeOpts::case_insensitive) is set;
+/// use `#[serde(alias = "...")]` to accept other spellings. Tuple variants cannot be serialized,
+/// since their fields would be written as repeated keys that read back as separate entries.
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use vdflex::Entries;
+///
+/// #[derive(Debug, PartialEq, Serialize, Deserialize)]
+/// enum SearchPath {
+///     Game(String),
+///     Mod(String),
+///     Platform(String),
+/// }
+///
+/// let paths: Entries<SearchPath> = vdflex::from_str(r#"
+///     "Game" "tf"
+///     "Platform" "platform"
+///     "Game" "hl2"
+/// "#).unwrap();
+/// assert_eq!(paths[1], SearchPath::Platform(String::from("platform")));
+/// assert_eq!(paths.len(), 3);
+///
+/// let text = vdflex::to_string(&paths).unwrap();
+/// assert_eq!(vdflex::from_str::<Entries<SearchPath>>(&text).unwrap(), paths);
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Entries<T>(pub Vec<T>);
+
+impl<T> Entries<T> {
+    /// Creates an empty list of entries.
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<T> Deref for Entries<T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Entries<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T> From<Vec<T>> for Entries<T> {
+    fn from(entries: Vec<T>) -> Self {
+        Self(entries)
+    }
+}
+
+impl<T> FromIterator<T> for Entries<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<T> IntoIterator for Entries<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Entries<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<T: Serialize> Serialize for Entries<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for entry in &self.0 {
+            let key = entry.serialize(VariantName).map_err(ser::Error::custom)?;
+            map.serialize_entry(key, &Contents(entry))?;
+        }
+        map.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Entries<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+        // `deserialize_any` visits every entry in document order, without grouping repeated keys.
+        deserializer.deserialize_any(EntriesVisitor(PhantomData))
+    }
+}
+
+struct EntriesVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for EntriesVisitor<T> {
+    type Value = Entries<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> result::Result<Self::Value, A::Error> {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(key) = map.next_key::<String>()? {
+            entries.push(map.next_value_seed(EntrySeed {
+                key,
+                marker: PhantomData,
+            })?);
+        }
+        Ok(Entries(entries))
+    }
+}
+
+/// Deserializes the value of the entry with the given key as a `T`.
+struct EntrySeed<T> {
+    key: String,
+    marker: PhantomData<T>,
+}
+
+impl<'de, T: Deserialize<'de>> DeserializeSeed<'de> for EntrySeed<T> {
+    type Value = T;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> result::Result<T, D::Error> {
+        T::deserialize(EntryDeserializer {
+            key: self.key,
+            value: deserializer,
+        })
+    }
+}
+
+/// Deserializes an entry as an enum variant named after its key.
+struct EntryDeserializer<D> {
+    key: String,
+    value: D,
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for EntryDeserializer<D> {
+    type Error = D::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> result::Result<V::Value, D::Error> {
+        visitor.visit_enum(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de, D: Deserializer<'de>> EnumAccess<'de> for EntryDeserializer<D> {
+    type Error = D::Error;
+    type Variant = VariantDeserializer<D>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> result::Result<(V::Value, Self::Variant), D::Error> {
+        let key: StringDeserializer<D::Error> = self.key.into_deserializer();
+        let variant = seed.deserialize(key)?;
+        Ok((variant, VariantDeserializer(self.value)))
+    }
+}
+
+/// Deserializes the contents of a variant from the value of an entry.
+struct VariantDeserializer<D>(D);
+
+impl<'de, D: Deserializer<'de>> VariantAccess<'de> for VariantDeserializer<D> {
+    type Error = D::Error;
+
+    fn unit_variant(self) -> result::Result<(), D::Error> {
+        de::IgnoredAny::deserialize(self.0).map(|_| ())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> result::Result<T::Value, D::Error> {
+        seed.deserialize(self.0)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> result::Result<V::Value, D::Error> {
+        self.0.deserialize_tuple(len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> result::Result<V::Value, D::Error> {
+        self.0.deserialize_struct("", fields, visitor)
+    }
+}
+
+fn not_a_variant<E: ser::Error>(found: &str) -> E {
+    E::custom(format!("entries must be enum variants, but found {found}"))
+}
+
+/// Tuple variants are rejected because their fields would be written as repeated keys.
+fn tuple_variant<E: ser::Error>(variant: &str) -> E {
+    E::custom(format!(
+        "entries cannot be tuple variants, but found `{variant}`"
+    ))
+}
+
+/// Generates `serialize_*` methods that fail because the value is not an enum variant.
+macro_rules! reject_non_variants {
+    () => {
+        reject_non_variants! {
+            serialize_bool(bool) serialize_i8(i8) serialize_i16(i16) serialize_i32(i32)
+            serialize_i64(i64) serialize_i128(i128) serialize_u8(u8) serialize_u16(u16)
+            serialize_u32(u32) serialize_u64(u64) serialize_u128(u128) serialize_f32(f32)
+            serialize_f64(f64) serialize_char(char) serialize_str(&str) serialize_bytes(&[u8])
+            serialize_unit_struct(&'static str)
+        }
+
+        fn serialize_none(self) -> result::Result<Self::Ok, Self::Error> {
+            Err(not_a_variant("none"))
+        }
+
+        fn serialize_some<T: ?Sized + Serialize>(
+            self,
+            _value: &T,
+        ) -> result::Result<Self::Ok, Self::Error> {
+            Err(not_a_variant("some"))
+        }
+
+        fn serialize_unit(self) -> result::Result<Self::Ok, Self::Error> {
+            Err(not_a_variant("unit"))
+        }
+
+        fn serialize_newtype_struct<T: ?Sized + Serialize>(
+            self,
+            _name: &'static str,
+            _value: &T,
+        ) -> result::Result<Self::Ok, Self::Error> {
+            Err(not_a_variant("newtype struct"))
+        }
+
+        fn serialize_seq(
+            self,
+            _len: Option<usize>,
+        ) -> result::Result<Self::SerializeSeq, Self::Error> {
+            Err(not_a_variant("sequence"))
+        }
+
+        fn serialize_tuple(self, _len: usize) -> result::Result<Self::SerializeTuple, Self::Error> {
+            Err(not_a_variant("tuple"))
+        }
+
+        fn serialize_tuple_struct(
+            self,
+            _name: &'static str,
+            _len: usize,
+        ) -> result::Result<Self::SerializeTupleStruct, Self::Error> {
+            Err(not_a_variant("tuple struct"))
+        }
+
+        fn serialize_map(
+            self,
+            _len: Option<usize>,
+        ) -> result::Result<Self::SerializeMap, Self::Error> {
+            Err(not_a_variant("map"))
+        }
+
+        fn serialize_struct(
+            self,
+            _name: &'static str,
+            _len: usize,
+        ) -> result::Result<Self::SerializeStruct, Self::Error> {
+            Err(not_a_variant("struct"))
+        }
+    };
+    ($($method:ident($ty:ty))*) => {
+        $(
+            fn $method(self, _value: $ty) -> result::Result<Self::Ok, Self::Error> {
+                Err(not_a_variant(stringify!($ty)))
+            }
+        )*
+    };
+}
+
+/// Returns the name of an enum variant.
+struct VariantName;
+
+impl Serializer for VariantName {
+    type Ok = &'static str;
+    type Error = crate::Error;
+    type SerializeSeq = Impossible<Self::Ok, Self::Error>;
+    type SerializeTuple = Impossible<Self::Ok, Self::Error>;
+    type SerializeTupleStruct = Impossible<Self::Ok, Self::Error>;
+    type SerializeTupleVariant = Impossible<Self::Ok, Self::Error>;
+    type SerializeMap = Impossible<Self::Ok, Self::Error>;
+    type SerializeStruct = Impossible<Self::Ok, Self::Error>;
+    type SerializeStructVariant = NamedVariant;
+
+    reject_non_variants!();
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> crate::Result<Self::Ok> {
+        Ok(variant)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _value: &T,
+    ) -> crate::Result<Self::Ok> {
+        Ok(variant)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> crate::Result<Self::SerializeTupleVariant> {
+        Err(tuple_variant(variant))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> crate::Result<Self::SerializeStructVariant> {
+        Ok(NamedVariant(variant))
+    }
+}
+
+/// Skips the fields of a variant, returning only its name.
+struct NamedVariant(&'static str);
+
+impl SerializeStructVariant for NamedVariant {
+    type Ok = &'static str;
+    type Error = crate::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _key: &'static str,
+        _value: &T,
+    ) -> crate::Result<()> {
+        Ok(())
+    }
+
+    fn end(self) -> crate::Result<Self::Ok> {
+        Ok(self.0)
+    }
+}
+
+/// Serializes the contents of an enum variant, without its name.
+struct Contents<'a, T>(&'a T);
+
+impl<T: Serialize> Serialize for Contents<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        self.0.serialize(ContentsSerializer(serializer))
+    }
+}
+
+struct ContentsSerializer<S>(S);
+
+impl<S: Serializer> Serializer for ContentsSerializer<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = Impossible<Self::Ok, Self::Error>;
+    type SerializeTuple = Impossible<Self::Ok, Self::Error>;
+    type SerializeTupleStruct = Impossible<Self::Ok, Self::Error>;
+    type SerializeTupleVariant = Impossible<Self::Ok, Self::Error>;
+    type SerializeMap = Impossible<Self::Ok, Self::Error>;
+    type SerializeStruct = Impossible<Self::Ok, Self::Error>;
+    type SerializeStructVariant = StructContents<S::SerializeStruct>;
+
+    reject_non_variants!();
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> result::Result<S::Ok, S::Error> {
+        // A unit value would be omitted entirely, which would also remove the key.
+        self.0.serialize_str("")
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> result::Result<S::Ok, S::Error> {
+        value.serialize(self.0)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> result::Result<Self::SerializeTupleVariant, S::Error> {
+        Err(tuple_variant(variant))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> result::Result<Self::SerializeStructVariant, S::Error> {
+        self.0.serialize_struct(variant, len).map(StructContents)
+    }
+}
+
+/// Serializes the fields of a struct variant as a struct.
+struct StructContents<S>(S);
+
+impl<S: SerializeStruct> SerializeStructVariant for StructContents<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> result::Result<(), S::Error> {
+        self.0.serialize_field(key, value)
+    }
+
+    fn end(self) -> result::Result<S::Ok, S::Error> {
+        self.0.end()
+    }
+}
@@ -1,5 +1,6 @@
 //! (De)serialization errors
 
+use crate::lex::Position;
 use std::fmt::Display;
 use std::io;
 use thiserror::Error;
@@ -35,17 +36,17 @@ pub enum Error {
     /// the VMF (Valve map file) format. To allow the library to handle these formats, two families
     /// of ser/de functions are provided: *key-value functions* and *value functions*.
     ///
-    /// Key-value functions like [`crate::kv_to_string`] and `crate::kv_from_string`
+    /// Key-value functions like [`crate::kv_to_string`] and [`crate::kv_from_str`]
     /// operate on a single key-value pair. They are mainly intended for serializing and
     /// deserializing KeyValues files.
     ///
-    /// Value functions like [`crate::to_string`] and `crate::from_string` handle values
+    /// Value functions like [`crate::to_string`] and [`crate::from_str`] handle values
     /// directly, with no enclosing object. These functions can handle multiple root level keys,
     /// as well as incomplete files.
     ///
     /// This error occurs when attempting to deserialize a file that contains multiple root-level
-    /// keys with a value function. When this happens, either the file was malformed or you should
-    /// have used a value function.
+    /// keys with a key-value function. When this happens, either the file was malformed or you
+    /// should have used a value function.
     #[error("tried to deserialize multiple root keys (try `from_str` or `from_reader`)")]
     MultipleRootKeys,
 
@@ -124,9 +125,292 @@ pub enum Error {
     #[error("key must be a string, but it was a `{0}`")]
     KeyMustBeAString(String),
 
+    /// Indicates that the input ended before a complete KeyValues document was read.
+    #[error("unexpected end of input")]
+    Eof,
+
+    /// Indicates that the input contained a token where it is not allowed, such as a `{` in
+    /// place of a key.
+    #[error("expected {expected}, but found {found}")]
+    UnexpectedToken {
+        /// A description of what was expected instead.
+        expected: &'static str,
+        /// A description of the token that was found.
+        found: String,
+    },
+
+    /// Indicates that a quoted string was never closed.
+    #[error("unterminated string")]
+    UnterminatedString,
+
+    /// Indicates that a conditional tag (such as `[$WIN32]`) was not closed before the end of
+    /// the line.
+    #[error("unterminated conditional tag")]
+    UnterminatedConditional,
+
+    /// Indicates that a conditional tag is not a valid expression. See [`crate::conditional`]
+    /// for the syntax of conditions.
+    #[error("invalid conditional tag `{condition}`: {reason}")]
+    InvalidCondition {
+        /// The text of the conditional tag.
+        condition: String,
+        /// What is wrong with it.
+        reason: &'static str,
+    },
+
+    /// Indicates that objects (or KV3 arrays) are nested more deeply than the deserializer
+    /// allows. The limit keeps corrupt or malicious input from overflowing the stack.
+    #[error("objects are nested more than {0} levels deep")]
+    NestedTooDeeply(usize),
+
+    /// Indicates that a key appeared more than once, but only one value was expected.
+    ///
+    /// Whether this is an error can be configured with
+    /// [`DeserializeOpts::duplicate_keys`](crate::de::DeserializeOpts::duplicate_keys).
+    #[error("key `{key}` is repeated (first at {first}, again at {second})")]
+    DuplicateKey {
+        /// The repeated key.
+        key: String,
+        /// The position of the first occurrence of the key.
+        first: Position,
+        /// The position of the second occurrence of the key.
+        second: Position,
+    },
+
+    /// Indicates that nothing exists at the key path passed to [`crate::de::from_str_at`].
+    /// Contains the path up to and including the first key that could not be found.
+    #[error("nothing found at `{0}`")]
+    PathNotFound(String),
+
+    /// Indicates that a `#include` or `#base` directive (directly or indirectly) includes the
+    /// file it is in. See [`crate::include::Loader`].
+    #[error("`{0}` includes itself")]
+    IncludeCycle(String),
+
+    /// Indicates that `#include` and `#base` directives were nested more deeply than allowed by
+    /// [`crate::include::LoadOpts::max_depth`].
+    #[error("directives are nested more than {0} levels deep")]
+    IncludeTooDeep(usize),
+
+    /// Indicates that `#include` and `#base` directives loaded more files than allowed by
+    /// [`crate::include::LoadOpts::max_files`]. A file that is included more than once counts each
+    /// time.
+    #[error("directives loaded more than {0} files")]
+    TooManyFiles(usize),
+
+    /// Indicates that the path of a `#include` or `#base` directive refers to a file outside of
+    /// the root directory of the [`crate::include::Resolver`], such as `../../secrets.txt`.
+    #[error("path `{0}` is outside of the root directory")]
+    PathOutsideRoot(String),
+
+    /// Indicates that a string containing escape sequences was deserialized into a borrowed
+    /// `&str`.
+    ///
+    /// # Explanation
+    ///
+    /// Strings are borrowed directly from the input whenever possible. However, strings with
+    /// escape sequences (such as `\"` or `\n`) must be unescaped into a new allocation, so they
+    /// cannot be borrowed. Use `String` or `Cow<str>` (with `#[serde(borrow)]`) for fields that may
+    /// contain escape sequences.
+    ///
+    /// ```
+    /// # use std::borrow::Cow;
+    /// # use vdflex::Error;
+    /// #[derive(Debug, serde::Deserialize)]
+    /// struct Borrowed<'a> {
+    ///     text: &'a str,
+    /// }
+    ///
+    /// #[derive(serde::Deserialize)]
+    /// struct MaybeBorrowed<'a> {
+    ///     #[serde(borrow)]
+    ///     text: Cow<'a, str>,
+    /// }
+    ///
+    /// let input = r#"text "say \"hi\"""#;
+    /// let err = vdflex::from_str::<Borrowed>(input).unwrap_err();
+    /// assert!(matches!(err.inner(), Error::BorrowedEscapedString(_)));
+    ///
+    /// let maybe_borrowed: MaybeBorrowed = vdflex::from_str(input).unwrap();
+    /// assert_eq!(maybe_borrowed.text, "say \"hi\"");
+    /// ```
+    #[error(
+        "cannot borrow {0:?} because it contains escape sequences (try `String` or `Cow<str>`)"
+    )]
+    BorrowedEscapedString(String),
+
+    /// Indicates that binary KeyValues data is malformed.
+    #[error("invalid binary KeyValues at byte {offset}: {reason}")]
+    InvalidBinary {
+        /// The offset of the byte where the problem was found.
+        offset: usize,
+        /// A description of the problem.
+        reason: String,
+    },
+
     /// Indicates that a Serde error occurred.
     #[error("a serde error occurred: {0}")]
     Serde(String),
+
+    /// Indicates which file another error occurred in, when loading files with
+    /// [`crate::include::Loader`].
+    #[error("{inner} in `{path}`")]
+    InFile {
+        /// The error that occurred.
+        inner: Box<Error>,
+        /// The path of the file, relative to the root of the resolver.
+        path: String,
+    },
+
+    /// Indicates where in the input another error occurred.
+    ///
+    /// # Explanation
+    ///
+    /// Errors that occur while deserializing KeyValues text, such as syntax errors and values
+    /// that can't be converted to the requested type, are wrapped in this variant. Errors that
+    /// concern the document as a whole (like [`Error::MultipleRootKeys`]) are not.
+    ///
+    /// Use [`Error::inner`] to inspect the underlying error regardless of whether its location
+    /// is known.
+    ///
+    /// ```
+    /// # use vdflex::Error;
+    /// #[derive(Debug, serde::Deserialize)]
+    /// #[serde(rename_all = "PascalCase")]
+    /// struct Depot {
+    ///     file_mapping: Vec<FileMapping>,
+    /// }
+    ///
+    /// #[derive(Debug, serde::Deserialize)]
+    /// #[serde(rename_all = "PascalCase")]
+    /// struct FileMapping {
+    ///     recursive: bool,
+    /// }
+    ///
+    /// let input = "Depot\n{\n    FileMapping { Recursive 1 }\n    FileMapping { Recursive yes }\n}";
+    /// let err = vdflex::kv_from_str::<Depot>(input).unwrap_err();
+    /// assert_eq!(err.path(), Some("Depot.FileMapping[1].Recursive"));
+    /// assert_eq!(err.position().map(|pos| (pos.line, pos.column)), Some((4, 29)));
+    /// assert!(matches!(err.inner(), Error::Serde(_)));
+    /// ```
+    #[error("{inner} at {position}{}", fmt_path(.path))]
+    Located {
+        /// The error that occurred.
+        inner: Box<Error>,
+        /// The position of the value (or token) that caused the error.
+        position: Position,
+        /// The path of keys leading to the value that caused the error, such as
+        /// `AppBuild.Depots.1234.FileMapping[2].LocalPath`. Repeated keys are indexed with
+        /// brackets. Empty if the error occurred at the root of the document.
+        path: String,
+    },
+}
+
+fn fmt_path(path: &str) -> String {
+    if path.is_empty() {
+        String::new()
+    } else {
+        format!(" (`{path}`)")
+    }
+}
+
+impl Error {
+    /// Returns the position in the input where this error occurred, if it is known.
+    pub fn position(&self) -> Option<Position> {
+        match self {
+            Error::Located { position, .. } => Some(*position),
+            Error::InFile { inner, .. } => inner.position(),
+            _ => None,
+        }
+    }
+
+    /// Returns the key path of the value that caused this error, if it is known.
+    ///
+    /// See [`Error::Located`] for the format of the path.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Error::Located { path, .. } => Some(path),
+            Error::InFile { inner, .. } => inner.path(),
+            _ => None,
+        }
+    }
+
+    /// Returns the path of the file this error occurred in, if it is known.
+    ///
+    /// See [`Error::InFile`].
+    pub fn file(&self) -> Option<&str> {
+        match self {
+            Error::InFile { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Returns the underlying error, skipping over any location information.
+    pub fn inner(&self) -> &Error {
+        match self {
+            Error::Located { inner, .. } | Error::InFile { inner, .. } => inner.inner(),
+            error => error,
+        }
+    }
+
+    /// Attaches the path of the file this error occurred in, unless it already has one.
+    pub(crate) fn in_file(self, path: &str) -> Self {
+        match self {
+            Error::InFile { .. } => self,
+            inner => Error::InFile {
+                inner: Box::new(inner),
+                path: path.to_string(),
+            },
+        }
+    }
+
+    /// Attaches a position to this error, unless it already has one.
+    pub(crate) fn at(self, position: Position) -> Self {
+        match self {
+            Error::Located { .. }
+            | Error::InFile { .. }
+            | Error::MultipleRootKeys
+            | Error::Io(_) => self,
+            inner => Error::Located {
+                inner: Box::new(inner),
+                position,
+                path: String::new(),
+            },
+        }
+    }
+
+    /// Translates the position of this error, if it has one.
+    pub(crate) fn map_position(mut self, f: impl FnOnce(Position) -> Position) -> Self {
+        if let Error::Located { position, .. } = &mut self {
+            *position = f(*position);
+        }
+        self
+    }
+
+    /// Prepends an object key to the path of this error.
+    pub(crate) fn in_key(mut self, key: &str) -> Self {
+        if let Error::Located { path, .. } = &mut self {
+            if path.is_empty() || path.starts_with('[') {
+                path.insert_str(0, key);
+            } else {
+                path.insert(0, '.');
+                path.insert_str(0, key);
+            }
+        }
+        self
+    }
+
+    /// Prepends the index of a repeated key to the path of this error.
+    pub(crate) fn in_index(mut self, index: usize) -> Self {
+        if let Error::Located { path, .. } = &mut self {
+            let index = format!("[{index}]");
+            if !path.is_empty() && !path.starts_with('[') {
+                path.insert(0, '.');
+            }
+            path.insert_str(0, &index);
+        }
+        self
+    }
 }
 
 impl From<io::Error> for Error {
@@ -0,0 +1,349 @@
+//! Expand `#include` and `#base` directives.
+//!
+//! KeyValues files may pull in other files with directives at the root of the document:
+//!
+//! ```text
+//! #base "HudPlayerClass_Base.res"
+//! #include "sourcemods.vdf"
+//! ```
+//!
+//! The deserialization functions in [`crate::de`] treat directives like any other key. A
+//! [`Loader`] reads a file through a [`Resolver`] and expands its directives using the same
+//! semantics as Valve's implementation:
+//!
+//! - `#include` appends the root-level entries of the included file to the including file.
+//! - `#base` merges the included file into the including file, but only fills in keys that are
+//!   missing. Keys in the including file are never overridden. Objects that appear in both files
+//!   are merged recursively.
+//!
+//! Paths are relative to the directory of the file containing the directive, and may use either
+//! `/` or `\` as a separator. Paths that would leave the root directory of the resolver (such as
+//! `../../secrets.txt`) are rejected.
+//!
+//! ```
+//! use vdflex::include::{Loader, MemoryResolver};
+//! use vdflex::Value;
+//!
+//! let mut resolver = MemoryResolver::new();
+//! resolver.insert("resource/ui/base.res", r#"
+//!     "HudLayout" { "xpos" "0" "ypos" "0" }
+//! "#);
+//! resolver.insert("resource/ui/hud.res", r#"
+//!     #base "base.res"
+//!     "HudLayout" { "xpos" "10" }
+//! "#);
+//!
+//! let kv = Loader::new(resolver).load("resource/ui/hud.res").unwrap();
+//! let Value::Object(layout) = &kv.root["HudLayout"][0] else { unreachable!() };
+//! assert_eq!(layout["xpos"], [Value::String(String::from("10"))]);
+//! assert_eq!(layout["ypos"], [Value::String(String::from("0"))]);
+//! ```
+
+use crate::de::{self, DeserializeOpts};
+use crate::{Error, KeyValues, Object, Result, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::{fs, io};
+
+/// Reads the files referred to by `#include` and `#base` directives.
+pub trait Resolver {
+    /// Returns the contents of the file at `path`.
+    ///
+    /// `path` is relative to the root of the resolver, uses `/` as a separator and never contains
+    /// `.` or `..` components.
+    fn read(&self, path: &str) -> io::Result<String>;
+}
+
+impl<R: ?Sized + Resolver> Resolver for &R {
+    fn read(&self, path: &str) -> io::Result<String> {
+        (**self).read(path)
+    }
+}
+
+/// A [`Resolver`] that reads files from a directory.
+#[derive(Clone, Debug)]
+pub struct FileResolver {
+    root: PathBuf,
+}
+
+impl FileResolver {
+    /// Creates a resolver that reads files relative to `root`.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl Resolver for FileResolver {
+    /// Reads the file at `path`, relative to the root directory.
+    ///
+    /// Fails with [`io::ErrorKind::PermissionDenied`] if the file is outside of the root
+    /// directory after resolving symbolic links.
+    fn read(&self, path: &str) -> io::Result<String> {
+        let root = self.root.canonicalize()?;
+        let file = root.join(path).canonicalize()?;
+        if !file.starts_with(&root) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("`{path}` is outside of the root directory"),
+            ));
+        }
+        fs::read_to_string(file)
+    }
+}
+
+/// A [`Resolver`] that reads files from memory.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryResolver {
+    files: HashMap<String, String>,
+}
+
+impl MemoryResolver {
+    /// Creates a resolver without any files.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file, replacing any existing file with the same path. `path` should be relative and
+    /// use `/` as a separator.
+    pub fn insert<P: Into<String>, C: Into<String>>(&mut self, path: P, contents: C) {
+        self.files.insert(path.into(), contents.into());
+    }
+}
+
+impl<P: Into<String>, C: Into<String>> FromIterator<(P, C)> for MemoryResolver {
+    fn from_iter<I: IntoIterator<Item = (P, C)>>(iter: I) -> Self {
+        let mut resolver = Self::new();
+        for (path, contents) in iter {
+            resolver.insert(path, contents);
+        }
+        resolver
+    }
+}
+
+impl Resolver for MemoryResolver {
+    fn read(&self, path: &str) -> io::Result<String> {
+        self.files.get(path).cloned().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("`{path}` does not exist"))
+        })
+    }
+}
+
+/// Options for [`Loader`].
+#[derive(Clone, Debug)]
+pub struct LoadOpts {
+    /// How deeply directives may be nested before loading fails with
+    /// [`Error::IncludeTooDeep`] (default: 16).
+    pub max_depth: usize,
+    /// How many files may be loaded in total before loading fails with
+    /// [`Error::TooManyFiles`]. A file that is included more than once counts each time
+    /// (default: 1024).
+    pub max_files: usize,
+    /// The options used to deserialize each file (default: [`DeserializeOpts::default()`]).
+    pub deserialize: DeserializeOpts,
+}
+
+impl Default for LoadOpts {
+    fn default() -> Self {
+        LoadOpts {
+            max_depth: 16,
+            max_files: 1024,
+            deserialize: DeserializeOpts::default(),
+        }
+    }
+}
+
+/// Loads KeyValues files and expands their `#include` and `#base` directives.
+///
+/// See the [module documentation](self) for details.
+#[derive(Clone, Debug)]
+pub struct Loader<R> {
+    resolver: R,
+    opts: LoadOpts,
+}
+
+impl<R: Resolver> Loader<R> {
+    /// Creates a loader that reads files with `resolver`.
+    pub fn new(resolver: R) -> Self {
+        Self::with_opts(resolver, LoadOpts::default())
+    }
+
+    /// Creates a loader that reads files with `resolver` and the specified `opts`.
+    pub fn with_opts(resolver: R, opts: LoadOpts) -> Self {
+        Self { resolver, opts }
+    }
+
+    /// Loads the file at `path` and every file it refers to.
+    ///
+    /// # Errors
+    ///
+    /// Loading fails if any file cannot be read or is not valid KeyValues, if a file includes
+    /// itself, if directives are nested too deeply or load too many files, or if a directive refers
+    /// to a file outside of the root directory. Errors that occur in a specific file are wrapped in
+    /// [`Error::InFile`].
+    pub fn load(&self, path: &str) -> Result<KeyValues> {
+        let path = join_path("", path)?;
+        let root = self.load_file(path, &mut LoadState::default())?;
+        Ok(KeyValues { root })
+    }
+
+    /// Loads a file and expands its directives.
+    fn load_file(&self, path: String, state: &mut LoadState) -> Result<Object> {
+        if state.stack.contains(&path) {
+            return Err(Error::IncludeCycle(path));
+        }
+        if state.stack.len() >= self.opts.max_depth {
+            return Err(Error::IncludeTooDeep(self.opts.max_depth));
+        }
+        if state.files >= self.opts.max_files {
+            return Err(Error::TooManyFiles(self.opts.max_files));
+        }
+
+        state.files += 1;
+        state.stack.push(path.clone());
+        let result = self.expand(&path, state);
+        state.stack.pop();
+        result.map_err(|err| err.in_file(&path))
+    }
+
+    fn expand(&self, path: &str, state: &mut LoadState) -> Result<Object> {
+        let text = self.resolver.read(path)?;
+        let kv: KeyValues = de::from_str_with_opts(&text, self.opts.deserialize.clone())?;
+        let dir = path.rsplit_once('/').map_or("", |(dir, _)| dir);
+
+        let mut root = Object::new();
+        let mut includes = Vec::new();
+        let mut bases = Vec::new();
+        for (key, values) in kv.root {
+            // Like the Source engine, directives are matched ignoring ASCII case.
+            if key.eq_ignore_ascii_case("#include") {
+                includes.extend(values);
+            } else if key.eq_ignore_ascii_case("#base") {
+                bases.extend(values);
+            } else {
+                root.insert(key, values);
+            }
+        }
+
+        for include in includes {
+            let included = self.load_file(directive_path(dir, include)?, state)?;
+            for (key, values) in included {
+                root.entry(key).or_default().extend(values);
+            }
+        }
+
+        for base in bases {
+            let base = self.load_file(directive_path(dir, base)?, state)?;
+            merge_base(&mut root, base);
+        }
+
+        Ok(root)
+    }
+}
+
+/// The progress of a single call to [`Loader::load`].
+#[derive(Default)]
+struct LoadState {
+    /// The files that are currently being loaded, to detect cycles.
+    stack: Vec<String>,
+    /// The number of files that have been loaded so far.
+    files: usize,
+}
+
+/// Resolves the path of a directive in the directory `dir`.
+fn directive_path(dir: &str, value: Value) -> Result<String> {
+    match value {
+        Value::String(path) => join_path(dir, &path),
+        Value::Object(_) => Err(Error::UnexpectedToken {
+            expected: "a file path",
+            found: String::from("`{`"),
+        }),
+        // Only binary KeyValues has typed values, and directives are only read from text.
+        _ => Err(Error::UnexpectedToken {
+            expected: "a file path",
+            found: String::from("a number"),
+        }),
+    }
+}
+
+/// Joins `path` onto `dir`, removing `.` and `..` components. Fails if the result would be
+/// outside of the root directory.
+fn join_path(dir: &str, path: &str) -> Result<String> {
+    let outside_root = || Error::PathOutsideRoot(path.to_string());
+
+    let mut components: Vec<&str> = if path.starts_with(['/', '\\']) {
+        Vec::new()
+    } else {
+        dir.split('/').filter(|c| !c.is_empty()).collect()
+    };
+    for (i, component) in path.split(['/', '\\']).enumerate() {
+        match component {
+            // A drive letter, like `C:`
+            _ if i == 0 && component.ends_with(':') => return Err(outside_root()),
+            "" | "." => {}
+            ".." => {
+                components.pop().ok_or_else(outside_root)?;
+            }
+            _ => components.push(component),
+        }
+    }
+
+    if components.is_empty() {
+        return Err(outside_root());
+    }
+    Ok(components.join("/"))
+}
+
+/// Adds the keys of `base` that are missing from `target`. Objects present in both are merged
+/// recursively.
+fn merge_base(target: &mut Object, base: Object) {
+    for (key, base_values) in base {
+        match target.get_mut(&key) {
+            Some(values) => {
+                for (value, base_value) in values.iter_mut().zip(base_values) {
+                    if let (Value::Object(object), Value::Object(base_object)) = (value, base_value)
+                    {
+                        merge_base(object, base_object);
+                    }
+                }
+            }
+            None => {
+                target.insert(key, base_values);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn join_paths() -> Result<()> {
+        assert_eq!(join_path("", "file.vdf")?, "file.vdf");
+        assert_eq!(
+            join_path("resource/ui", "base.res")?,
+            "resource/ui/base.res"
+        );
+        assert_eq!(join_path("resource/ui", "..\\hud.res")?, "resource/hud.res");
+        assert_eq!(
+            join_path("resource/ui", "./a/./b//c.res")?,
+            "resource/ui/a/b/c.res"
+        );
+        assert_eq!(join_path("resource/ui", "/scripts/x.txt")?, "scripts/x.txt");
+
+        for (dir, path) in [
+            ("", "../file.vdf"),
+            ("resource", "../../file.vdf"),
+            ("resource", "a/../../../file.vdf"),
+            ("", "C:\\Windows\\win.ini"),
+            ("resource", ".."),
+        ] {
+            assert!(
+                matches!(join_path(dir, path), Err(Error::PathOutsideRoot(_))),
+                "{dir:?} {path:?}"
+            );
+        }
+
+        Ok(())
+    }
+}
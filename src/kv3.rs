@@ -0,0 +1,214 @@
+//! Serialize and deserialize KeyValues3 (KV3) text.
+//!
+//! Source 2 stores most of its data, such as `.vdata`, `.vmat`, `.vpcf` and `.vsndevts` files,
+//! in KeyValues3. Unlike KeyValues, KV3 has typed values, arrays and unique keys, so it maps onto
+//! Rust types much like JSON does:
+//!
+//! ```text
+//! <!-- kv3 encoding:text:version{e21c7f3c-8a33-41c5-9977-a76d3a32aa0d} format:generic:version{7412167c-06e9-4698-aff2-e63eb59037e7} -->
+//! {
+//!     // Comments may start with `//` or `#`, or be enclosed in `/* */`.
+//!     name = "Explosion"
+//!     enabled = true
+//!     count = 3
+//!     radius = 64.0
+//!     parent = null
+//!     sounds = [ soundevent:"Weapon.Explode", soundevent:"Weapon.Debris" ]
+//!     model = resource:"models/props/crate.vmdl"
+//!     notes = """
+//! A multi-line string, which ends at the next three quotes.
+//! """
+//!     data = #[ 00 01 FF ]
+//! }
+//! ```
+//!
+//! | KV3                            | Serialized from                        | [`Value`]                 |
+//! |--------------------------------|----------------------------------------|---------------------------|
+//! | `null`                         | `None`, unit, unit structs             | [`Value::Null`]           |
+//! | `true`, `false`                | `bool`                                 | [`Value::Bool`]           |
+//! | Integers                       | `i8`-`i64`, `u8`-`u64`                 | [`Value::Int`], [`Value::UInt`] |
+//! | Doubles                        | `f32`, `f64`                           | [`Value::Double`]         |
+//! | `"string"`, `"""multi-line"""` | Strings, `char`s, unit variants        | [`Value::String`]         |
+//! | `#[ 00 FF ]`                   | Bytes                                  | [`Value::Binary`]         |
+//! | `[ 1, 2, 3 ]`                  | Sequences, tuples                      | [`Value::Array`]          |
+//! | `{ key = value }`              | Maps, structs, non-unit variants       | [`Value::Object`]         |
+//! | `resource:"path"`              | [`Flagged`]                            | [`Value::Flagged`]        |
+//!
+//! Enum variants are represented like in JSON: a unit variant is a string, and other variants
+//! are an object with the name of the variant as its only key. Flagged values can be read into
+//! any type that asks for the kind of the unflagged value, so a `String` field accepts
+//! `resource:"path"` as well as `"path"`. Types that accept any kind of value, such as untagged
+//! enums, see the flag as an enum variant instead. Use [`Flagged`] or [`Value`] to read or write
+//! the flag.
+//!
+//! Every document starts with a [`Header`]. It is optional when deserializing, and the
+//! serialization functions write [`Header::default`].
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//! use vdflex::kv3::{Flag, Flagged};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Prop {
+//!     model: Flagged<String>,
+//!     health: i32,
+//!     tags: Vec<String>,
+//! }
+//!
+//! let prop = Prop {
+//!     model: Flagged::new(Flag::Resource, String::from("models/props/crate.vmdl")),
+//!     health: 100,
+//!     tags: vec![String::from("breakable")],
+//! };
+//! let text = vdflex::kv3::to_string(&prop)?;
+//! assert!(text.starts_with("<!-- kv3 encoding:text:"));
+//! assert!(text.contains("model = resource:\"models/props/crate.vmdl\""));
+//!
+//! let read: Prop = vdflex::kv3::from_str(&text)?;
+//! assert_eq!(read, prop);
+//! # Ok::<(), vdflex::Error>(())
+//! ```
+
+mod deserializer;
+mod formatter;
+mod lexer;
+mod serializer;
+mod value;
+
+use crate::Result;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{Read, Write};
+
+pub use deserializer::Deserializer;
+pub use formatter::{FormatOpts, Formatter, PrettyFormatter};
+pub use serializer::Serializer;
+pub use value::{Flag, Flagged, Object, Value};
+
+/// The name of the enum that [`Flagged`] serializes as. A newtype variant of this enum is written
+/// as `flag:value`, and flagged values are deserialized as one.
+const FLAG_TOKEN: &str = "$__vdflex_private_kv3_flag";
+
+/// The `<!-- kv3 ... -->` header at the start of a KV3 document.
+///
+/// The header names the encoding of the document and its format, each with a version GUID.
+/// Generic documents use [`Header::default`], but some tools check for their own format.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Header {
+    /// The encoding of the document, which is `text` for KV3 text.
+    pub encoding: Tag,
+    /// The format of the document, such as `generic`.
+    pub format: Tag,
+}
+
+/// A name and version GUID in a KV3 [`Header`], such as `generic:version{7412167c-...}`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Tag {
+    /// The name, such as `text` or `generic`.
+    pub name: String,
+    /// The version GUID, without the braces.
+    pub version: String,
+}
+
+impl Tag {
+    /// Creates a tag with the specified `name` and `version`.
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+        }
+    }
+}
+
+impl Default for Header {
+    /// Returns the header of a generic KV3 text document.
+    fn default() -> Self {
+        Self {
+            encoding: Tag::new("text", "e21c7f3c-8a33-41c5-9977-a76d3a32aa0d"),
+            format: Tag::new("generic", "7412167c-06e9-4698-aff2-e63eb59037e7"),
+        }
+    }
+}
+
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "<!-- kv3 encoding:{}:version{{{}}} format:{}:version{{{}}} -->",
+            self.encoding.name, self.encoding.version, self.format.name, self.format.version
+        )
+    }
+}
+
+/// Deserialize an instance of type `T` from KV3 text.
+///
+/// # Errors
+///
+/// Fails if `s` is not valid KV3, or if it does not match the structure expected by `T`.
+pub fn from_str<'de, T: Deserialize<'de>>(s: &'de str) -> Result<T> {
+    let mut deserializer = Deserializer::from_str(s);
+    let value = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// Deserialize an instance of type `T` from KV3 text read from `reader`.
+///
+/// # Errors
+///
+/// Fails if reading from `reader` fails. See [`from_str`] for the other errors.
+pub fn from_reader<R: Read, T: DeserializeOwned>(mut reader: R) -> Result<T> {
+    let mut s = String::new();
+    reader.read_to_string(&mut s)?;
+    from_str(&s)
+}
+
+/// Serialize the given value as a KV3 document.
+///
+/// # Errors
+///
+/// Serialization can fail if `T` cannot be represented as KV3 or if `T`'s implementation of
+/// `Serialize` decides to fail.
+pub fn to_string<T: ?Sized + Serialize>(value: &T) -> Result<String> {
+    to_string_pretty(value, PrettyFormatter::default())
+}
+
+/// Serialize the given value as a KV3 document using a custom formatter.
+///
+/// # Errors
+///
+/// See [`to_string`].
+pub fn to_string_pretty<T: ?Sized + Serialize, F: Formatter>(
+    value: &T,
+    formatter: F,
+) -> Result<String> {
+    let mut writer = Vec::new();
+    to_writer_pretty(&mut writer, value, formatter)?;
+    // Safety: given valid utf-8 as input, the writer will never produce invalid utf-8
+    unsafe { Ok(String::from_utf8_unchecked(writer)) }
+}
+
+/// Serialize the given value as a KV3 document into the specified writer.
+///
+/// # Errors
+///
+/// Fails if writing to `writer` fails. See [`to_string`] for the other errors.
+pub fn to_writer<W: Write, T: ?Sized + Serialize>(writer: W, value: &T) -> Result<()> {
+    to_writer_pretty(writer, value, PrettyFormatter::default())
+}
+
+/// Serialize the given value as a KV3 document into the specified writer using a custom
+/// formatter.
+///
+/// # Errors
+///
+/// Fails if writing to `writer` fails. See [`to_string`] for the other errors.
+pub fn to_writer_pretty<W: Write, T: ?Sized + Serialize, F: Formatter>(
+    writer: W,
+    value: &T,
+    formatter: F,
+) -> Result<()> {
+    let mut serializer = Serializer::new(writer, formatter);
+    value.serialize(&mut serializer)
+}
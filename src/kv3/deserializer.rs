@@ -0,0 +1,540 @@
+use super::lexer::{Lexer, Token};
+use super::{Flag, Header};
+use crate::de::MAX_DEPTH;
+use crate::lex::Position;
+use crate::{Error, Result};
+use serde::de::value::BorrowedStrDeserializer;
+use serde::de::{
+    self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, Unexpected,
+    VariantAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+use std::borrow::Cow;
+
+/// Deserializes KV3 text into Rust types.
+///
+/// The header of the document is read along with the first value, or by calling
+/// [`Deserializer::header`].
+pub struct Deserializer<'de> {
+    lexer: Lexer<'de>,
+    /// The number of arrays and objects that are currently being read.
+    depth: usize,
+}
+
+impl<'de> Deserializer<'de> {
+    /// Creates a KV3 deserializer from a `&str`.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(input: &'de str) -> Self {
+        Self {
+            lexer: Lexer::new(input),
+            depth: 0,
+        }
+    }
+
+    /// Returns the header of the document, or `None` if it has none.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the document starts with a comment that is not a valid KV3 header.
+    pub fn header(&mut self) -> Result<Option<&Header>> {
+        self.lexer.header()
+    }
+
+    /// Checks that only whitespace and comments remain in the input. This should be called
+    /// after deserializing a value.
+    ///
+    /// # Errors
+    ///
+    /// Fails if there are tokens after the value that was deserialized.
+    pub fn end(&mut self) -> Result<()> {
+        match self.lexer.next_token()? {
+            Some(token) => Err(self.lexer.unexpected("the end of the input", token)),
+            None => Ok(()),
+        }
+    }
+
+    fn next(&mut self) -> Result<Token<'de>> {
+        self.lexer.next_token()?.ok_or_else(|| self.lexer.eof())
+    }
+
+    /// Reads the next value token. If the value has a flag, the flag is returned with it.
+    fn next_value(&mut self) -> Result<(Option<Flag>, Token<'de>)> {
+        match self.next()? {
+            Token::Identifier(name) if self.lexer.peek_token()? == Some(&Token::Colon) => {
+                let flag = Flag::from_name(name)
+                    .ok_or_else(|| self.lexer.unexpected("a flag", Token::Identifier(name)))?;
+                self.next()?;
+                Ok((Some(flag), self.next()?))
+            }
+            token => Ok((None, token)),
+        }
+    }
+
+    /// Visits a value that was just read, ignoring its flag.
+    fn visit_token<V: Visitor<'de>>(&mut self, token: Token<'de>, visitor: V) -> Result<V::Value> {
+        match token {
+            Token::Identifier("null") => visitor.visit_unit(),
+            Token::Identifier("true") => visitor.visit_bool(true),
+            Token::Identifier("false") => visitor.visit_bool(false),
+            Token::Number(n) => visit_number(n, visitor),
+            Token::String(Cow::Borrowed(s)) => visitor.visit_borrowed_str(s),
+            Token::String(Cow::Owned(s)) => visitor.visit_string(s),
+            Token::Binary(bytes) => visitor.visit_byte_buf(bytes),
+            Token::OpenBracket => self.nested(|de| {
+                let mut access = ArrayAccess::new(de);
+                let value = visitor.visit_seq(&mut access)?;
+                access.end()?;
+                Ok(value)
+            }),
+            Token::OpenBrace => self.nested(|de| {
+                let mut access = ObjectAccess::new(de);
+                let value = visitor.visit_map(&mut access)?;
+                access.end()?;
+                Ok(value)
+            }),
+            token => Err(self.lexer.unexpected("a value", token)),
+        }
+    }
+
+    /// Reads the contents of an array or object whose opening bracket was just read, failing if
+    /// it is nested more deeply than [`MAX_DEPTH`].
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth == MAX_DEPTH {
+            return Err(Error::NestedTooDeeply(MAX_DEPTH).at(self.lexer.start()));
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Deserializes the next value, ignoring its flag. Errors without a position are located at
+    /// the value.
+    fn deserialize_value<V: Visitor<'de>>(&mut self, visitor: V) -> Result<V::Value> {
+        let (_, token) = self.next_value()?;
+        let start = self.lexer.start();
+        self.visit_token(token, visitor)
+            .map_err(|err| err.at(start))
+    }
+}
+
+fn visit_number<'de, V: Visitor<'de>>(n: &str, visitor: V) -> Result<V::Value> {
+    if n.contains(['.', 'e', 'E']) {
+        if let Ok(v) = n.parse() {
+            return visitor.visit_f64(v);
+        }
+    } else if let Ok(v) = n.parse() {
+        return visitor.visit_i64(v);
+    } else if let Ok(v) = n.parse() {
+        return visitor.visit_u64(v);
+    }
+
+    Err(Error::UnexpectedToken {
+        expected: "a number",
+        found: format!("`{n}`"),
+    })
+}
+
+/// Implements `Deserializer` methods that read the next value without its flag, so that a flagged
+/// value can be read into any type that accepts the value itself.
+macro_rules! forward_to_deserialize_value {
+    ($($method:ident($($arg:ident: $ty:ty),*))*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value> {
+                self.deserialize_value(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let (flag, token) = self.next_value()?;
+        let start = self.lexer.start();
+        let result = match flag {
+            Some(flag) => visitor.visit_enum(FlagAccess {
+                de: self,
+                flag,
+                token,
+            }),
+            None => self.visit_token(token, visitor),
+        };
+        result.map_err(|err| err.at(start))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.lexer.eat(&Token::Identifier("null"))? {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let (flag, token) = self.next_value()?;
+        let start = self.lexer.start();
+        let result = match (flag, token) {
+            (Some(flag), token) => visitor.visit_enum(FlagAccess {
+                de: self,
+                flag,
+                token,
+            }),
+            (None, Token::String(variant)) => visitor.visit_enum(variant.into_deserializer()),
+            (None, Token::OpenBrace) => self.nested(|de| {
+                let value = visitor.visit_enum(VariantObjectAccess { de: &mut *de })?;
+                match de.next()? {
+                    Token::CloseBrace => Ok(value),
+                    token => Err(de.lexer.unexpected("`}`", token)),
+                }
+            }),
+            (None, token) => Err(self.lexer.unexpected("a string or object", token)),
+        };
+        result.map_err(|err| err.at(start))
+    }
+
+    forward_to_deserialize_value! {
+        deserialize_bool()
+        deserialize_i8()
+        deserialize_i16()
+        deserialize_i32()
+        deserialize_i64()
+        deserialize_i128()
+        deserialize_u8()
+        deserialize_u16()
+        deserialize_u32()
+        deserialize_u64()
+        deserialize_u128()
+        deserialize_f32()
+        deserialize_f64()
+        deserialize_char()
+        deserialize_str()
+        deserialize_string()
+        deserialize_bytes()
+        deserialize_byte_buf()
+        deserialize_unit()
+        deserialize_unit_struct(_name: &'static str)
+        deserialize_seq()
+        deserialize_tuple(_len: usize)
+        deserialize_tuple_struct(_name: &'static str, _len: usize)
+        deserialize_map()
+        deserialize_struct(_name: &'static str, _fields: &'static [&'static str])
+        deserialize_identifier()
+        deserialize_ignored_any()
+    }
+}
+
+/// Reads the elements of an array whose `[` was just consumed.
+struct ArrayAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    index: usize,
+    done: bool,
+}
+
+impl<'a, 'de> ArrayAccess<'a, 'de> {
+    fn new(de: &'a mut Deserializer<'de>) -> Self {
+        Self {
+            de,
+            index: 0,
+            done: false,
+        }
+    }
+
+    /// Checks that the visitor read every element.
+    fn end(&mut self) -> Result<()> {
+        if self.done {
+            return Ok(());
+        }
+        match self.de.next()? {
+            Token::CloseBracket => Ok(()),
+            Token::Comma if self.de.lexer.eat(&Token::CloseBracket)? => Ok(()),
+            token => Err(self.de.lexer.unexpected("`]`", token)),
+        }
+    }
+}
+
+impl<'de> SeqAccess<'de> for ArrayAccess<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        // Elements are separated by commas, and the last one may be followed by one.
+        if self.index > 0 && !self.de.lexer.eat(&Token::Comma)? {
+            match self.de.next()? {
+                Token::CloseBracket => {
+                    self.done = true;
+                    return Ok(None);
+                }
+                token => return Err(self.de.lexer.unexpected("`,` or `]`", token)),
+            }
+        }
+        if self.de.lexer.eat(&Token::CloseBracket)? {
+            self.done = true;
+            return Ok(None);
+        }
+
+        let index = self.index;
+        self.index += 1;
+        seed.deserialize(&mut *self.de)
+            .map(Some)
+            .map_err(|err| err.in_index(index))
+    }
+}
+
+/// Reads the members of an object whose `{` was just consumed.
+struct ObjectAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    key: Cow<'de, str>,
+    done: bool,
+}
+
+impl<'a, 'de> ObjectAccess<'a, 'de> {
+    fn new(de: &'a mut Deserializer<'de>) -> Self {
+        Self {
+            de,
+            key: Cow::Borrowed(""),
+            done: false,
+        }
+    }
+
+    /// Checks that the visitor read every member.
+    fn end(&mut self) -> Result<()> {
+        if self.done {
+            return Ok(());
+        }
+        match self.de.next()? {
+            Token::CloseBrace => Ok(()),
+            token => Err(self.de.lexer.unexpected("`}`", token)),
+        }
+    }
+}
+
+/// Reads the key of an object member and the `=` after it. Returns the key and its position.
+fn read_key<'de>(de: &mut Deserializer<'de>) -> Result<(Cow<'de, str>, Position)> {
+    let key = match de.next()? {
+        Token::Identifier(key) => Cow::Borrowed(key),
+        Token::String(key) => key,
+        token => return Err(de.lexer.unexpected("a key", token)),
+    };
+    let start = de.lexer.start();
+    match de.next()? {
+        Token::Equals => Ok((key, start)),
+        token => Err(de.lexer.unexpected("`=`", token)),
+    }
+}
+
+impl<'de> MapAccess<'de> for ObjectAccess<'_, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        // Members may be separated by commas, although they usually are not.
+        self.de.lexer.eat(&Token::Comma)?;
+        if self.de.lexer.eat(&Token::CloseBrace)? {
+            self.done = true;
+            return Ok(None);
+        }
+
+        let (key, start) = read_key(self.de)?;
+        self.key = key;
+        seed.deserialize(KeyDeserializer {
+            key: self.key.clone(),
+        })
+        .map(Some)
+        .map_err(|err| err.at(start))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        seed.deserialize(&mut *self.de)
+            .map_err(|err| err.in_key(&self.key))
+    }
+}
+
+/// Deserializes the value of a flagged value, whose flag is the variant of an enum.
+struct FlagAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    flag: Flag,
+    token: Token<'de>,
+}
+
+impl<'de> EnumAccess<'de> for FlagAccess<'_, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self::Variant)> {
+        let variant = seed.deserialize(BorrowedStrDeserializer::<Error>::new(self.flag.name()))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for FlagAccess<'_, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Err(de::Error::invalid_type(
+            Unexpected::NewtypeVariant,
+            &"unit variant",
+        ))
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(TokenDeserializer {
+            de: self.de,
+            token: self.token,
+        })
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.de.visit_token(self.token, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.de.visit_token(self.token, visitor)
+    }
+}
+
+/// Deserializes a value whose first token was already read.
+struct TokenDeserializer<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    token: Token<'de>,
+}
+
+impl<'de> de::Deserializer<'de> for TokenDeserializer<'_, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.de.visit_token(self.token, visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.token {
+            Token::Identifier("null") => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct enum
+        identifier ignored_any
+    }
+}
+
+/// Deserializes an enum from an object with a single member, whose key is the variant.
+struct VariantObjectAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de> EnumAccess<'de> for VariantObjectAccess<'_, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self::Variant)> {
+        let (key, start) = read_key(self.de)?;
+        let variant = seed
+            .deserialize(KeyDeserializer { key })
+            .map_err(|err| err.at(start))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for VariantObjectAccess<'_, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        de::Deserialize::deserialize(self.de)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_seq(self.de, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_map(self.de, visitor)
+    }
+}
+
+/// Deserializes an object key. Keys that look like numbers can be read as numbers.
+struct KeyDeserializer<'de> {
+    key: Cow<'de, str>,
+}
+
+macro_rules! deserialize_key_from_str_impl {
+    ($($ty:ident),* $(,)?) => {
+        paste::paste! {
+            $(
+                fn [<deserialize_ $ty>]<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                    match self.key.parse() {
+                        Ok(v) => visitor.[<visit_ $ty>](v),
+                        Err(_) => Err(de::Error::invalid_value(
+                            Unexpected::Str(&self.key),
+                            &visitor,
+                        )),
+                    }
+                }
+            )*
+        }
+    };
+}
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.key {
+            Cow::Borrowed(key) => visitor.visit_borrowed_str(key),
+            Cow::Owned(key) => visitor.visit_string(key),
+        }
+    }
+
+    deserialize_key_from_str_impl!(i8, i16, i32, i64, u8, u16, u32, u64, f32, f64, char);
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i128 u128 str string bytes byte_buf option unit unit_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
@@ -0,0 +1,295 @@
+use super::{Flag, Header};
+use crate::ser::{write_string_element, BraceStyle, Quoting};
+use std::io::{self, Write};
+
+/// This trait allows the user to customize KV3 formatting.
+///
+/// By default, there is only one implementation: [`PrettyFormatter`].
+pub trait Formatter {
+    /// Called before the root value with the header of the document. By default, the header is
+    /// written on its own line.
+    fn write_header<W: ?Sized + Write>(
+        &mut self,
+        writer: &mut W,
+        header: &Header,
+    ) -> io::Result<()> {
+        writeln!(writer, "{header}")
+    }
+
+    /// Called after the root value. By default, a line break is written.
+    fn end_document<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(b"\n")
+    }
+
+    /// Called before writing an object.
+    fn begin_object<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()>;
+
+    /// Called after every object.
+    fn end_object<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()>;
+
+    /// Called before writing the key of an object member. `first` is `true` for the first member
+    /// of the object.
+    fn begin_key<W: ?Sized + Write>(&mut self, writer: &mut W, first: bool) -> io::Result<()>;
+
+    /// Writes the key of an object member.
+    fn write_key<W: ?Sized + Write>(&mut self, writer: &mut W, key: &str) -> io::Result<()>;
+
+    /// Called after writing the key of an object member.
+    fn end_key<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()>;
+
+    /// Called before writing the value of an object member.
+    fn begin_value<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()>;
+
+    /// Called after writing the value of an object member.
+    fn end_value<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()>;
+
+    /// Called before writing an array.
+    fn begin_array<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()>;
+
+    /// Called after every array.
+    fn end_array<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()>;
+
+    /// Called before writing an element of an array. `first` is `true` for the first element of
+    /// the array.
+    fn begin_element<W: ?Sized + Write>(&mut self, writer: &mut W, first: bool) -> io::Result<()>;
+
+    /// Called after writing an element of an array.
+    fn end_element<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()>;
+
+    /// Writes `null`.
+    fn write_null<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()>;
+
+    /// Writes `true` or `false`.
+    fn write_bool<W: ?Sized + Write>(&mut self, writer: &mut W, v: bool) -> io::Result<()>;
+
+    /// Writes a signed integer.
+    fn write_i64<W: ?Sized + Write>(&mut self, writer: &mut W, v: i64) -> io::Result<()>;
+
+    /// Writes an unsigned integer.
+    fn write_u64<W: ?Sized + Write>(&mut self, writer: &mut W, v: u64) -> io::Result<()>;
+
+    /// Writes a finite double.
+    fn write_f64<W: ?Sized + Write>(&mut self, writer: &mut W, v: f64) -> io::Result<()>;
+
+    /// Writes a string.
+    fn write_string<W: ?Sized + Write>(&mut self, writer: &mut W, s: &str) -> io::Result<()>;
+
+    /// Writes a binary blob.
+    fn write_bytes<W: ?Sized + Write>(&mut self, writer: &mut W, v: &[u8]) -> io::Result<()>;
+
+    /// Writes the flag of the value that is written next.
+    fn write_flag<W: ?Sized + Write>(&mut self, writer: &mut W, flag: Flag) -> io::Result<()>;
+}
+
+/// Format options for [`PrettyFormatter`].
+#[derive(Clone, Debug)]
+pub struct FormatOpts {
+    /// The sequence of characters to print for each indent level (default: a tab).
+    pub indent: String,
+    /// How to format the braces of objects and the brackets of arrays that are the values of
+    /// object members (default: [`BraceStyle::Allman`]).
+    pub brace_style: BraceStyle,
+    /// Whether strings with line breaks are written as multi-line strings (default: `true`).
+    pub multiline_strings: bool,
+}
+
+impl Default for FormatOpts {
+    fn default() -> Self {
+        FormatOpts {
+            indent: String::from("\t"),
+            brace_style: BraceStyle::Allman,
+            multiline_strings: true,
+        }
+    }
+}
+
+/// A [`Formatter`] that prints a human-readable version of the input, like Valve's tools do.
+///
+/// Every object member and array element is written on its own line. Arrays have a comma after
+/// every element, including the last one.
+pub struct PrettyFormatter {
+    opts: FormatOpts,
+    indent_level: usize,
+    /// Whether the current object or array has any elements yet.
+    has_elements: bool,
+    /// Whether the next value is the value of an object member.
+    in_value: bool,
+}
+
+impl PrettyFormatter {
+    /// Creates a new [`PrettyFormatter`] with the specified `opts`.
+    pub fn new(opts: FormatOpts) -> Self {
+        Self {
+            opts,
+            indent_level: 0,
+            has_elements: false,
+            in_value: false,
+        }
+    }
+
+    fn write_indent<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        for _ in 0..self.indent_level {
+            writer.write_all(self.opts.indent.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Separates a value from the `=` before it, if it is the value of an object member.
+    fn begin_scalar<W: ?Sized + Write>(&mut self, writer: &mut W, nested: bool) -> io::Result<()> {
+        if !std::mem::take(&mut self.in_value) {
+            return Ok(());
+        }
+
+        if nested && self.opts.brace_style == BraceStyle::Allman {
+            writer.write_all(b"\n")?;
+            self.write_indent(writer)
+        } else {
+            writer.write_all(b" ")
+        }
+    }
+
+    fn begin_nested<W: ?Sized + Write>(&mut self, writer: &mut W, open: &[u8]) -> io::Result<()> {
+        self.begin_scalar(writer, true)?;
+        writer.write_all(open)?;
+        self.indent_level += 1;
+        self.has_elements = false;
+        Ok(())
+    }
+
+    fn end_nested<W: ?Sized + Write>(&mut self, writer: &mut W, close: &[u8]) -> io::Result<()> {
+        self.indent_level -= 1;
+        if self.has_elements {
+            writer.write_all(b"\n")?;
+            self.write_indent(writer)?;
+        }
+        // The enclosing object or array (if any) has at least this element.
+        self.has_elements = true;
+        writer.write_all(close)
+    }
+
+    fn begin_line<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.has_elements = true;
+        writer.write_all(b"\n")?;
+        self.write_indent(writer)
+    }
+}
+
+impl Default for PrettyFormatter {
+    fn default() -> Self {
+        Self::new(FormatOpts::default())
+    }
+}
+
+/// Returns `true` if `key` can be written without quotes.
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+impl Formatter for PrettyFormatter {
+    fn begin_object<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.begin_nested(writer, b"{")
+    }
+
+    fn end_object<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.end_nested(writer, b"}")
+    }
+
+    fn begin_key<W: ?Sized + Write>(&mut self, writer: &mut W, _first: bool) -> io::Result<()> {
+        self.begin_line(writer)
+    }
+
+    fn write_key<W: ?Sized + Write>(&mut self, writer: &mut W, key: &str) -> io::Result<()> {
+        if is_identifier(key) {
+            writer.write_all(key.as_bytes())
+        } else {
+            write_string_element(writer, key, Quoting::Always)
+        }
+    }
+
+    fn end_key<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(b" =")
+    }
+
+    fn begin_value<W: ?Sized + Write>(&mut self, _writer: &mut W) -> io::Result<()> {
+        self.in_value = true;
+        Ok(())
+    }
+
+    fn end_value<W: ?Sized + Write>(&mut self, _writer: &mut W) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn begin_array<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.begin_nested(writer, b"[")
+    }
+
+    fn end_array<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.end_nested(writer, b"]")
+    }
+
+    fn begin_element<W: ?Sized + Write>(&mut self, writer: &mut W, _first: bool) -> io::Result<()> {
+        self.begin_line(writer)
+    }
+
+    fn end_element<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(b",")
+    }
+
+    fn write_null<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.begin_scalar(writer, false)?;
+        writer.write_all(b"null")
+    }
+
+    fn write_bool<W: ?Sized + Write>(&mut self, writer: &mut W, v: bool) -> io::Result<()> {
+        self.begin_scalar(writer, false)?;
+        writer.write_all(if v { b"true" } else { b"false" })
+    }
+
+    fn write_i64<W: ?Sized + Write>(&mut self, writer: &mut W, v: i64) -> io::Result<()> {
+        self.begin_scalar(writer, false)?;
+        write!(writer, "{v}")
+    }
+
+    fn write_u64<W: ?Sized + Write>(&mut self, writer: &mut W, v: u64) -> io::Result<()> {
+        self.begin_scalar(writer, false)?;
+        write!(writer, "{v}")
+    }
+
+    fn write_f64<W: ?Sized + Write>(&mut self, writer: &mut W, v: f64) -> io::Result<()> {
+        self.begin_scalar(writer, false)?;
+        // Unlike `Display`, `Debug` always writes a decimal point or an exponent, so the number
+        // is read back as a double.
+        write!(writer, "{v:?}")
+    }
+
+    fn write_string<W: ?Sized + Write>(&mut self, writer: &mut W, s: &str) -> io::Result<()> {
+        self.begin_scalar(writer, false)?;
+        if self.opts.multiline_strings
+            && s.contains('\n')
+            && !s.contains("\"\"\"")
+            && !s.ends_with('\r')
+        {
+            write!(writer, "\"\"\"\n{s}\n\"\"\"")
+        } else {
+            write_string_element(writer, s, Quoting::Always)
+        }
+    }
+
+    fn write_bytes<W: ?Sized + Write>(&mut self, writer: &mut W, v: &[u8]) -> io::Result<()> {
+        self.begin_scalar(writer, false)?;
+        writer.write_all(b"#[")?;
+        for byte in v {
+            write!(writer, " {byte:02X}")?;
+        }
+        writer.write_all(if v.is_empty() { b"]" } else { b" ]" })
+    }
+
+    fn write_flag<W: ?Sized + Write>(&mut self, writer: &mut W, flag: Flag) -> io::Result<()> {
+        self.begin_scalar(writer, false)?;
+        write!(writer, "{flag}:")
+    }
+}
@@ -0,0 +1,391 @@
+use super::{Header, Tag};
+use crate::lex::{self, Position};
+use crate::{Error, Result};
+use std::borrow::Cow;
+use std::fmt;
+
+/// A significant KV3 token.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Token<'de> {
+    /// An unquoted word, such as a key, `true`, `null` or the name of a flag.
+    Identifier(&'de str),
+    /// A quoted or multi-line string, with escape sequences already processed.
+    String(Cow<'de, str>),
+    /// A number, which has not been parsed yet.
+    Number(&'de str),
+    /// A binary blob such as `#[ 00 01 FF ]`.
+    Binary(Vec<u8>),
+    /// The `{` character.
+    OpenBrace,
+    /// The `}` character.
+    CloseBrace,
+    /// The `[` character.
+    OpenBracket,
+    /// The `]` character.
+    CloseBracket,
+    /// The `=` character.
+    Equals,
+    /// The `,` character.
+    Comma,
+    /// The `:` character.
+    Colon,
+}
+
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Identifier(s) => write!(f, "`{s}`"),
+            Token::String(s) => write!(f, "string \"{s}\""),
+            Token::Number(n) => write!(f, "number `{n}`"),
+            Token::Binary(_) => f.write_str("binary blob"),
+            Token::OpenBrace => f.write_str("`{`"),
+            Token::CloseBrace => f.write_str("`}`"),
+            Token::OpenBracket => f.write_str("`[`"),
+            Token::CloseBracket => f.write_str("`]`"),
+            Token::Equals => f.write_str("`=`"),
+            Token::Comma => f.write_str("`,`"),
+            Token::Colon => f.write_str("`:`"),
+        }
+    }
+}
+
+/// Reads significant [`Token`]s from KV3 text, skipping over whitespace and comments.
+pub(crate) struct Lexer<'de> {
+    input: &'de str,
+    pos: Position,
+    peeked: Option<(Token<'de>, Position)>,
+    start: Position,
+    /// The header of the document, or `None` if it has not been read yet.
+    header: Option<Option<Header>>,
+}
+
+impl<'de> Lexer<'de> {
+    pub fn new(input: &'de str) -> Self {
+        Self {
+            input,
+            pos: Position::START,
+            peeked: None,
+            start: Position::START,
+            header: None,
+        }
+    }
+
+    /// Returns the header of the document, reading it first if no token has been read yet.
+    pub fn header(&mut self) -> Result<Option<&Header>> {
+        if self.header.is_none() {
+            let header = self.read_header()?;
+            self.header = Some(header);
+        }
+        Ok(self.header.as_ref().and_then(Option::as_ref))
+    }
+
+    /// Returns the next token, or `None` if the end of the input was reached.
+    pub fn next_token(&mut self) -> Result<Option<Token<'de>>> {
+        if let Some((token, start)) = self.peeked.take() {
+            self.start = start;
+            return Ok(Some(token));
+        }
+
+        self.header()?;
+        self.skip_trivia()?;
+        self.start = self.pos;
+        self.read_token()
+    }
+
+    /// Returns the next token without consuming it.
+    pub fn peek_token(&mut self) -> Result<Option<&Token<'de>>> {
+        if self.peeked.is_none() {
+            let start = self.start;
+            if let Some(token) = self.next_token()? {
+                self.peeked = Some((token, self.start));
+            }
+            self.start = start;
+        }
+        Ok(self.peeked.as_ref().map(|(token, _)| token))
+    }
+
+    /// Consumes the next token if it is `token`.
+    pub fn eat(&mut self, token: &Token) -> Result<bool> {
+        if self.peek_token()? == Some(token) {
+            self.next_token()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Returns the position where the last token returned by [`Lexer::next_token`] started.
+    pub fn start(&self) -> Position {
+        self.start
+    }
+
+    /// Returns an error for a token that was just read but is not allowed here.
+    pub fn unexpected(&self, expected: &'static str, found: Token) -> Error {
+        Error::UnexpectedToken {
+            expected,
+            found: found.to_string(),
+        }
+        .at(self.start)
+    }
+
+    /// Returns an error for reaching the end of the input too early.
+    pub fn eof(&self) -> Error {
+        Error::Eof.at(self.pos)
+    }
+
+    fn rest(&self) -> &'de str {
+        &self.input[self.pos.offset..]
+    }
+
+    /// Advances past the next `len` bytes and returns them.
+    fn bump(&mut self, len: usize) -> &'de str {
+        let text = &self.input[self.pos.offset..self.pos.offset + len];
+        self.pos.offset += len;
+        match text.rfind('\n') {
+            Some(last) => {
+                self.pos.line += text.bytes().filter(|&b| b == b'\n').count();
+                self.pos.column = text[last + 1..].chars().count() + 1;
+            }
+            None => self.pos.column += text.chars().count(),
+        }
+        text
+    }
+
+    fn skip_trivia(&mut self) -> Result<()> {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.bump(rest.len() - trimmed.len());
+
+            if trimmed.starts_with("//") || (trimmed.starts_with('#') && !trimmed.starts_with("#["))
+            {
+                self.bump(trimmed.find('\n').unwrap_or(trimmed.len()));
+            } else if trimmed.starts_with("/*") {
+                let start = self.pos;
+                match trimmed.find("*/") {
+                    Some(end) => self.bump(end + 2),
+                    None => return Err(Error::Eof.at(start)),
+                };
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Reads the `<!-- kv3 ... -->` header if the input starts with one.
+    fn read_header(&mut self) -> Result<Option<Header>> {
+        let rest = self.rest();
+        let trimmed = rest.trim_start();
+        if !trimmed.starts_with("<!--") {
+            return Ok(None);
+        }
+
+        self.bump(rest.len() - trimmed.len());
+        let start = self.pos;
+        let Some(end) = trimmed.find("-->") else {
+            return Err(Error::Eof.at(start));
+        };
+        let comment = &self.bump(end + 3)[4..end];
+
+        let invalid = || {
+            Error::UnexpectedToken {
+                expected: "a KV3 header",
+                found: format!("`<!--{comment}-->`"),
+            }
+            .at(start)
+        };
+        let mut words = comment.split_whitespace();
+        if words.next() != Some("kv3") {
+            return Err(invalid());
+        }
+        let encoding = words.next().and_then(|word| header_tag(word, "encoding:"));
+        let format = words.next().and_then(|word| header_tag(word, "format:"));
+        match (encoding, format, words.next()) {
+            (Some(encoding), Some(format), None) => Ok(Some(Header { encoding, format })),
+            _ => Err(invalid()),
+        }
+    }
+
+    fn read_token(&mut self) -> Result<Option<Token<'de>>> {
+        let rest = self.rest();
+        let Some(c) = rest.chars().next() else {
+            return Ok(None);
+        };
+
+        let token = match c {
+            '{' => Token::OpenBrace,
+            '}' => Token::CloseBrace,
+            '[' => Token::OpenBracket,
+            ']' => Token::CloseBracket,
+            '=' => Token::Equals,
+            ',' => Token::Comma,
+            ':' => Token::Colon,
+            '"' => return self.read_string().map(Some),
+            '#' => return self.read_binary().map(Some),
+            c if c.is_ascii_digit() || matches!(c, '-' | '+' | '.') => {
+                let len = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && !matches!(c, '-' | '+' | '.'))
+                    .unwrap_or(rest.len());
+                return Ok(Some(Token::Number(self.bump(len))));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let len = rest
+                    .find(|c: char| !c.is_alphanumeric() && !matches!(c, '_' | '.'))
+                    .unwrap_or(rest.len());
+                return Ok(Some(Token::Identifier(self.bump(len))));
+            }
+            c => {
+                return Err(Error::UnexpectedToken {
+                    expected: "a KV3 token",
+                    found: format!("`{c}`"),
+                }
+                .at(self.pos))
+            }
+        };
+
+        self.bump(1);
+        Ok(Some(token))
+    }
+
+    fn read_string(&mut self) -> Result<Token<'de>> {
+        let start = self.pos;
+        let rest = self.rest();
+
+        if let Some(body) = rest.strip_prefix("\"\"\"") {
+            // Multi-line strings are raw. The line breaks after the opening quotes and before
+            // the closing quotes are not part of the string.
+            let Some(end) = body.find("\"\"\"") else {
+                return Err(Error::UnterminatedString.at(start));
+            };
+            let text = &body[..end];
+            let text = text
+                .strip_prefix("\r\n")
+                .or_else(|| text.strip_prefix('\n'))
+                .unwrap_or(text);
+            let text = text
+                .strip_suffix("\r\n")
+                .or_else(|| text.strip_suffix('\n'))
+                .unwrap_or(text);
+            self.bump(end + 6);
+            return Ok(Token::String(Cow::Borrowed(text)));
+        }
+
+        let mut escaped = false;
+        for (i, c) in rest.char_indices().skip(1) {
+            match c {
+                '"' if !escaped => {
+                    let text = self.bump(i + 1);
+                    return Ok(Token::String(lex::unescape(&text[1..i])));
+                }
+                '\\' => escaped = !escaped,
+                _ => escaped = false,
+            }
+        }
+        Err(Error::UnterminatedString.at(start))
+    }
+
+    fn read_binary(&mut self) -> Result<Token<'de>> {
+        let start = self.pos;
+        let rest = self.rest();
+        let Some(body) = rest.strip_prefix("#[") else {
+            return Err(Error::UnexpectedToken {
+                expected: "a KV3 token",
+                found: String::from("`#`"),
+            }
+            .at(start));
+        };
+        let Some(end) = body.find(']') else {
+            return Err(Error::Eof.at(start));
+        };
+
+        let digits: Vec<u8> = body[..end]
+            .bytes()
+            .filter(|b| !b.is_ascii_whitespace())
+            .collect();
+        let bytes = digits
+            .chunks(2)
+            .map(|pair| {
+                std::str::from_utf8(pair)
+                    .ok()
+                    .filter(|pair| pair.len() == 2)
+                    .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+            })
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(|| {
+                Error::UnexpectedToken {
+                    expected: "a binary blob of hexadecimal bytes",
+                    found: format!("`#[{}]`", &body[..end]),
+                }
+                .at(start)
+            })?;
+
+        self.bump(end + 3);
+        Ok(Token::Binary(bytes))
+    }
+}
+
+/// Parses a part of the header such as `format:generic:version{7412167c-...}`.
+fn header_tag(word: &str, prefix: &str) -> Option<Tag> {
+    let (name, version) = word.strip_prefix(prefix)?.split_once(":version{")?;
+    let version = version.strip_suffix('}')?;
+    Some(Tag::new(name, version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(input: &str) -> Result<Vec<Token<'_>>> {
+        let mut lexer = Lexer::new(input);
+        let mut tokens = Vec::new();
+        while let Some(token) = lexer.next_token()? {
+            tokens.push(token);
+        }
+        Ok(tokens)
+    }
+
+    #[test]
+    fn tokens_and_trivia() -> Result<()> {
+        assert_eq!(
+            tokens("{ a = -1.5e3 // comment\n b=#[0aff] /* c */ c: \"x\\ty\" } # end")?,
+            [
+                Token::OpenBrace,
+                Token::Identifier("a"),
+                Token::Equals,
+                Token::Number("-1.5e3"),
+                Token::Identifier("b"),
+                Token::Equals,
+                Token::Binary(vec![0x0A, 0xFF]),
+                Token::Identifier("c"),
+                Token::Colon,
+                Token::String(Cow::Borrowed("x\ty")),
+                Token::CloseBrace,
+            ]
+        );
+        assert_eq!(
+            tokens("\"\"\"\r\nraw \\n\r\n\"\"\"")?,
+            [Token::String(Cow::Borrowed("raw \\n"))]
+        );
+        assert!(tokens("#[ 0 ]").is_err());
+        assert!(tokens("/* open").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn header() -> Result<()> {
+        let mut lexer = Lexer::new("\n<!-- kv3 encoding:a:version{1} format:b:version{2} -->\n{}");
+        assert_eq!(
+            lexer.header()?,
+            Some(&Header {
+                encoding: Tag::new("a", "1"),
+                format: Tag::new("b", "2"),
+            })
+        );
+        assert_eq!(lexer.next_token()?, Some(Token::OpenBrace));
+        assert_eq!((lexer.start().line, lexer.start().column), (3, 1));
+
+        assert_eq!(Lexer::new("{}").header()?, None);
+        assert!(Lexer::new("<!-- kv3 encoding:a -->").header().is_err());
+        Ok(())
+    }
+}
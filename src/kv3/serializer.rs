@@ -0,0 +1,561 @@
+use super::formatter::{Formatter, PrettyFormatter};
+use super::{Flag, Header, FLAG_TOKEN};
+use crate::{Error, Result};
+use serde::ser::{
+    self, Impossible, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
+    SerializeTuple, SerializeTupleStruct, SerializeTupleVariant,
+};
+use serde::Serialize;
+use std::io::{self, Write};
+
+/// Serializes Rust types into KV3 text.
+///
+/// The header of the document is written before the first value.
+pub struct Serializer<W, F = PrettyFormatter> {
+    writer: W,
+    formatter: F,
+    /// The header of the document, until it has been written.
+    header: Option<Header>,
+    /// The objects and arrays being written.
+    containers: Vec<Container>,
+}
+
+/// An object or array being written by a [`Serializer`].
+struct Container {
+    /// Whether nothing has been written to the container yet.
+    first: bool,
+    /// Whether the container is the value of an object written for an enum variant, which must
+    /// be closed along with it.
+    variant: bool,
+}
+
+impl<W: Write, F: Formatter> Serializer<W, F> {
+    /// Creates a new KV3 serializer using the given `writer` and `formatter`, which writes
+    /// [`Header::default`].
+    pub fn new(writer: W, formatter: F) -> Self {
+        Self::with_header(writer, formatter, Header::default())
+    }
+
+    /// Creates a new KV3 serializer using the given `writer` and `formatter`, which writes the
+    /// specified `header`.
+    pub fn with_header(writer: W, formatter: F, header: Header) -> Self {
+        Self {
+            writer,
+            formatter,
+            header: Some(header),
+            containers: Vec::new(),
+        }
+    }
+
+    /// Writes the header if this is the first value of the document.
+    fn begin_value(&mut self) -> Result<()> {
+        if let Some(header) = self.header.take() {
+            self.formatter.write_header(&mut self.writer, &header)?;
+        }
+        Ok(())
+    }
+
+    /// Ends the document if a value was written at the root.
+    fn end_value(&mut self) -> Result<()> {
+        if self.containers.is_empty() {
+            self.formatter.end_document(&mut self.writer)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a value that is not an object or array, using `write` to write the value itself.
+    fn scalar_value(&mut self, write: impl FnOnce(&mut F, &mut W) -> io::Result<()>) -> Result<()> {
+        self.begin_value()?;
+        write(&mut self.formatter, &mut self.writer)?;
+        self.end_value()
+    }
+
+    fn begin_object(&mut self, variant: bool) -> Result<()> {
+        self.begin_value()?;
+        self.formatter.begin_object(&mut self.writer)?;
+        self.containers.push(Container {
+            first: true,
+            variant,
+        });
+        Ok(())
+    }
+
+    fn begin_array(&mut self, variant: bool) -> Result<()> {
+        self.begin_value()?;
+        self.formatter.begin_array(&mut self.writer)?;
+        self.containers.push(Container {
+            first: true,
+            variant,
+        });
+        Ok(())
+    }
+
+    /// Writes the `{ Variant =` that encloses the value of an enum variant.
+    fn begin_variant(&mut self, variant: &str) -> Result<()> {
+        self.begin_object(false)?;
+        self.write_key(variant)?;
+        self.formatter.begin_value(&mut self.writer)?;
+        Ok(())
+    }
+
+    /// Writes the `}` that encloses the value of an enum variant.
+    fn end_variant(&mut self) -> Result<()> {
+        self.formatter.end_value(&mut self.writer)?;
+        self.end_object()
+    }
+
+    fn end_object(&mut self) -> Result<()> {
+        let container = self
+            .containers
+            .pop()
+            .expect("ended object before starting it");
+        self.formatter.end_object(&mut self.writer)?;
+        self.end_value()?;
+        if container.variant {
+            self.end_variant()?;
+        }
+        Ok(())
+    }
+
+    fn end_array(&mut self) -> Result<()> {
+        let container = self
+            .containers
+            .pop()
+            .expect("ended array before starting it");
+        self.formatter.end_array(&mut self.writer)?;
+        self.end_value()?;
+        if container.variant {
+            self.end_variant()?;
+        }
+        Ok(())
+    }
+
+    fn write_key(&mut self, key: &str) -> Result<()> {
+        let container = self
+            .containers
+            .last_mut()
+            .expect("wrote key outside of object");
+        let first = std::mem::replace(&mut container.first, false);
+        self.formatter.begin_key(&mut self.writer, first)?;
+        self.formatter.write_key(&mut self.writer, key)?;
+        self.formatter.end_key(&mut self.writer)?;
+        Ok(())
+    }
+
+    fn serialize_member<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.formatter.begin_value(&mut self.writer)?;
+        value.serialize(&mut *self)?;
+        self.formatter.end_value(&mut self.writer)?;
+        Ok(())
+    }
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let container = self
+            .containers
+            .last_mut()
+            .expect("wrote element outside of array");
+        let first = std::mem::replace(&mut container.first, false);
+        self.formatter.begin_element(&mut self.writer, first)?;
+        value.serialize(&mut *self)?;
+        self.formatter.end_element(&mut self.writer)?;
+        Ok(())
+    }
+}
+
+/// Implements `serialize_$ty` by converting the value to a type that the [`Formatter`] has a
+/// method for.
+macro_rules! serialize_scalar_impl {
+    ($($ty:ident => $method:ident($target:ty)),* $(,)?) => {
+        paste::paste! {
+            $(
+                fn [<serialize_ $ty>](self, v: $ty) -> $crate::Result<Self::Ok> {
+                    self.scalar_value(|formatter, writer| formatter.$method(writer, <$target>::from(v)))
+                }
+            )*
+        }
+    };
+}
+
+impl<W: Write, F: Formatter> ser::Serializer for &mut Serializer<W, F> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    serialize_scalar_impl!(
+        bool => write_bool(bool),
+        i8 => write_i64(i64),
+        i16 => write_i64(i64),
+        i32 => write_i64(i64),
+        i64 => write_i64(i64),
+        u8 => write_u64(u64),
+        u16 => write_u64(u64),
+        u32 => write_u64(u64),
+        u64 => write_u64(u64),
+    );
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok> {
+        self.serialize_f64(f64::from(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok> {
+        if !v.is_finite() {
+            return Err(ser::Error::custom(format!("KV3 cannot represent {v}")));
+        }
+        self.scalar_value(|formatter, writer| formatter.write_f64(writer, v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok> {
+        self.scalar_value(|formatter, writer| formatter.write_string(writer, v))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok> {
+        self.scalar_value(|formatter, writer| formatter.write_bytes(writer, v))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Self::Ok> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok> {
+        self.scalar_value(|formatter, writer| formatter.write_null(writer))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok> {
+        if name == FLAG_TOKEN {
+            if let Some(flag) = Flag::from_name(variant) {
+                self.begin_value()?;
+                self.formatter.write_flag(&mut self.writer, flag)?;
+                return value.serialize(self);
+            }
+        }
+
+        self.begin_variant(variant)?;
+        value.serialize(&mut *self)?;
+        self.end_variant()
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        self.begin_array(false)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        self.begin_variant(variant)?;
+        self.begin_array(true)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        self.begin_object(false)?;
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        self.begin_variant(variant)?;
+        self.begin_object(true)?;
+        Ok(self)
+    }
+}
+
+impl<W: Write, F: Formatter> SerializeSeq for &mut Serializer<W, F> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<Self::Ok> {
+        Serializer::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.end_array()
+    }
+}
+
+impl<W: Write, F: Formatter> SerializeTuple for &mut Serializer<W, F> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<Self::Ok> {
+        Serializer::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.end_array()
+    }
+}
+
+impl<W: Write, F: Formatter> SerializeTupleStruct for &mut Serializer<W, F> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<Self::Ok> {
+        Serializer::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.end_array()
+    }
+}
+
+impl<W: Write, F: Formatter> SerializeTupleVariant for &mut Serializer<W, F> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<Self::Ok> {
+        Serializer::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.end_array()
+    }
+}
+
+impl<W: Write, F: Formatter> SerializeMap for &mut Serializer<W, F> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<Self::Ok> {
+        let key = key.serialize(MapKeySerializer)?;
+        self.write_key(&key)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<Self::Ok> {
+        self.serialize_member(value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.end_object()
+    }
+}
+
+impl<W: Write, F: Formatter> SerializeStruct for &mut Serializer<W, F> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok> {
+        self.write_key(key)?;
+        self.serialize_member(value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.end_object()
+    }
+}
+
+impl<W: Write, F: Formatter> SerializeStructVariant for &mut Serializer<W, F> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok> {
+        self.write_key(key)?;
+        self.serialize_member(value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.end_object()
+    }
+}
+
+macro_rules! serialize_as_str_impl {
+    ($($ty:ident),* $(,)?) => {
+        paste::paste! {
+            $(
+                fn [<serialize_ $ty>](self, v: $ty) -> $crate::Result<Self::Ok> {
+                    Ok(v.to_string())
+                }
+            )*
+        }
+    };
+}
+
+/// Converts an object key to a string.
+struct MapKeySerializer;
+
+impl ser::Serializer for MapKeySerializer {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = Impossible<Self::Ok, Self::Error>;
+    type SerializeTuple = Impossible<Self::Ok, Self::Error>;
+    type SerializeTupleStruct = Impossible<Self::Ok, Self::Error>;
+    type SerializeTupleVariant = Impossible<Self::Ok, Self::Error>;
+    type SerializeMap = Impossible<Self::Ok, Self::Error>;
+    type SerializeStruct = Impossible<Self::Ok, Self::Error>;
+    type SerializeStructVariant = Impossible<Self::Ok, Self::Error>;
+
+    serialize_as_str_impl!(i8, i16, i32, i64, i128, u8, u16, u32, u64, u128, f32, f64, char);
+
+    fn serialize_bool(self, _v: bool) -> Result<Self::Ok> {
+        Err(Error::KeyMustBeAString("bool".to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok> {
+        Ok(String::from(v))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok> {
+        Err(Error::KeyMustBeAString("bytes".to_string()))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok> {
+        Err(Error::KeyMustBeAString("none".to_string()))
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<Self::Ok> {
+        Err(Error::KeyMustBeAString("some".to_string()))
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok> {
+        Err(Error::KeyMustBeAString("unit".to_string()))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
+        Err(Error::KeyMustBeAString("unit struct".to_string()))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok> {
+        Ok(String::from(variant))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok> {
+        Err(Error::KeyMustBeAString("newtype variant".to_string()))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(Error::KeyMustBeAString("sequence".to_string()))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(Error::KeyMustBeAString("tuple".to_string()))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(Error::KeyMustBeAString("tuple struct".to_string()))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(Error::KeyMustBeAString("tuple variant".to_string()))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(Error::KeyMustBeAString("map".to_string()))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(Error::KeyMustBeAString("struct".to_string()))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(Error::KeyMustBeAString("struct variant".to_string()))
+    }
+}
@@ -0,0 +1,292 @@
+use super::FLAG_TOKEN;
+use serde::de::{self, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor};
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::marker::PhantomData;
+use std::{fmt, result};
+
+/// Represents any KV3 value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    /// The `null` value.
+    Null,
+    /// A `true` or `false` value.
+    Bool(bool),
+    /// An integer.
+    Int(i64),
+    /// An integer that is too large for [`Value::Int`].
+    UInt(u64),
+    /// A double-precision float.
+    Double(f64),
+    /// A string, which may have been written as a multi-line string.
+    String(String),
+    /// A binary blob, written as `#[ 00 01 FF ]`.
+    Binary(Vec<u8>),
+    /// An array of values.
+    Array(Vec<Value>),
+    /// An object.
+    Object(Object),
+    /// A value with a flag, such as `resource:"materials/dev/dev_measuregeneric01.vmat"`.
+    Flagged(Flag, Box<Value>),
+}
+
+/// Represents a KV3 object. Keys are unique, unlike in KeyValues.
+#[cfg(feature = "preserve_order")]
+pub type Object = indexmap::IndexMap<String, Value>;
+
+/// Represents a KV3 object. Keys are unique, unlike in KeyValues.
+#[cfg(not(feature = "preserve_order"))]
+pub type Object = std::collections::BTreeMap<String, Value>;
+
+/// The flags that a KV3 value can have. A flag tells tools what a value refers to, but does not
+/// change the value itself.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Flag {
+    /// `resource:`, the path of a compiled resource.
+    Resource,
+    /// `resource_name:`, the path of a resource that is not compiled with the file.
+    ResourceName,
+    /// `panorama:`, the path of a Panorama UI file.
+    Panorama,
+    /// `soundevent:`, the name of a sound event.
+    SoundEvent,
+    /// `subclass:`, the name of a subclass in a `.vdata` file.
+    SubClass,
+    /// `entity_name:`, the name of an entity.
+    EntityName,
+}
+
+impl Flag {
+    const NAMES: &'static [&'static str] = &[
+        "resource",
+        "resource_name",
+        "panorama",
+        "soundevent",
+        "subclass",
+        "entity_name",
+    ];
+
+    /// Returns the flag as it is written before a value, without the colon.
+    pub fn name(self) -> &'static str {
+        Self::NAMES[self as usize]
+    }
+
+    /// Returns the flag with the specified name, if there is one.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "resource" => Some(Flag::Resource),
+            "resource_name" => Some(Flag::ResourceName),
+            "panorama" => Some(Flag::Panorama),
+            "soundevent" => Some(Flag::SoundEvent),
+            "subclass" => Some(Flag::SubClass),
+            "entity_name" => Some(Flag::EntityName),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Flag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Serialize for Flag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Flag {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+        deserializer.deserialize_identifier(FlagVisitor)
+    }
+}
+
+struct FlagVisitor;
+
+impl Visitor<'_> for FlagVisitor {
+    type Value = Flag;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a KV3 flag")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> result::Result<Self::Value, E> {
+        Flag::from_name(v).ok_or_else(|| E::unknown_variant(v, Flag::NAMES))
+    }
+}
+
+/// A value with a [`Flag`], such as `resource:"models/props/crate.vmdl"`.
+///
+/// KV3 serializers write it as `flag:value`. Other formats see an enum with a newtype variant
+/// named after the flag.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Flagged<T> {
+    /// The flag of the value.
+    pub flag: Flag,
+    /// The value itself.
+    pub value: T,
+}
+
+impl<T> Flagged<T> {
+    /// Creates a value with the specified `flag`.
+    pub fn new(flag: Flag, value: T) -> Self {
+        Self { flag, value }
+    }
+}
+
+impl<T: Serialize> Serialize for Flagged<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_variant(
+            FLAG_TOKEN,
+            self.flag as u32,
+            self.flag.name(),
+            &self.value,
+        )
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Flagged<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+        deserializer.deserialize_enum(FLAG_TOKEN, Flag::NAMES, FlaggedVisitor(PhantomData))
+    }
+}
+
+struct FlaggedVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for FlaggedVisitor<T> {
+    type Value = Flagged<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a flagged value")
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> result::Result<Self::Value, A::Error> {
+        let (flag, variant) = data.variant()?;
+        Ok(Flagged::new(flag, variant.newtype_variant()?))
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        match self {
+            Value::Null => serializer.serialize_unit(),
+            Value::Bool(v) => serializer.serialize_bool(*v),
+            Value::Int(v) => serializer.serialize_i64(*v),
+            Value::UInt(v) => serializer.serialize_u64(*v),
+            Value::Double(v) => serializer.serialize_f64(*v),
+            Value::String(v) => serializer.serialize_str(v),
+            Value::Binary(v) => serializer.serialize_bytes(v),
+            Value::Array(values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for value in values {
+                    seq.serialize_element(value)?;
+                }
+                seq.end()
+            }
+            Value::Object(object) => {
+                let mut map = serializer.serialize_map(Some(object.len()))?;
+                for (key, value) in object {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+            Value::Flagged(flag, value) => serializer.serialize_newtype_variant(
+                FLAG_TOKEN,
+                *flag as u32,
+                flag.name(),
+                &**value,
+            ),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a KV3 value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> result::Result<Self::Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> result::Result<Self::Value, E> {
+        Ok(Value::Int(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> result::Result<Self::Value, E> {
+        Ok(i64::try_from(v).map_or(Value::UInt(v), Value::Int))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> result::Result<Self::Value, E> {
+        Ok(Value::Double(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> result::Result<Self::Value, E> {
+        Ok(Value::String(String::from(v)))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> result::Result<Self::Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> result::Result<Self::Value, E> {
+        Ok(Value::Binary(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> result::Result<Self::Value, E> {
+        Ok(Value::Binary(v))
+    }
+
+    fn visit_none<E: de::Error>(self) -> result::Result<Self::Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> result::Result<Self::Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> result::Result<Self::Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> result::Result<Self::Value, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Value::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> result::Result<Self::Value, A::Error> {
+        #[cfg(feature = "preserve_order")]
+        let mut object = Object::with_capacity(map.size_hint().unwrap_or(0));
+        #[cfg(not(feature = "preserve_order"))]
+        let mut object = Object::new();
+
+        while let Some((key, value)) = map.next_entry()? {
+            object.insert(key, value);
+        }
+        Ok(Value::Object(object))
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> result::Result<Self::Value, A::Error> {
+        // KV3 deserializers visit flagged values as enums.
+        let (flag, variant) = data.variant()?;
+        Ok(Value::Flagged(flag, Box::new(variant.newtype_variant()?)))
+    }
+}
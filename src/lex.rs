@@ -0,0 +1,508 @@
+//! Split KeyValues text into tokens.
+//!
+//! The [`Lexer`] produces every token in the input, including whitespace, newlines and comments,
+//! which makes it suitable for syntax highlighters, linters and other editor tooling. The text of
+//! every token is borrowed from the input, so concatenating the text of all tokens reproduces the
+//! input exactly.
+//!
+//! Lexing never fails. Malformed input such as an unterminated string is returned as a token
+//! with the kind [`TokenKind::Error`] and lexing continues after it.
+//!
+//! ```
+//! use vdflex::lex::{Lexer, TokenKind};
+//!
+//! let kinds: Vec<TokenKind> = Lexer::new("\"$basetexture\" gravel01 // comment\n")
+//!     .map(|token| token.kind)
+//!     .collect();
+//!
+//! assert_eq!(
+//!     kinds,
+//!     [
+//!         TokenKind::QuotedString,
+//!         TokenKind::Whitespace,
+//!         TokenKind::UnquotedString,
+//!         TokenKind::Whitespace,
+//!         TokenKind::Comment,
+//!         TokenKind::Newline,
+//!     ]
+//! );
+//! ```
+
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Range;
+use thiserror::Error;
+
+/// A location in KeyValues text.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Position {
+    /// The byte offset from the start of the input.
+    pub offset: usize,
+    /// The line number, starting at 1.
+    pub line: usize,
+    /// The column number in characters, starting at 1.
+    pub column: usize,
+}
+
+impl Position {
+    /// The position of the first character of the input.
+    pub const START: Position = Position {
+        offset: 0,
+        line: 1,
+        column: 1,
+    };
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self::START
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// The region of the input covered by a token. `end` is exclusive.
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Span {
+    /// The position of the first character.
+    pub start: Position,
+    /// The position just after the last character.
+    pub end: Position,
+}
+
+impl Span {
+    /// Returns the byte range of this span, which can be used to slice the input.
+    pub fn range(&self) -> Range<usize> {
+        self.start.offset..self.end.offset
+    }
+}
+
+/// Describes what went wrong in a [`TokenKind::Error`] token.
+#[derive(Copy, Clone, Debug, Eq, Error, Hash, PartialEq)]
+pub enum LexError {
+    /// A quoted string has no closing quote. The token extends to the end of the line.
+    #[error("unterminated string")]
+    UnterminatedString,
+    /// A conditional tag has no closing `]` on the same line. The token extends to the end of
+    /// the line.
+    #[error("unterminated conditional tag")]
+    UnterminatedConditional,
+    /// A `}` was found without a matching `{`.
+    #[error("unmatched closing brace")]
+    UnmatchedCloseBrace,
+}
+
+/// The kind of a [`Token`].
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum TokenKind {
+    /// A string enclosed in double quotes, such as `"LightmappedGeneric"`.
+    QuotedString,
+    /// A string without quotes, such as `LightmappedGeneric`.
+    UnquotedString,
+    /// The `{` character.
+    OpenBrace,
+    /// The `}` character.
+    CloseBrace,
+    /// A conditional tag, such as `[$WIN32]` or `[!$X360 && $OSX]`.
+    Conditional,
+    /// A `#base` or `#include` directive, which may be quoted.
+    Directive,
+    /// A line comment starting with `//`. Does not include the line break.
+    Comment,
+    /// A sequence of whitespace characters other than line breaks.
+    Whitespace,
+    /// A line break (`\n` or `\r\n`).
+    Newline,
+    /// Malformed input.
+    Error(LexError),
+}
+
+/// A token of KeyValues text.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Token<'a> {
+    /// The kind of token.
+    pub kind: TokenKind,
+    /// The exact text of the token, including quotes, brackets or comment markers.
+    pub text: &'a str,
+    /// The location of the token in the input.
+    pub span: Span,
+}
+
+impl<'a> Token<'a> {
+    /// Returns `true` if this token is whitespace, a line break or a comment.
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self.kind,
+            TokenKind::Whitespace | TokenKind::Newline | TokenKind::Comment
+        )
+    }
+
+    /// Returns the content of this token without any delimiters.
+    ///
+    /// - Strings and directives have their quotes removed and escape sequences processed.
+    /// - Conditional tags have their brackets removed.
+    /// - Comments have their leading `//` removed.
+    /// - All other tokens are returned unchanged.
+    pub fn value(&self) -> Cow<'a, str> {
+        match self.kind {
+            TokenKind::QuotedString => unescape(strip_quotes(self.text)),
+            TokenKind::UnquotedString => unescape(self.text),
+            TokenKind::Directive => Cow::Borrowed(strip_quotes(self.text)),
+            TokenKind::Conditional => Cow::Borrowed(&self.text[1..self.text.len() - 1]),
+            TokenKind::Comment => Cow::Borrowed(&self.text[2..]),
+            _ => Cow::Borrowed(self.text),
+        }
+    }
+}
+
+fn strip_quotes(s: &str) -> &str {
+    s.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s)
+}
+
+/// Processes the escape sequences written by [`crate::ser::PrettyFormatter`] (`\n`, `\t`, `\\`
+/// and `\"`). Unknown escape sequences are left untouched, since many hand-written files contain
+/// unescaped Windows paths. For the same reason, a `\"` at the end of a line closes its string
+/// instead of escaping the quote, and the backslash is kept.
+pub fn unescape(s: &str) -> Cow<'_, str> {
+    if !s.contains('\\') {
+        return Cow::Borrowed(s);
+    }
+
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('\\') => unescaped.push('\\'),
+            Some('"') => unescaped.push('"'),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    Cow::Owned(unescaped)
+}
+
+/// Returns `true` if `c` ends an unquoted string.
+pub(crate) fn is_unquoted_terminator(c: char) -> bool {
+    c == '"' || c == '{' || c == '}' || c.is_whitespace()
+}
+
+/// Returns `true` if `s` is the name of a `#base` or `#include` directive, ignoring ASCII case.
+pub(crate) fn is_directive(s: &str) -> bool {
+    s.eq_ignore_ascii_case("#base") || s.eq_ignore_ascii_case("#include")
+}
+
+/// An iterator over the [`Token`]s of KeyValues text.
+#[derive(Clone, Debug)]
+pub struct Lexer<'a> {
+    input: &'a str,
+    pos: Position,
+    depth: usize,
+}
+
+impl<'a> Lexer<'a> {
+    /// Creates a lexer over the given input.
+    pub fn new(input: &'a str) -> Self {
+        Self::resume(input, Position::START, 0)
+    }
+
+    /// Creates a lexer that starts at `pos`, inside `depth` levels of objects.
+    pub(crate) fn resume(input: &'a str, pos: Position, depth: usize) -> Self {
+        Self { input, pos, depth }
+    }
+
+    /// Returns the position of the next token.
+    pub fn position(&self) -> Position {
+        self.pos
+    }
+
+    /// Returns the number of objects that are currently open.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    fn token(&mut self, kind: TokenKind, len: usize) -> Token<'a> {
+        let start = self.pos;
+        let text = &self.input[start.offset..start.offset + len];
+
+        self.pos.offset += len;
+        match text.rfind('\n') {
+            Some(last) => {
+                self.pos.line += text.bytes().filter(|&b| b == b'\n').count();
+                self.pos.column = text[last + 1..].chars().count() + 1;
+            }
+            None => self.pos.column += text.chars().count(),
+        }
+
+        Token {
+            kind,
+            text,
+            span: Span {
+                start,
+                end: self.pos,
+            },
+        }
+    }
+}
+
+/// Returns the length of `s` up to (but not including) the next line break.
+fn line_len(s: &str) -> usize {
+    match s.find('\n') {
+        Some(len) if s[..len].ends_with('\r') => len - 1,
+        Some(len) => len,
+        None => s.len(),
+    }
+}
+
+/// Returns the length of the quoted string at the start of `s`, including both quotes.
+///
+/// `\"` is an escaped quote unless it is the last `"` on its line, in which case it closes the
+/// string. This lets unescaped Windows paths such as `"C:\dir\"` end in a backslash.
+fn quoted_len(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let mut pos = 1;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'\\' if bytes.get(pos + 1) == Some(&b'"') && !quote_follows(&bytes[pos + 2..]) => {
+                return Some(pos + 2);
+            }
+            b'\\' => pos += 2,
+            b'"' => return Some(pos + 1),
+            _ => pos += 1,
+        }
+    }
+    None
+}
+
+/// Returns `true` if there is a `"` before the end of the first line of `bytes`.
+fn quote_follows(bytes: &[u8]) -> bool {
+    bytes
+        .iter()
+        .take_while(|&&b| b != b'\n')
+        .any(|&b| b == b'"')
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.input[self.pos.offset..];
+        let c = rest.chars().next()?;
+
+        let (kind, len) = match c {
+            '\n' => (TokenKind::Newline, 1),
+            '\r' if rest.starts_with("\r\n") => (TokenKind::Newline, 2),
+            c if c.is_whitespace() => {
+                let len = rest
+                    .char_indices()
+                    .find(|&(i, c)| {
+                        !c.is_whitespace() || c == '\n' || rest[i..].starts_with("\r\n")
+                    })
+                    .map_or(rest.len(), |(i, _)| i);
+                (TokenKind::Whitespace, len)
+            }
+            '{' => {
+                self.depth += 1;
+                (TokenKind::OpenBrace, 1)
+            }
+            '}' if self.depth == 0 => (TokenKind::Error(LexError::UnmatchedCloseBrace), 1),
+            '}' => {
+                self.depth -= 1;
+                (TokenKind::CloseBrace, 1)
+            }
+            '"' => match quoted_len(rest) {
+                Some(len) if is_directive(&rest[1..len - 1]) => (TokenKind::Directive, len),
+                Some(len) => (TokenKind::QuotedString, len),
+                None => (
+                    TokenKind::Error(LexError::UnterminatedString),
+                    line_len(rest),
+                ),
+            },
+            '[' => {
+                let line = &rest[..line_len(rest)];
+                match line.find(']') {
+                    Some(end) => (TokenKind::Conditional, end + 1),
+                    None => (
+                        TokenKind::Error(LexError::UnterminatedConditional),
+                        line.len(),
+                    ),
+                }
+            }
+            '/' if rest.starts_with("//") => (TokenKind::Comment, line_len(rest)),
+            _ => {
+                let len = rest.find(is_unquoted_terminator).unwrap_or(rest.len());
+                if is_directive(&rest[..len]) {
+                    (TokenKind::Directive, len)
+                } else {
+                    (TokenKind::UnquotedString, len)
+                }
+            }
+        };
+
+        Some(self.token(kind, len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    fn kinds(input: &str) -> Vec<(TokenKind, &str)> {
+        Lexer::new(input)
+            .map(|token| (token.kind, token.text))
+            .collect()
+    }
+
+    #[test]
+    fn tokens() {
+        use TokenKind::*;
+
+        assert_eq!(
+            kinds(indoc! {r##"
+                #base "panelBase.res"
+                "Resource" { // comment
+                	key [$WIN32]	"say \"hi\""
+                }
+            "##}),
+            [
+                (Directive, "#base"),
+                (Whitespace, " "),
+                (QuotedString, "\"panelBase.res\""),
+                (Newline, "\n"),
+                (QuotedString, "\"Resource\""),
+                (Whitespace, " "),
+                (OpenBrace, "{"),
+                (Whitespace, " "),
+                (Comment, "// comment"),
+                (Newline, "\n"),
+                (Whitespace, "\t"),
+                (UnquotedString, "key"),
+                (Whitespace, " "),
+                (Conditional, "[$WIN32]"),
+                (Whitespace, "\t"),
+                (QuotedString, r#""say \"hi\"""#),
+                (Newline, "\n"),
+                (CloseBrace, "}"),
+                (Newline, "\n"),
+            ]
+        );
+    }
+
+    #[test]
+    fn unquoted_strings_end_at_control_characters() {
+        use TokenKind::*;
+
+        assert_eq!(
+            kinds("foo{bar\"baz\"}"),
+            [
+                (UnquotedString, "foo"),
+                (OpenBrace, "{"),
+                (UnquotedString, "bar"),
+                (QuotedString, "\"baz\""),
+                (CloseBrace, "}"),
+            ]
+        );
+        assert_eq!(
+            kinds("http://example.com"),
+            [(UnquotedString, "http://example.com")]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        use TokenKind::*;
+
+        assert_eq!(
+            kinds("} key \"value\r\nnext"),
+            [
+                (Error(LexError::UnmatchedCloseBrace), "}"),
+                (Whitespace, " "),
+                (UnquotedString, "key"),
+                (Whitespace, " "),
+                (Error(LexError::UnterminatedString), "\"value"),
+                (Newline, "\r\n"),
+                (UnquotedString, "next"),
+            ]
+        );
+        assert_eq!(
+            kinds("[$X360\n]"),
+            [
+                (Error(LexError::UnterminatedConditional), "[$X360"),
+                (Newline, "\n"),
+                (UnquotedString, "]"),
+            ]
+        );
+    }
+
+    #[test]
+    fn paths_ending_in_backslash() {
+        use TokenKind::*;
+
+        let input = "path \"C:\\dir\\\"\nkey \"say \\\"hi\\\"\"";
+        assert_eq!(
+            kinds(input),
+            [
+                (UnquotedString, "path"),
+                (Whitespace, " "),
+                (QuotedString, "\"C:\\dir\\\""),
+                (Newline, "\n"),
+                (UnquotedString, "key"),
+                (Whitespace, " "),
+                (QuotedString, "\"say \\\"hi\\\"\""),
+            ]
+        );
+
+        let values: Vec<Cow<str>> = Lexer::new(input)
+            .filter(|token| !token.is_trivia())
+            .map(|token| token.value())
+            .collect();
+        assert_eq!(values, ["path", "C:\\dir\\", "key", "say \"hi\""]);
+    }
+
+    #[test]
+    fn positions() {
+        let tokens: Vec<Token> = Lexer::new("a \"multi\nline\" é b\n  c").collect();
+        let position = |text: &str| {
+            tokens
+                .iter()
+                .find(|token| token.text == text)
+                .map(|token| (token.span.start.line, token.span.start.column))
+                .unwrap()
+        };
+
+        assert_eq!(position("a"), (1, 1));
+        assert_eq!(position("\"multi\nline\""), (1, 3));
+        assert_eq!(position("é"), (2, 7));
+        assert_eq!(position("b"), (2, 9));
+        assert_eq!(position("c"), (3, 3));
+
+        let b = tokens.iter().find(|token| token.text == "b").unwrap();
+        assert_eq!(&"a \"multi\nline\" é b\n  c"[b.span.range()], "b");
+    }
+
+    #[test]
+    fn values() {
+        let values: Vec<Cow<str>> = Lexer::new(r##""#include" "a\"b" c\\d [$OSX] // note"##)
+            .filter(|token| !token.is_trivia() || token.kind == TokenKind::Comment)
+            .map(|token| token.value())
+            .collect();
+
+        assert_eq!(values, ["#include", "a\"b", "c\\d", "$OSX", " note"]);
+    }
+}
@@ -67,6 +67,16 @@
 //!     let text: String = vdflex::kv_to_string("AppBuild", &build_script)?;
 //!     println!("{text}");
 //!
+//!     let (key, settings): (String, BTreeMap<String, String>) = vdflex::kv_from_str(r#"
+//!         "Settings"
+//!         {
+//!             "Volume" "0.15"
+//!             Sensitivity 0.9 // comments and unquoted strings are allowed
+//!         }
+//!     "#)?;
+//!     assert_eq!(key, "Settings");
+//!     assert_eq!(settings["Sensitivity"], "0.9");
+//!
 //!     Ok(())
 //! }
 //! ```
@@ -94,6 +104,10 @@
 //! |          Maps/Structs          | Represented by objects (a curly bracket-enclosed list of key-value pairs)                              |
 //! |        Struct Variants         | Represented as an object mapping the variant name to the struct representation of its fields           |
 //!
+//! When deserializing, a sequence receives every occurrence of its key, even if the occurrences are
+//! not adjacent. A key that appears once is a sequence of one element. A missing key is an error
+//! unless the field is an `Option` or has `#[serde(default)]`, in which case it is `None` or empty.
+//!
 //! ### Limitations
 //!
 //! - The *Bytes* type is unsupported, as there is no clear way to represent binary data in KeyValues.
@@ -105,24 +119,31 @@
 //! This library is in an early state. As such, many features have not yet been implemented.
 //! Some missing features include:
 //!
-//! - Deserialization
-//!   - Text parsing
-//!   - Conversion to Rust types
 //! - An easier API for [`Object`]
 //! - A `keyvalues!` macro to create [`Object`]s
-//! - Conditional tags
-//!   - The [`ser::Formatter`] API supports conditional tags, but this is unsupported for the
-//!     serde API.
-//! - `#base` and `#include` directives
-//!   - The [`ser::Formatter`] API supports macro formatting, but the serde API treats
-//!     macros like normal fields.
+//! - Serializing conditional tags
+//!   - The [`ser::Formatter`] API supports conditional tags, but the serde API cannot write
+//!     them. Deserialization can evaluate them (see [`de::DeserializeOpts`]).
+//! - `#base` and `#include` directives in the serde API
+//!   - The serde API treats directives like normal fields. Use [`include::Loader`] to expand
+//!     them into a [`KeyValues`] document instead.
 
 #![warn(missing_docs)]
 
-mod de;
+pub mod binary;
+pub mod conditional;
+pub mod cst;
+pub mod de;
+mod entries;
 pub mod error;
+pub mod include;
+pub mod kv3;
+pub mod lex;
 pub mod ser;
+pub mod steam;
 
+pub use de::{from_reader, from_str, kv_from_reader, kv_from_str};
+pub use entries::Entries;
 pub use error::{Error, Result};
 pub use ser::{
     kv_to_string, kv_to_string_pretty, kv_to_writer, kv_to_writer_pretty, to_string,
@@ -133,26 +154,100 @@ use std::fmt;
 use std::result;
 
 /// Represents all possible VDF values.
-#[derive(Clone, Debug, Eq, PartialEq)]
+///
+/// Text KeyValues only has strings and objects. The other variants hold the typed values of
+/// [binary KeyValues](binary), so that a binary document can be read and written back without
+/// changing its types. When written as text, they become strings.
+///
+/// Only binary KeyValues produces the typed variants. Numbers and `bool`s from any other source,
+/// including text read with [`infer_numbers`](de::DeserializeOpts::infer_numbers), are stored as
+/// [`Value::String`]s, spelled the way text KeyValues would write them.
+///
+/// [`Value::Float32`]s are compared by their bits, so that `Value` can implement [`Eq`].
+#[derive(Clone, Debug)]
 pub enum Value {
     /// Stores a [`String`] value.
     String(String),
     /// Stores an [`Object`] value.
     Object(Object),
+    /// Stores a 32-bit integer. Steam also stores unsigned IDs, such as app IDs, in its bits.
+    Int32(i32),
+    /// Stores a signed 64-bit integer.
+    Int64(i64),
+    /// Stores an unsigned 64-bit integer.
+    UInt64(u64),
+    /// Stores a 32-bit float.
+    Float32(f32),
+    /// Stores a 32-bit pointer. Written as a decimal number in text.
+    Pointer(u32),
+    /// Stores an RGBA color. Written as `"r g b a"` in text.
+    Color([u8; 4]),
+}
+
+/// The name of the newtype struct that [`Value::Pointer`] serializes as, and the key of the map
+/// that a pointer is deserialized from. This lets serializers and deserializers in this crate
+/// tell pointers apart from other numbers.
+pub(crate) const POINTER_TOKEN: &str = "$__vdflex_private_pointer";
+
+/// Like [`POINTER_TOKEN`], but for [`Value::Color`]. The color is passed on as the `u32` with
+/// the same little-endian bytes.
+pub(crate) const COLOR_TOKEN: &str = "$__vdflex_private_color";
+
+/// The name of the newtype struct that [`Value`] is deserialized as. The binary deserializer
+/// answers it by visiting typed numbers as maps whose only key is one of the tokens below, so
+/// that they are not confused with numbers inferred from text.
+pub(crate) const VALUE_TOKEN: &str = "$__vdflex_private_value";
+
+/// The key of the map that a [`Value::Int32`] is deserialized from. See [`VALUE_TOKEN`].
+pub(crate) const INT32_TOKEN: &str = "$__vdflex_private_int32";
+
+/// The key of the map that a [`Value::Int64`] is deserialized from. See [`VALUE_TOKEN`].
+pub(crate) const INT64_TOKEN: &str = "$__vdflex_private_int64";
+
+/// The key of the map that a [`Value::UInt64`] is deserialized from. See [`VALUE_TOKEN`].
+pub(crate) const UINT64_TOKEN: &str = "$__vdflex_private_uint64";
+
+/// The key of the map that a [`Value::Float32`] is deserialized from. See [`VALUE_TOKEN`].
+pub(crate) const FLOAT32_TOKEN: &str = "$__vdflex_private_float32";
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Object(a), Value::Object(b)) => a == b,
+            (Value::Int32(a), Value::Int32(b)) => a == b,
+            (Value::Int64(a), Value::Int64(b)) => a == b,
+            (Value::UInt64(a), Value::UInt64(b)) => a == b,
+            (Value::Float32(a), Value::Float32(b)) => a.to_bits() == b.to_bits(),
+            (Value::Pointer(a), Value::Pointer(b)) => a == b,
+            (Value::Color(a), Value::Color(b)) => a == b,
+            _ => false,
+        }
+    }
 }
 
+impl Eq for Value {}
+
 impl serde::Serialize for Value {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
         match self {
             Value::String(string) => string.serialize(serializer),
             Value::Object(object) => object.serialize(serializer),
+            Value::Int32(v) => serializer.serialize_i32(*v),
+            Value::Int64(v) => serializer.serialize_i64(*v),
+            Value::UInt64(v) => serializer.serialize_u64(*v),
+            Value::Float32(v) => serializer.serialize_f32(*v),
+            Value::Pointer(v) => serializer.serialize_newtype_struct(POINTER_TOKEN, v),
+            Value::Color(rgba) => {
+                serializer.serialize_newtype_struct(COLOR_TOKEN, &u32::from_le_bytes(*rgba))
+            }
         }
     }
 }
 
 impl<'de> serde::Deserialize<'de> for Value {
     fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
-        deserializer.deserialize_any(ValueVisitor)
+        deserializer.deserialize_newtype_struct(VALUE_TOKEN, ValueVisitor)
     }
 }
 
@@ -162,7 +257,11 @@ impl<'de> serde::de::Visitor<'de> for ValueVisitor {
     type Value = Value;
 
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("a KeyValues string or object")
+        formatter.write_str("a KeyValues value")
+    }
+
+    fn visit_bool<E: serde::de::Error>(self, v: bool) -> result::Result<Self::Value, E> {
+        Ok(Value::String(String::from(if v { "1" } else { "0" })))
     }
 
     fn visit_str<E: serde::de::Error>(self, v: &str) -> result::Result<Self::Value, E> {
@@ -173,6 +272,25 @@ impl<'de> serde::de::Visitor<'de> for ValueVisitor {
         Ok(Value::String(v))
     }
 
+    fn visit_i64<E: serde::de::Error>(self, v: i64) -> result::Result<Self::Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, v: u64) -> result::Result<Self::Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, v: f64) -> result::Result<Self::Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_newtype_struct<D: serde::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> result::Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
     fn visit_map<A: serde::de::MapAccess<'de>>(
         self,
         mut map: A,
@@ -187,8 +305,20 @@ impl<'de> serde::de::Visitor<'de> for ValueVisitor {
         #[cfg(not(feature = "preserve_order"))]
         let mut obj = Object::new();
 
-        while let Some((key, value)) = map.next_entry::<String, Value>()? {
-            match obj.entry(key) {
+        let mut key = map.next_key::<String>()?;
+        match key.as_deref() {
+            Some(POINTER_TOKEN) => return Ok(Value::Pointer(map.next_value()?)),
+            Some(COLOR_TOKEN) => return Ok(Value::Color(map.next_value::<u32>()?.to_le_bytes())),
+            Some(INT32_TOKEN) => return Ok(Value::Int32(map.next_value()?)),
+            Some(INT64_TOKEN) => return Ok(Value::Int64(map.next_value()?)),
+            Some(UINT64_TOKEN) => return Ok(Value::UInt64(map.next_value()?)),
+            Some(FLOAT32_TOKEN) => return Ok(Value::Float32(map.next_value()?)),
+            _ => {}
+        }
+
+        while let Some(k) = key {
+            let value = map.next_value::<Value>()?;
+            match obj.entry(k) {
                 Entry::Occupied(mut oe) => {
                     oe.get_mut().push(value);
                 }
@@ -196,6 +326,7 @@ impl<'de> serde::de::Visitor<'de> for ValueVisitor {
                     ve.insert(vec![value]);
                 }
             }
+            key = map.next_key()?;
         }
 
         Ok(Value::Object(obj))
@@ -210,11 +341,62 @@ pub type Object = indexmap::IndexMap<String, Vec<Value>>;
 #[cfg(not(feature = "preserve_order"))]
 pub type Object = std::collections::BTreeMap<String, Vec<Value>>;
 
+/// Case-insensitive lookups for [`Object`].
+///
+/// The Source engine compares keys ignoring ASCII case, so `$BaseTexture` and `$basetexture`
+/// refer to the same key. These methods find a key regardless of its case without changing how
+/// it is spelled in the object. A key spelled exactly like the one being looked up is preferred.
+///
+/// ```
+/// use vdflex::{KeyValues, ObjectExt, Value};
+///
+/// let kv: KeyValues = vdflex::from_str(r#""AppID" "440""#).unwrap();
+/// let (key, values) = kv.root.get_key_value_ignore_case("appid").unwrap();
+/// assert_eq!(key, "AppID");
+/// assert_eq!(values, &[Value::String(String::from("440"))]);
+/// ```
+pub trait ObjectExt {
+    /// Returns the spelling of `key` in this object and its values, ignoring ASCII case.
+    fn get_key_value_ignore_case(&self, key: &str) -> Option<(&String, &Vec<Value>)>;
+
+    /// Returns the values of `key`, ignoring ASCII case.
+    fn get_ignore_case(&self, key: &str) -> Option<&Vec<Value>> {
+        self.get_key_value_ignore_case(key)
+            .map(|(_, values)| values)
+    }
+
+    /// Returns a mutable reference to the values of `key`, ignoring ASCII case.
+    fn get_ignore_case_mut(&mut self, key: &str) -> Option<&mut Vec<Value>>;
+
+    /// Returns `true` if the object contains `key`, ignoring ASCII case.
+    fn contains_key_ignore_case(&self, key: &str) -> bool {
+        self.get_key_value_ignore_case(key).is_some()
+    }
+}
+
+impl ObjectExt for Object {
+    fn get_key_value_ignore_case(&self, key: &str) -> Option<(&String, &Vec<Value>)> {
+        self.get_key_value(key).or_else(|| {
+            self.iter()
+                .find(|(candidate, _)| candidate.eq_ignore_ascii_case(key))
+        })
+    }
+
+    fn get_ignore_case_mut(&mut self, key: &str) -> Option<&mut Vec<Value>> {
+        let key = match self.get_key_value_ignore_case(key) {
+            Some((key, _)) => key.clone(),
+            None => return None,
+        };
+        self.get_mut(&key)
+    }
+}
+
 /// Represents a KeyValues document.
 ///
 /// Note: A document typically consists of a single key-object pair. However, this library
 /// allows multiple root keys to exist simultaneously. This is because some implementations
-/// of KeyValues (such as the VMF format) *do* permit multiple root keys.
+/// of KeyValues (such as the VMF format) *do* permit multiple root keys. To process the root
+/// keys of a large document one at a time, use [`de::StreamDeserializer`].
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct KeyValues {
     /// The root object of the document.
@@ -11,6 +11,8 @@ use std::io::Write;
 pub use formatter::{BraceStyle, FormatOpts, Formatter, PrettyFormatter, Quoting};
 pub use serializer::Serializer;
 
+pub(crate) use formatter::write_string_element;
+
 /// Serialize the given value as a KeyValues value.
 ///
 /// # Errors
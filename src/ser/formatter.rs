@@ -25,6 +25,56 @@ pub trait Formatter {
     /// Writes a string value.
     fn write_string<W: ?Sized + Write>(&mut self, writer: &mut W, s: &str) -> io::Result<()>;
 
+    /// Writes a boolean value. By default, it is written as the string `1` or `0`.
+    fn write_bool<W: ?Sized + Write>(&mut self, writer: &mut W, v: bool) -> io::Result<()> {
+        self.write_string(writer, if v { "1" } else { "0" })
+    }
+
+    /// Writes a signed integer value that fits in 32 bits. By default, it is written as a
+    /// string.
+    fn write_i32<W: ?Sized + Write>(&mut self, writer: &mut W, v: i32) -> io::Result<()> {
+        self.write_string(writer, &v.to_string())
+    }
+
+    /// Writes an unsigned integer value that fits in 32 bits. By default, it is written as a
+    /// string.
+    fn write_u32<W: ?Sized + Write>(&mut self, writer: &mut W, v: u32) -> io::Result<()> {
+        self.write_string(writer, &v.to_string())
+    }
+
+    /// Writes a signed 64-bit integer value. By default, it is written as a string.
+    fn write_i64<W: ?Sized + Write>(&mut self, writer: &mut W, v: i64) -> io::Result<()> {
+        self.write_string(writer, &v.to_string())
+    }
+
+    /// Writes an unsigned 64-bit integer value. By default, it is written as a string.
+    fn write_u64<W: ?Sized + Write>(&mut self, writer: &mut W, v: u64) -> io::Result<()> {
+        self.write_string(writer, &v.to_string())
+    }
+
+    /// Writes a 32-bit floating point value. By default, it is written as a string.
+    fn write_f32<W: ?Sized + Write>(&mut self, writer: &mut W, v: f32) -> io::Result<()> {
+        self.write_string(writer, &v.to_string())
+    }
+
+    /// Writes a 64-bit floating point value. By default, it is written as a string.
+    fn write_f64<W: ?Sized + Write>(&mut self, writer: &mut W, v: f64) -> io::Result<()> {
+        self.write_string(writer, &v.to_string())
+    }
+
+    /// Writes a pointer, such as [`Value::Pointer`](crate::Value::Pointer). By default, it is
+    /// written as a string.
+    fn write_pointer<W: ?Sized + Write>(&mut self, writer: &mut W, v: u32) -> io::Result<()> {
+        self.write_string(writer, &v.to_string())
+    }
+
+    /// Writes an RGBA color, such as [`Value::Color`](crate::Value::Color). By default, it is
+    /// written as a string of four space-separated numbers.
+    fn write_color<W: ?Sized + Write>(&mut self, writer: &mut W, rgba: [u8; 4]) -> io::Result<()> {
+        let [r, g, b, a] = rgba;
+        self.write_string(writer, &format!("{r} {g} {b} {a}"))
+    }
+
     /// Writes a conditional tag. Must be called after `write_key` and before `write_string` and
     /// `end_key`.
     fn write_conditional<W: ?Sized + Write>(
@@ -157,60 +207,60 @@ impl PrettyFormatter {
         }
         Ok(())
     }
+}
 
-    fn write_string_element<W: ?Sized + Write>(
-        &mut self,
-        writer: &mut W,
-        s: &str,
-        quoting: Quoting,
-    ) -> io::Result<()> {
-        // Write a quote if necessary and remember for later.
-        let need_quotes = match quoting {
-            Quoting::Always => true,
-            Quoting::WhenRequired => {
-                s.is_empty()
-                    || s.starts_with('[')
-                    || s.contains(|c: char| c == '{' || c == '}' || c == '"' || c.is_whitespace())
-            }
-        };
-
-        if need_quotes {
-            writer.write_all(b"\"")?;
+/// Writes `s` with its special characters escaped, adding quotes if `quoting` asks for them.
+pub(crate) fn write_string_element<W: ?Sized + Write>(
+    writer: &mut W,
+    s: &str,
+    quoting: Quoting,
+) -> io::Result<()> {
+    // Write a quote if necessary and remember for later.
+    let need_quotes = match quoting {
+        Quoting::Always => true,
+        Quoting::WhenRequired => {
+            s.is_empty()
+                || s.starts_with('[')
+                || s.contains(|c: char| c == '{' || c == '}' || c == '"' || c.is_whitespace())
         }
+    };
 
-        // Write all fragment-escape pairs.
-        let mut start = 0;
-        for (current, unescaped) in s.match_indices(&['\t', '\n', '\\', '\"']) {
-            // Write a raw string fragment if one was present.
-            if start != current {
-                writer.write_all(s[start..current].as_bytes())?;
-            }
+    if need_quotes {
+        writer.write_all(b"\"")?;
+    }
 
-            // Now write the escape character.
-            let escaped = match unescaped.chars().next().unwrap() {
-                '\t' => "\\t",
-                '\n' => "\\n",
-                '\\' => "\\\\",
-                '\"' => "\\\"",
-                _ => unreachable!(),
-            };
-            writer.write_all(escaped.as_bytes())?;
-
-            start = current + unescaped.len();
+    // Write all fragment-escape pairs.
+    let mut start = 0;
+    for (current, unescaped) in s.match_indices(&['\t', '\n', '\\', '\"']) {
+        // Write a raw string fragment if one was present.
+        if start != current {
+            writer.write_all(&s.as_bytes()[start..current])?;
         }
 
-        // If there was a trailing fragment, write that too.
-        if start < s.len() {
-            writer.write_all(s[start..].as_bytes())?;
-        }
+        // Now write the escape character.
+        let escaped = match unescaped.chars().next().unwrap() {
+            '\t' => "\\t",
+            '\n' => "\\n",
+            '\\' => "\\\\",
+            '\"' => "\\\"",
+            _ => unreachable!(),
+        };
+        writer.write_all(escaped.as_bytes())?;
 
-        // write the trailing quote
-        if need_quotes {
-            writer.write_all(b"\"")?;
-        }
+        start = current + unescaped.len();
+    }
 
-        Ok(())
+    // If there was a trailing fragment, write that too.
+    if start < s.len() {
+        writer.write_all(&s.as_bytes()[start..])?;
+    }
+
+    // write the trailing quote
+    if need_quotes {
+        writer.write_all(b"\"")?;
     }
+
+    Ok(())
 }
 
 impl Default for PrettyFormatter {
@@ -340,7 +390,7 @@ impl Formatter for PrettyFormatter {
             writer.write_all(self.opts.separator.as_bytes())?;
         }
 
-        self.write_string_element(writer, s, quoting)
+        write_string_element(writer, s, quoting)
     }
 
     fn write_conditional<W: ?Sized + Write>(
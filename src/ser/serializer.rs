@@ -1,18 +1,21 @@
 use super::formatter::{Formatter, PrettyFormatter};
-use crate::{Error, Result};
+use crate::{Error, Result, COLOR_TOKEN, POINTER_TOKEN};
 use serde::ser::{
     Impossible, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
     SerializeTuple, SerializeTupleStruct, SerializeTupleVariant,
 };
 use serde::Serialize;
 use std::borrow::Cow;
-use std::io::Write;
+use std::io::{self, Write};
 
 /// Serializes Rust types into KeyValues text.
 pub struct Serializer<W, F = PrettyFormatter> {
     writer: W,
     formatter: F,
     elements: Vec<Option<Cow<'static, str>>>,
+    /// The name of the private newtype struct being serialized, if any. It tells the formatter
+    /// whether a `u32` is a pointer or a color.
+    token: Option<&'static str>,
 }
 
 impl<W: Write, F: Formatter> Serializer<W, F> {
@@ -22,6 +25,7 @@ impl<W: Write, F: Formatter> Serializer<W, F> {
             writer,
             formatter,
             elements: Vec::new(),
+            token: None,
         }
     }
 
@@ -79,21 +83,30 @@ impl<W: Write, F: Formatter> Serializer<W, F> {
     }
 
     fn string_value(&mut self, value: &str) -> Result<()> {
-        if let Some(key) = Self::current_key(&self.elements) {
+        self.scalar_value(|formatter, writer| formatter.write_string(writer, value))
+    }
+
+    /// Writes a value that is not an object, using `write` to write the value itself.
+    fn scalar_value(&mut self, write: impl FnOnce(&mut F, &mut W) -> io::Result<()>) -> Result<()> {
+        let Self {
+            writer,
+            formatter,
+            elements,
+            ..
+        } = self;
+        if let Some(key) = Self::current_key(elements) {
             // We're in a map or sequence. Write a key-value.
-            self.formatter
-                .begin_key(&mut self.writer)
-                .and_then(|_| self.formatter.write_string(&mut self.writer, key))
-                .and_then(|_| self.formatter.end_key(&mut self.writer))
-                .and_then(|_| self.formatter.begin_value(&mut self.writer))
-                .and_then(|_| self.formatter.write_string(&mut self.writer, value))
-                .and_then(|_| self.formatter.end_value(&mut self.writer))
+            formatter
+                .begin_key(writer)
+                .and_then(|_| formatter.write_string(writer, key))
+                .and_then(|_| formatter.end_key(writer))
+                .and_then(|_| formatter.begin_value(writer))
+                .and_then(|_| write(formatter, writer))
+                .and_then(|_| formatter.end_value(writer))
                 .map_err(Error::Io)
         } else {
-            // We're at the root level. Just write the plain string.
-            self.formatter
-                .write_string(&mut self.writer, value)
-                .map_err(Error::Io)
+            // We're at the root level. Just write the plain value.
+            write(formatter, writer).map_err(Error::Io)
         }
     }
 
@@ -110,6 +123,20 @@ impl<W: Write, F: Formatter> Serializer<W, F> {
     }
 }
 
+/// Implements `serialize_$ty` by converting the value to a type that the [`Formatter`] has a
+/// method for.
+macro_rules! serialize_scalar_impl {
+    ($($ty:ident => $method:ident($target:ty)),* $(,)?) => {
+        paste::paste! {
+            $(
+                fn [<serialize_ $ty>](self, v: $ty) -> $crate::Result<Self::Ok> {
+                    self.scalar_value(|formatter, writer| formatter.$method(writer, <$target>::from(v)))
+                }
+            )*
+        }
+    };
+}
+
 macro_rules! serialize_as_str_impl {
     ($ty:ident) => {
         paste::paste! {
@@ -124,7 +151,7 @@ macro_rules! serialize_as_str_impl {
     }
 }
 
-impl<'a, W: Write, F: Formatter> serde::Serializer for &'a mut Serializer<W, F> {
+impl<W: Write, F: Formatter> serde::Serializer for &mut Serializer<W, F> {
     type Ok = ();
     type Error = Error;
     type SerializeSeq = Self;
@@ -135,13 +162,32 @@ impl<'a, W: Write, F: Formatter> serde::Serializer for &'a mut Serializer<W, F>
     type SerializeStruct = Self;
     type SerializeStructVariant = Self;
 
-    fn serialize_bool(self, v: bool) -> Result<Self::Ok> {
-        let s = if v { "1" } else { "0" };
-        self.serialize_str(s)
+    serialize_scalar_impl!(
+        bool => write_bool(bool),
+        i8 => write_i32(i32),
+        i16 => write_i32(i32),
+        i32 => write_i32(i32),
+        i64 => write_i64(i64),
+        u8 => write_u32(u32),
+        u16 => write_u32(u32),
+        u64 => write_u64(u64),
+        f32 => write_f32(f32),
+        f64 => write_f64(f64),
+    );
+
+    serialize_as_str_impl!(i128, u128, char);
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok> {
+        match self.token.take() {
+            Some(POINTER_TOKEN) => {
+                self.scalar_value(|formatter, writer| formatter.write_pointer(writer, v))
+            }
+            Some(COLOR_TOKEN) => self
+                .scalar_value(|formatter, writer| formatter.write_color(writer, v.to_le_bytes())),
+            _ => self.scalar_value(|formatter, writer| formatter.write_u32(writer, v)),
+        }
     }
 
-    serialize_as_str_impl!(i8, i16, i32, i64, i128, u8, u16, u32, u64, u128, f32, f64, char);
-
     fn serialize_str(self, v: &str) -> Result<Self::Ok> {
         self.string_value(v)
     }
@@ -182,10 +228,17 @@ impl<'a, W: Write, F: Formatter> serde::Serializer for &'a mut Serializer<W, F>
 
     fn serialize_newtype_struct<T: ?Sized + Serialize>(
         self,
-        _name: &'static str,
+        name: &'static str,
         value: &T,
     ) -> Result<Self::Ok> {
-        value.serialize(self)
+        if name == POINTER_TOKEN || name == COLOR_TOKEN {
+            self.token = Some(name);
+            let result = value.serialize(&mut *self);
+            self.token = None;
+            result
+        } else {
+            value.serialize(self)
+        }
     }
 
     fn serialize_newtype_variant<T: ?Sized + Serialize>(
@@ -375,7 +428,7 @@ impl<'a, W: Write, F: Formatter> serde::Serializer for MapKeySerializer<'a, W, F
     }
 }
 
-impl<'a, W: Write, F: Formatter> SerializeSeq for &'a mut Serializer<W, F> {
+impl<W: Write, F: Formatter> SerializeSeq for &mut Serializer<W, F> {
     type Ok = ();
     type Error = Error;
 
@@ -390,7 +443,7 @@ impl<'a, W: Write, F: Formatter> SerializeSeq for &'a mut Serializer<W, F> {
     }
 }
 
-impl<'a, W: Write, F: Formatter> SerializeTuple for &'a mut Serializer<W, F> {
+impl<W: Write, F: Formatter> SerializeTuple for &mut Serializer<W, F> {
     type Ok = ();
     type Error = Error;
 
@@ -403,7 +456,7 @@ impl<'a, W: Write, F: Formatter> SerializeTuple for &'a mut Serializer<W, F> {
     }
 }
 
-impl<'a, W: Write, F: Formatter> SerializeTupleStruct for &'a mut Serializer<W, F> {
+impl<W: Write, F: Formatter> SerializeTupleStruct for &mut Serializer<W, F> {
     type Ok = ();
     type Error = Error;
 
@@ -416,7 +469,7 @@ impl<'a, W: Write, F: Formatter> SerializeTupleStruct for &'a mut Serializer<W,
     }
 }
 
-impl<'a, W: Write, F: Formatter> SerializeTupleVariant for &'a mut Serializer<W, F> {
+impl<W: Write, F: Formatter> SerializeTupleVariant for &mut Serializer<W, F> {
     type Ok = ();
     type Error = Error;
 
@@ -430,7 +483,7 @@ impl<'a, W: Write, F: Formatter> SerializeTupleVariant for &'a mut Serializer<W,
     }
 }
 
-impl<'a, W: Write, F: Formatter> SerializeMap for &'a mut Serializer<W, F> {
+impl<W: Write, F: Formatter> SerializeMap for &mut Serializer<W, F> {
     type Ok = ();
     type Error = Error;
 
@@ -449,7 +502,7 @@ impl<'a, W: Write, F: Formatter> SerializeMap for &'a mut Serializer<W, F> {
     }
 }
 
-impl<'a, W: Write, F: Formatter> SerializeStruct for &'a mut Serializer<W, F> {
+impl<W: Write, F: Formatter> SerializeStruct for &mut Serializer<W, F> {
     type Ok = ();
     type Error = Error;
 
@@ -468,7 +521,7 @@ impl<'a, W: Write, F: Formatter> SerializeStruct for &'a mut Serializer<W, F> {
     }
 }
 
-impl<'a, W: Write, F: Formatter> SerializeStructVariant for &'a mut Serializer<W, F> {
+impl<W: Write, F: Formatter> SerializeStructVariant for &mut Serializer<W, F> {
     type Ok = ();
     type Error = Error;
 
@@ -0,0 +1,186 @@
+//! Read and write the binary files of the Steam client.
+//!
+//! The Steam client caches information about apps and packages in `appcache/appinfo.vdf` and
+//! `appcache/packageinfo.vdf`. Each of these files is a list of entries with a header and
+//! [binary KeyValues](crate::binary) data. See [`appinfo`] and [`packageinfo`]. The non-Steam
+//! games in a user's library are stored in `shortcuts.vdf`, which [`shortcuts`] reads and
+//! writes.
+
+pub mod appinfo;
+pub mod packageinfo;
+mod sha1;
+pub mod shortcuts;
+
+use crate::binary::{self, Deserializer};
+use crate::ser::{BraceStyle, FormatOpts, PrettyFormatter, Quoting};
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Reads the little-endian fields of a file, reporting errors at their offset in the file.
+struct Bytes<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Bytes<'a> {
+    fn new(input: &'a [u8], pos: usize) -> Self {
+        Self { input, pos }
+    }
+
+    fn error(&self, reason: impl Into<String>) -> Error {
+        Error::InvalidBinary {
+            offset: self.pos,
+            reason: reason.into(),
+        }
+    }
+
+    fn slice(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .input
+            .get(self.pos..)
+            .and_then(|rest| rest.get(..len))
+            .ok_or_else(|| self.error("unexpected end of input"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self
+            .slice(N)?
+            .try_into()
+            .expect("slice has the right length"))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    /// Reads a null-terminated UTF-8 string.
+    fn string(&mut self) -> Result<&'a str> {
+        let rest = self.input.get(self.pos..).unwrap_or_default();
+        let len = rest
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| self.error("unexpected end of input"))?;
+        let s = std::str::from_utf8(&rest[..len]).map_err(|_| self.error("invalid UTF-8"))?;
+        self.pos += len + 1;
+        Ok(s)
+    }
+}
+
+/// Moves the offset of a binary KeyValues error that occurred in data starting at `base`.
+fn locate(error: Error, base: usize) -> Error {
+    match error {
+        Error::InvalidBinary { offset, reason } => Error::InvalidBinary {
+            offset: base + offset,
+            reason,
+        },
+        error => error,
+    }
+}
+
+/// Deserializes the binary KeyValues of an entry, which starts at `offset` in the file.
+fn deserialize_data<'a, T: Deserialize<'a>>(
+    data: &'a [u8],
+    offset: usize,
+    keys: Option<&'a [&'a str]>,
+) -> Result<T> {
+    let mut deserializer = match keys {
+        Some(keys) => Deserializer::with_keys(data, keys),
+        None => Deserializer::from_slice(data),
+    };
+    T::deserialize(&mut deserializer)
+        .and_then(|value| deserializer.end().map(|_| value))
+        .map_err(|err| locate(err, offset))
+}
+
+/// Returns the length of the binary KeyValues at the start of `input`, which starts at `offset`
+/// in the file.
+fn data_len(input: &[u8], offset: usize) -> Result<usize> {
+    let mut deserializer = Deserializer::from_slice(input);
+    deserializer.object().map_err(|err| locate(err, offset))?;
+    Ok(deserializer.position())
+}
+
+/// The binary KeyValues of an entry and the checksums that Steam stores with it.
+struct Data {
+    bytes: Vec<u8>,
+    text_sha1: [u8; 20],
+    binary_sha1: [u8; 20],
+}
+
+impl Data {
+    fn new<T: ?Sized + Serialize>(value: &T) -> Result<Self> {
+        let bytes = binary::to_vec(value)?;
+        let binary_sha1 = sha1::sha1(&bytes);
+        let text = crate::ser::to_string_pretty(value, PrettyFormatter::new(valve_format()))?;
+        Ok(Self {
+            bytes,
+            text_sha1: sha1::sha1(text.as_bytes()),
+            binary_sha1,
+        })
+    }
+}
+
+/// The format that Steam writes KeyValues text in, which the text checksum of an entry is
+/// computed from.
+fn valve_format() -> FormatOpts {
+    FormatOpts {
+        indent: String::from("\t"),
+        separator: String::from("\t\t"),
+        brace_style: BraceStyle::Allman,
+        quote_keys: Quoting::Always,
+        quote_macro_keys: Quoting::Always,
+        quote_values: Quoting::Always,
+    }
+}
+
+/// Collects the keys of binary KeyValues into a string table, like newer `appinfo.vdf` files do.
+#[derive(Default)]
+struct KeyTable {
+    keys: Vec<String>,
+    indexes: HashMap<String, u32>,
+}
+
+impl KeyTable {
+    /// Rewrites binary KeyValues `data` with the keys replaced by their index in the table.
+    fn encode(&mut self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut deserializer = Deserializer::from_slice(data);
+        let entries = deserializer.object()?;
+        let mut out = Vec::with_capacity(data.len());
+        binary::write_object(&mut out, &entries, &mut |out, key| {
+            let index = match self.indexes.get(key) {
+                Some(index) => *index,
+                None => {
+                    let index = self.keys.len() as u32;
+                    self.keys.push(key.to_string());
+                    self.indexes.insert(key.to_string(), index);
+                    index
+                }
+            };
+            out.extend_from_slice(&index.to_le_bytes());
+        });
+        Ok(out)
+    }
+
+    /// Writes the table as a count followed by null-terminated strings.
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(self.keys.len() as u32).to_le_bytes());
+        for key in &self.keys {
+            out.extend_from_slice(key.as_bytes());
+            out.push(0);
+        }
+    }
+}
+
+/// Reads a string table written by [`KeyTable::write`].
+fn read_key_table(input: &[u8], offset: usize) -> Result<Vec<&str>> {
+    let mut bytes = Bytes::new(input, offset);
+    let count = bytes.u32()?;
+    (0..count).map(|_| bytes.string()).collect()
+}
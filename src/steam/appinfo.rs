@@ -0,0 +1,376 @@
+//! Read and write `appinfo.vdf`, the Steam client's cache of app information.
+//!
+//! The file starts with a header, followed by one entry per app and, since version 29, a table
+//! of the keys used by the entries. All numbers are little-endian.
+//!
+//! | Field            | Type        | Notes                                                  |
+//! |------------------|-------------|--------------------------------------------------------|
+//! | Magic            | `u32`       | `0x07564427` plus the version minus 27                 |
+//! | Universe         | `u32`       | `1` for the public universe                            |
+//! | Key table offset | `i64`       | Since version 29                                       |
+//! | Entries          |             | Ended by an app ID of `0`                              |
+//! | Key table        |             | Since version 29. A `u32` count and that many strings  |
+//!
+//! Each entry is laid out as follows:
+//!
+//! | Field            | Type        | Notes                                                  |
+//! |------------------|-------------|--------------------------------------------------------|
+//! | App ID           | `u32`       |                                                        |
+//! | Size             | `u32`       | The number of bytes in the rest of the entry           |
+//! | Info state       | `u32`       |                                                        |
+//! | Last updated     | `u32`       | Unix time                                              |
+//! | PICS token       | `u64`       |                                                        |
+//! | Text SHA-1       | `[u8; 20]`  | Of the data written as KeyValues text                  |
+//! | Change number    | `u32`       |                                                        |
+//! | Binary SHA-1     | `[u8; 20]`  | Since version 28. Of the data as binary KeyValues      |
+//! | Data             |             | Binary KeyValues. Keys are `u32` table indexes in v29  |
+//!
+//! [`AppInfo`] reads the header of a file and iterates over its apps lazily, so the data of an
+//! app is only deserialized when asked for. [`Writer`] writes a file and computes the checksums
+//! of each app.
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//! use vdflex::steam::appinfo::{AppHeader, AppInfo, Version, Writer};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Data {
+//!     appinfo: Info,
+//! }
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Info {
+//!     appid: u32,
+//!     common: Common,
+//! }
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Common {
+//!     name: String,
+//! }
+//!
+//! let data = Data {
+//!     appinfo: Info {
+//!         appid: 440,
+//!         common: Common { name: String::from("Team Fortress 2") },
+//!     },
+//! };
+//! let header = AppHeader {
+//!     appid: 440,
+//!     change_number: 20_000_000,
+//!     ..Default::default()
+//! };
+//! let mut writer = Writer::new(Vec::new(), Version::V29, 1);
+//! writer.write_app(&header, &data)?;
+//! let file = writer.finish()?;
+//!
+//! let appinfo = AppInfo::from_slice(&file)?;
+//! assert_eq!(appinfo.version(), Version::V29);
+//! for app in appinfo.apps() {
+//!     let app = app?;
+//!     assert_eq!(app.header.change_number, 20_000_000);
+//!     assert_eq!(app.deserialize::<Data>()?, data);
+//! }
+//! # Ok::<(), vdflex::Error>(())
+//! ```
+
+use super::{deserialize_data, read_key_table, Bytes, Data, KeyTable};
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+
+/// The versions of `appinfo.vdf`.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Version {
+    /// Version 27, which has no binary checksum.
+    V27,
+    /// Version 28, which adds the binary checksum of each app.
+    V28,
+    /// Version 29, which stores the keys of all apps in a table at the end of the file.
+    V29,
+}
+
+impl Version {
+    const MAGIC_V27: u32 = 0x0756_4427;
+
+    fn from_magic(magic: u32) -> Option<Self> {
+        match magic.checked_sub(Self::MAGIC_V27)? {
+            0 => Some(Version::V27),
+            1 => Some(Version::V28),
+            2 => Some(Version::V29),
+            _ => None,
+        }
+    }
+
+    fn magic(self) -> u32 {
+        Self::MAGIC_V27 + self as u32
+    }
+}
+
+/// The header of an app in `appinfo.vdf`.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct AppHeader {
+    /// The ID of the app.
+    pub appid: u32,
+    /// The state of the app's information.
+    pub info_state: u32,
+    /// When the app's information was last updated, in seconds since the Unix epoch.
+    pub last_updated: u32,
+    /// The token needed to request the app's information, or `0` if none is needed.
+    pub pics_token: u64,
+    /// The SHA-1 hash of the app's data written as KeyValues text.
+    pub text_sha1: [u8; 20],
+    /// The change number of the app's information.
+    pub change_number: u32,
+    /// The SHA-1 hash of the app's data as binary KeyValues. Only present since version 28.
+    pub binary_sha1: Option<[u8; 20]>,
+}
+
+/// Reads an `appinfo.vdf` file. See the [module documentation](self) for an example.
+#[derive(Clone, Debug)]
+pub struct AppInfo<'a> {
+    input: &'a [u8],
+    version: Version,
+    universe: u32,
+    /// Where the entries start and end.
+    start: usize,
+    end: usize,
+    keys: Vec<&'a str>,
+}
+
+impl<'a> AppInfo<'a> {
+    /// Reads the header of an `appinfo.vdf` file, and its key table if it has one.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `input` does not start with the header of a known version.
+    pub fn from_slice(input: &'a [u8]) -> Result<Self> {
+        let mut bytes = Bytes::new(input, 0);
+        let magic = bytes.u32()?;
+        let version = Version::from_magic(magic).ok_or_else(|| {
+            bytes.pos = 0;
+            bytes.error(format!("unknown appinfo.vdf magic {magic:#010x}"))
+        })?;
+        let universe = bytes.u32()?;
+
+        let (end, keys) = if version == Version::V29 {
+            let offset = bytes.u64()?;
+            match usize::try_from(offset).ok().filter(|&o| o <= input.len()) {
+                Some(offset) => (offset, read_key_table(input, offset)?),
+                None => {
+                    bytes.pos -= 8;
+                    return Err(bytes.error(format!("key table offset {offset} is out of range")));
+                }
+            }
+        } else {
+            (input.len(), Vec::new())
+        };
+
+        Ok(Self {
+            input,
+            version,
+            universe,
+            start: bytes.pos,
+            end,
+            keys,
+        })
+    }
+
+    /// Returns the version of the file.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Returns the universe of the file, which is `1` for the public universe.
+    pub fn universe(&self) -> u32 {
+        self.universe
+    }
+
+    /// Returns an iterator over the apps in the file.
+    pub fn apps(&self) -> Apps<'_> {
+        Apps {
+            bytes: Bytes::new(&self.input[..self.end], self.start),
+            version: self.version,
+            keys: (self.version == Version::V29).then_some(self.keys.as_slice()),
+            done: false,
+        }
+    }
+}
+
+/// An iterator over the apps in an `appinfo.vdf` file, created by [`AppInfo::apps`].
+///
+/// Iteration ends after the first error.
+pub struct Apps<'a> {
+    bytes: Bytes<'a>,
+    version: Version,
+    keys: Option<&'a [&'a str]>,
+    done: bool,
+}
+
+impl<'a> Apps<'a> {
+    fn read(&mut self) -> Result<Option<App<'a>>> {
+        let appid = self.bytes.u32()?;
+        if appid == 0 {
+            return Ok(None);
+        }
+
+        let size = self.bytes.u32()? as usize;
+        let start = self.bytes.pos;
+        self.bytes.slice(size)?;
+
+        let mut entry = Bytes::new(&self.bytes.input[..start + size], start);
+        let header = AppHeader {
+            appid,
+            info_state: entry.u32()?,
+            last_updated: entry.u32()?,
+            pics_token: entry.u64()?,
+            text_sha1: entry.array()?,
+            change_number: entry.u32()?,
+            binary_sha1: match self.version {
+                Version::V27 => None,
+                Version::V28 | Version::V29 => Some(entry.array()?),
+            },
+        };
+        Ok(Some(App {
+            header,
+            data: &entry.input[entry.pos..],
+            offset: entry.pos,
+            keys: self.keys,
+        }))
+    }
+}
+
+impl<'a> Iterator for Apps<'a> {
+    type Item = Result<App<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let app = self.read();
+        self.done = !matches!(app, Ok(Some(_)));
+        app.transpose()
+    }
+}
+
+/// An app in an `appinfo.vdf` file.
+#[derive(Clone, Debug)]
+pub struct App<'a> {
+    /// The header of the app.
+    pub header: AppHeader,
+    data: &'a [u8],
+    offset: usize,
+    keys: Option<&'a [&'a str]>,
+}
+
+impl<'a> App<'a> {
+    /// Returns the binary KeyValues data of the app, as it is stored in the file.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Deserializes the data of the app. The data is usually an object with the single key
+    /// `appinfo`, and can be deserialized as a [`KeyValues`](crate::KeyValues) to keep every
+    /// value with its binary type.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the data is not valid binary KeyValues or does not match the structure expected
+    /// by `T`. The offsets of binary errors are relative to the start of the file.
+    pub fn deserialize<T: Deserialize<'a>>(&self) -> Result<T> {
+        deserialize_data(self.data, self.offset, self.keys)
+    }
+}
+
+/// Writes an `appinfo.vdf` file.
+///
+/// Apps are written to memory until [`finish`](Self::finish) is called, since the header of
+/// version 29 files points to the key table after them.
+pub struct Writer<W> {
+    writer: W,
+    version: Version,
+    universe: u32,
+    entries: Vec<u8>,
+    keys: KeyTable,
+}
+
+impl<W: Write> Writer<W> {
+    /// Creates a writer for a file with the specified `version` and `universe`.
+    pub fn new(writer: W, version: Version, universe: u32) -> Self {
+        Self {
+            writer,
+            version,
+            universe,
+            entries: Vec::new(),
+            keys: KeyTable::default(),
+        }
+    }
+
+    /// Writes an app with the specified `header` whose data is `value`. The checksums in
+    /// `header` are ignored and computed from `value` instead.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the app ID is `0`, which marks the end of the apps, or if `value` cannot be
+    /// serialized as binary KeyValues.
+    pub fn write_app<T: ?Sized + Serialize>(
+        &mut self,
+        header: &AppHeader,
+        value: &T,
+    ) -> Result<()> {
+        if header.appid == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "app ID 0 marks the end of appinfo.vdf",
+            )
+            .into());
+        }
+
+        let data = Data::new(value)?;
+        let bytes = match self.version {
+            Version::V29 => self.keys.encode(&data.bytes)?,
+            Version::V27 | Version::V28 => data.bytes,
+        };
+
+        let mut entry = Vec::with_capacity(60 + bytes.len());
+        entry.extend_from_slice(&header.info_state.to_le_bytes());
+        entry.extend_from_slice(&header.last_updated.to_le_bytes());
+        entry.extend_from_slice(&header.pics_token.to_le_bytes());
+        entry.extend_from_slice(&data.text_sha1);
+        entry.extend_from_slice(&header.change_number.to_le_bytes());
+        if self.version != Version::V27 {
+            entry.extend_from_slice(&data.binary_sha1);
+        }
+        entry.extend_from_slice(&bytes);
+
+        self.entries.extend_from_slice(&header.appid.to_le_bytes());
+        self.entries
+            .extend_from_slice(&(entry.len() as u32).to_le_bytes());
+        self.entries.extend_from_slice(&entry);
+        Ok(())
+    }
+
+    /// Writes the file and returns the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Fails if writing to the underlying writer fails.
+    pub fn finish(mut self) -> Result<W> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&self.version.magic().to_le_bytes());
+        out.extend_from_slice(&self.universe.to_le_bytes());
+        if self.version == Version::V29 {
+            let offset = out.len() + 8 + self.entries.len() + 4;
+            out.extend_from_slice(&(offset as u64).to_le_bytes());
+        }
+        out.extend_from_slice(&self.entries);
+        out.extend_from_slice(&0u32.to_le_bytes());
+        if self.version == Version::V29 {
+            self.keys.write(&mut out);
+        }
+
+        self.writer.write_all(&out)?;
+        Ok(self.writer)
+    }
+}
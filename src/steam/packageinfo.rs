@@ -0,0 +1,288 @@
+//! Read and write `packageinfo.vdf`, the Steam client's cache of package information.
+//!
+//! The file has the same structure as [`appinfo.vdf`](super::appinfo) with simpler entries.
+//! All numbers are little-endian.
+//!
+//! | Field            | Type        | Notes                                                  |
+//! |------------------|-------------|--------------------------------------------------------|
+//! | Magic            | `u32`       | `0x06565527` plus the version minus 27                 |
+//! | Universe         | `u32`       | `1` for the public universe                            |
+//! | Entries          |             | Ended by a package ID of `0xFFFFFFFF`                  |
+//!
+//! Each entry is laid out as follows:
+//!
+//! | Field            | Type        | Notes                                                  |
+//! |------------------|-------------|--------------------------------------------------------|
+//! | Package ID       | `u32`       |                                                        |
+//! | Text SHA-1       | `[u8; 20]`  | Of the data written as KeyValues text                  |
+//! | Change number    | `u32`       |                                                        |
+//! | PICS token       | `u64`       | Since version 28                                       |
+//! | Data             |             | Binary KeyValues                                       |
+//!
+//! Entries have no size, so [`Packages`] reads the data of each package to find where the
+//! next one starts. It is only deserialized into a Rust type when asked for.
+//!
+//! ```
+//! use std::collections::BTreeMap;
+//! use vdflex::steam::packageinfo::{PackageHeader, PackageInfo, Version, Writer};
+//! use vdflex::KeyValues;
+//!
+//! let data = BTreeMap::from([("0", BTreeMap::from([("packageid", 0u32), ("billingtype", 10)]))]);
+//! let mut writer = Writer::new(Vec::new(), Version::V28, 1);
+//! writer.write_package(&PackageHeader::default(), &data)?;
+//! let file = writer.finish()?;
+//!
+//! let packages = PackageInfo::from_slice(&file)?;
+//! let package = packages.packages().next().unwrap()?;
+//! assert_eq!(package.header.pics_token, Some(0));
+//! let data: KeyValues = package.deserialize()?;
+//! assert!(data.root.contains_key("0"));
+//! # Ok::<(), vdflex::Error>(())
+//! ```
+
+use super::{data_len, deserialize_data, Bytes, Data};
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+
+/// The ID that marks the end of the packages.
+const END: u32 = 0xFFFF_FFFF;
+
+/// The versions of `packageinfo.vdf`.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Version {
+    /// Version 27.
+    V27,
+    /// Version 28, which adds the PICS token of each package.
+    V28,
+}
+
+impl Version {
+    const MAGIC_V27: u32 = 0x0656_5527;
+
+    fn from_magic(magic: u32) -> Option<Self> {
+        match magic.checked_sub(Self::MAGIC_V27)? {
+            0 => Some(Version::V27),
+            1 => Some(Version::V28),
+            _ => None,
+        }
+    }
+
+    fn magic(self) -> u32 {
+        Self::MAGIC_V27 + self as u32
+    }
+}
+
+/// The header of a package in `packageinfo.vdf`.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct PackageHeader {
+    /// The ID of the package.
+    pub packageid: u32,
+    /// The SHA-1 hash of the package's data written as KeyValues text.
+    pub text_sha1: [u8; 20],
+    /// The change number of the package's information.
+    pub change_number: u32,
+    /// The token needed to request the package's information, or `0` if none is needed. Only
+    /// present since version 28.
+    pub pics_token: Option<u64>,
+}
+
+/// Reads a `packageinfo.vdf` file. See the [module documentation](self) for an example.
+#[derive(Clone, Debug)]
+pub struct PackageInfo<'a> {
+    input: &'a [u8],
+    version: Version,
+    universe: u32,
+}
+
+impl<'a> PackageInfo<'a> {
+    /// Reads the header of a `packageinfo.vdf` file.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `input` does not start with the header of a known version.
+    pub fn from_slice(input: &'a [u8]) -> Result<Self> {
+        let mut bytes = Bytes::new(input, 0);
+        let magic = bytes.u32()?;
+        let version = Version::from_magic(magic).ok_or_else(|| {
+            bytes.pos = 0;
+            bytes.error(format!("unknown packageinfo.vdf magic {magic:#010x}"))
+        })?;
+        let universe = bytes.u32()?;
+
+        Ok(Self {
+            input,
+            version,
+            universe,
+        })
+    }
+
+    /// Returns the version of the file.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Returns the universe of the file, which is `1` for the public universe.
+    pub fn universe(&self) -> u32 {
+        self.universe
+    }
+
+    /// Returns an iterator over the packages in the file.
+    pub fn packages(&self) -> Packages<'a> {
+        Packages {
+            bytes: Bytes::new(self.input, 8),
+            version: self.version,
+            done: false,
+        }
+    }
+}
+
+/// An iterator over the packages in a `packageinfo.vdf` file, created by
+/// [`PackageInfo::packages`].
+///
+/// Iteration ends after the first error.
+pub struct Packages<'a> {
+    bytes: Bytes<'a>,
+    version: Version,
+    done: bool,
+}
+
+impl<'a> Packages<'a> {
+    fn read(&mut self) -> Result<Option<Package<'a>>> {
+        let packageid = self.bytes.u32()?;
+        if packageid == END {
+            return Ok(None);
+        }
+
+        let header = PackageHeader {
+            packageid,
+            text_sha1: self.bytes.array()?,
+            change_number: self.bytes.u32()?,
+            pics_token: match self.version {
+                Version::V27 => None,
+                Version::V28 => Some(self.bytes.u64()?),
+            },
+        };
+        let offset = self.bytes.pos;
+        let len = data_len(&self.bytes.input[offset..], offset)?;
+        Ok(Some(Package {
+            header,
+            data: self.bytes.slice(len)?,
+            offset,
+        }))
+    }
+}
+
+impl<'a> Iterator for Packages<'a> {
+    type Item = Result<Package<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let package = self.read();
+        self.done = !matches!(package, Ok(Some(_)));
+        package.transpose()
+    }
+}
+
+/// A package in a `packageinfo.vdf` file.
+#[derive(Clone, Debug)]
+pub struct Package<'a> {
+    /// The header of the package.
+    pub header: PackageHeader,
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Package<'a> {
+    /// Returns the binary KeyValues data of the package, as it is stored in the file.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Deserializes the data of the package. The data is usually an object whose single key is
+    /// the package ID.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the data does not match the structure expected by `T`.
+    pub fn deserialize<T: Deserialize<'a>>(&self) -> Result<T> {
+        deserialize_data(self.data, self.offset, None)
+    }
+}
+
+/// Writes a `packageinfo.vdf` file.
+///
+/// Like [`appinfo::Writer`](super::appinfo::Writer), packages are written to memory until
+/// [`finish`](Self::finish) is called.
+pub struct Writer<W> {
+    writer: W,
+    version: Version,
+    universe: u32,
+    entries: Vec<u8>,
+}
+
+impl<W: Write> Writer<W> {
+    /// Creates a writer for a file with the specified `version` and `universe`.
+    pub fn new(writer: W, version: Version, universe: u32) -> Self {
+        Self {
+            writer,
+            version,
+            universe,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Writes a package with the specified `header` whose data is `value`. The checksum in
+    /// `header` is ignored and computed from `value` instead. A missing PICS token is written
+    /// as `0` in version 28 files.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the package ID is `0xFFFFFFFF`, which marks the end of the packages, or if
+    /// `value` cannot be serialized as binary KeyValues.
+    pub fn write_package<T: ?Sized + Serialize>(
+        &mut self,
+        header: &PackageHeader,
+        value: &T,
+    ) -> Result<()> {
+        if header.packageid == END {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "package ID 0xFFFFFFFF marks the end of packageinfo.vdf",
+            )
+            .into());
+        }
+
+        let data = Data::new(value)?;
+        self.entries
+            .extend_from_slice(&header.packageid.to_le_bytes());
+        self.entries.extend_from_slice(&data.text_sha1);
+        self.entries
+            .extend_from_slice(&header.change_number.to_le_bytes());
+        if self.version == Version::V28 {
+            let pics_token = header.pics_token.unwrap_or_default();
+            self.entries.extend_from_slice(&pics_token.to_le_bytes());
+        }
+        self.entries.extend_from_slice(&data.bytes);
+        Ok(())
+    }
+
+    /// Writes the file and returns the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Fails if writing to the underlying writer fails.
+    pub fn finish(mut self) -> Result<W> {
+        let mut out = Vec::with_capacity(12 + self.entries.len());
+        out.extend_from_slice(&self.version.magic().to_le_bytes());
+        out.extend_from_slice(&self.universe.to_le_bytes());
+        out.extend_from_slice(&self.entries);
+        out.extend_from_slice(&END.to_le_bytes());
+
+        self.writer.write_all(&out)?;
+        Ok(self.writer)
+    }
+}
@@ -0,0 +1,418 @@
+//! Read and write `shortcuts.vdf`, the list of non-Steam games in a user's library.
+//!
+//! Steam stores the shortcuts of each user in `userdata/<user id>/config/shortcuts.vdf`. The
+//! file is [binary KeyValues](crate::binary) with a `shortcuts` object, whose keys are the
+//! indexes of the shortcuts (`"0"`, `"1"`, ...). This module reads and writes the list as
+//! [`Shortcut`]s.
+//!
+//! ```no_run
+//! use vdflex::steam::shortcuts::{self, Shortcut};
+//!
+//! let path = "userdata/12345678/config/shortcuts.vdf";
+//! let mut list = shortcuts::load(path)?;
+//! let mut shortcut = Shortcut::new("My Game", r#""C:\Games\My Game\game.exe""#);
+//! shortcut.start_dir = String::from(r#""C:\Games\My Game\""#);
+//! shortcut.tags.push(String::from("Favorites"));
+//! list.push(shortcut);
+//! shortcuts::save(path, &list)?;
+//! # Ok::<(), vdflex::Error>(())
+//! ```
+
+use crate::{binary, Result, Value};
+use serde::de::{self, IgnoredAny, MapAccess, Unexpected, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::{fmt, result};
+
+/// A non-Steam game in a user's library.
+///
+/// Fields that Steam writes but this struct does not know about are kept in
+/// [`extra`](Self::extra), so that reading and writing a file does not lose them. Missing fields
+/// are empty, `false` or `0`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Shortcut {
+    /// The ID that Steam uses for the shortcut (`appid`). See [`appid`].
+    pub appid: u32,
+    /// The name of the game (`AppName`).
+    pub app_name: String,
+    /// The path of the executable, usually in quotes (`Exe`).
+    pub exe: String,
+    /// The working directory of the game, usually in quotes (`StartDir`).
+    pub start_dir: String,
+    /// The path of the icon, or an empty string to use the executable's icon (`icon`).
+    pub icon: String,
+    /// The path of the shortcut that the game was added from, if any (`ShortcutPath`).
+    pub shortcut_path: String,
+    /// The arguments to launch the game with (`LaunchOptions`).
+    pub launch_options: String,
+    /// Whether the game is hidden from the library (`IsHidden`).
+    pub is_hidden: bool,
+    /// Whether the desktop controller configuration is used (`AllowDesktopConfig`).
+    pub allow_desktop_config: bool,
+    /// Whether the Steam overlay is enabled (`AllowOverlay`).
+    pub allow_overlay: bool,
+    /// Whether the game is a VR game (`OpenVR`).
+    pub open_vr: bool,
+    /// When the game was last played, in seconds since the Unix epoch, or `0` if never
+    /// (`LastPlayTime`).
+    pub last_play_time: u32,
+    /// The collections that the game is in (`tags`).
+    pub tags: Vec<String>,
+    /// Any other fields of the shortcut, such as `Devkit` or `FlatpakAppID`.
+    pub extra: BTreeMap<String, Value>,
+}
+
+impl Shortcut {
+    /// Creates a shortcut for the executable `exe` named `app_name`, with the [`appid`] that
+    /// Steam would give it. The desktop configuration and the overlay are allowed, like they are
+    /// for shortcuts added through Steam.
+    pub fn new(app_name: impl Into<String>, exe: impl Into<String>) -> Self {
+        let app_name = app_name.into();
+        let exe = exe.into();
+        Self {
+            appid: appid(&exe, &app_name),
+            app_name,
+            exe,
+            allow_desktop_config: true,
+            allow_overlay: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// Computes the ID that Steam gives a shortcut to `exe` named `app_name`: the CRC-32 checksum
+/// of `exe` followed by `app_name`, with the high bit set.
+///
+/// The 64-bit ID used for grid artwork and `steam://rungameid/` links is this ID shifted left by
+/// 32 bits, plus `0x02000000`.
+///
+/// ```
+/// let appid = vdflex::steam::shortcuts::appid(r#""C:\Games\Game\game.exe""#, "My Game");
+/// assert_eq!(appid, 0xBBB4_6DE1);
+/// ```
+pub fn appid(exe: &str, app_name: &str) -> u32 {
+    crc32(exe.bytes().chain(app_name.bytes())) | 0x8000_0000
+}
+
+/// Computes the CRC-32 (ISO-HDLC) checksum of `bytes`.
+fn crc32(bytes: impl IntoIterator<Item = u8>) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Deserialize shortcuts from the contents of a `shortcuts.vdf` file.
+///
+/// # Errors
+///
+/// Fails if `v` is not valid binary KeyValues or does not contain a list of shortcuts.
+pub fn from_slice(v: &[u8]) -> Result<Vec<Shortcut>> {
+    binary::from_slice::<File>(v).map(|file| file.0)
+}
+
+/// Deserialize shortcuts from a `shortcuts.vdf` file read from `reader`.
+///
+/// # Errors
+///
+/// See [`from_slice`].
+pub fn from_reader<R: Read>(reader: R) -> Result<Vec<Shortcut>> {
+    binary::from_reader::<_, File>(reader).map(|file| file.0)
+}
+
+/// Serialize `shortcuts` as a `shortcuts.vdf` file into the specified writer.
+///
+/// # Errors
+///
+/// Fails if writing to `writer` fails, or if a string contains a null character.
+pub fn to_writer<W: Write>(writer: W, shortcuts: &[Shortcut]) -> Result<()> {
+    binary::to_writer(writer, &FileRef(shortcuts))
+}
+
+/// Serialize `shortcuts` as the contents of a `shortcuts.vdf` file.
+///
+/// # Errors
+///
+/// See [`to_writer`].
+pub fn to_vec(shortcuts: &[Shortcut]) -> Result<Vec<u8>> {
+    binary::to_vec(&FileRef(shortcuts))
+}
+
+/// Reads the shortcuts in the `shortcuts.vdf` file at `path`.
+///
+/// # Errors
+///
+/// Fails if the file cannot be read. See [`from_slice`] for the other errors.
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<Shortcut>> {
+    from_slice(&fs::read(path)?)
+}
+
+/// Writes `shortcuts` to the `shortcuts.vdf` file at `path`, replacing its contents. Steam should
+/// not be running, since it overwrites the file with its own list when it exits.
+///
+/// # Errors
+///
+/// Fails if the file cannot be written. See [`to_writer`] for the other errors.
+pub fn save(path: impl AsRef<Path>, shortcuts: &[Shortcut]) -> Result<()> {
+    fs::write(path, to_vec(shortcuts)?)?;
+    Ok(())
+}
+
+impl Serialize for Shortcut {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(13 + self.extra.len()))?;
+        // Steam writes the fields in this order.
+        map.serialize_entry("appid", &self.appid)?;
+        map.serialize_entry("AppName", &self.app_name)?;
+        map.serialize_entry("Exe", &self.exe)?;
+        map.serialize_entry("StartDir", &self.start_dir)?;
+        map.serialize_entry("icon", &self.icon)?;
+        map.serialize_entry("ShortcutPath", &self.shortcut_path)?;
+        map.serialize_entry("LaunchOptions", &self.launch_options)?;
+        map.serialize_entry("IsHidden", &self.is_hidden)?;
+        map.serialize_entry("AllowDesktopConfig", &self.allow_desktop_config)?;
+        map.serialize_entry("AllowOverlay", &self.allow_overlay)?;
+        map.serialize_entry("OpenVR", &self.open_vr)?;
+        map.serialize_entry("LastPlayTime", &self.last_play_time)?;
+        for (key, value) in &self.extra {
+            map.serialize_entry(key, value)?;
+        }
+        map.serialize_entry("tags", &IndexedRef(&self.tags))?;
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Shortcut {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+        deserializer.deserialize_map(ShortcutVisitor)
+    }
+}
+
+struct ShortcutVisitor;
+
+impl<'de> Visitor<'de> for ShortcutVisitor {
+    type Value = Shortcut;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a shortcut")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> result::Result<Self::Value, A::Error> {
+        let mut shortcut = Shortcut::default();
+        while let Some(key) = map.next_key::<String>()? {
+            // Older versions of Steam wrote some keys in lowercase.
+            match key.to_ascii_lowercase().as_str() {
+                "appid" => shortcut.appid = map.next_value::<Bits>()?.0,
+                "appname" => shortcut.app_name = map.next_value()?,
+                "exe" => shortcut.exe = map.next_value()?,
+                "startdir" => shortcut.start_dir = map.next_value()?,
+                "icon" => shortcut.icon = map.next_value()?,
+                "shortcutpath" => shortcut.shortcut_path = map.next_value()?,
+                "launchoptions" => shortcut.launch_options = map.next_value()?,
+                "ishidden" => shortcut.is_hidden = map.next_value::<Flag>()?.0,
+                "allowdesktopconfig" => shortcut.allow_desktop_config = map.next_value::<Flag>()?.0,
+                "allowoverlay" => shortcut.allow_overlay = map.next_value::<Flag>()?.0,
+                "openvr" => shortcut.open_vr = map.next_value::<Flag>()?.0,
+                "lastplaytime" => shortcut.last_play_time = map.next_value::<Bits>()?.0,
+                "tags" => shortcut.tags = map.next_value::<Indexed<String>>()?.0,
+                _ => {
+                    let value = map.next_value()?;
+                    shortcut.extra.insert(key, value);
+                }
+            }
+        }
+        Ok(shortcut)
+    }
+}
+
+/// The root of a `shortcuts.vdf` file, for deserialization.
+struct File(Vec<Shortcut>);
+
+impl<'de> Deserialize<'de> for File {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+        deserializer.deserialize_map(FileVisitor)
+    }
+}
+
+struct FileVisitor;
+
+impl<'de> Visitor<'de> for FileVisitor {
+    type Value = File;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an object with a `shortcuts` key")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> result::Result<Self::Value, A::Error> {
+        let mut shortcuts = None;
+        while let Some(key) = map.next_key::<String>()? {
+            if key.eq_ignore_ascii_case("shortcuts") {
+                shortcuts = Some(map.next_value::<Indexed<Shortcut>>()?.0);
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        shortcuts
+            .map(File)
+            .ok_or_else(|| de::Error::missing_field("shortcuts"))
+    }
+}
+
+/// The root of a `shortcuts.vdf` file, for serialization.
+struct FileRef<'a>(&'a [Shortcut]);
+
+impl Serialize for FileRef<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("shortcuts", &IndexedRef(self.0))?;
+        map.end()
+    }
+}
+
+/// A list that is stored as an object whose keys are the indexes of the elements, which is how
+/// Steam stores lists in binary KeyValues.
+struct Indexed<T>(Vec<T>);
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Indexed<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+        deserializer.deserialize_map(IndexedVisitor(PhantomData))
+    }
+}
+
+struct IndexedVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for IndexedVisitor<T> {
+    type Value = Indexed<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an object with numbered keys")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> result::Result<Self::Value, A::Error> {
+        let mut elements = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(key) = map.next_key::<String>()? {
+            let index = key
+                .parse::<usize>()
+                .map_err(|_| de::Error::invalid_value(Unexpected::Str(&key), &self))?;
+            elements.push((index, map.next_value()?));
+        }
+        // Steam numbers the elements in order, but gaps and other orders are harmless.
+        elements.sort_by_key(|(index, _)| *index);
+        Ok(Indexed(elements.into_iter().map(|(_, v)| v).collect()))
+    }
+}
+
+/// Serializes a list like [`Indexed`].
+struct IndexedRef<'a, T>(&'a [T]);
+
+impl<T: Serialize> Serialize for IndexedRef<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (i, element) in self.0.iter().enumerate() {
+            map.serialize_entry(&i.to_string(), element)?;
+        }
+        map.end()
+    }
+}
+
+/// A `u32` that may be stored in the bits of a signed 32-bit integer.
+struct Bits(u32);
+
+impl<'de> Deserialize<'de> for Bits {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+        deserializer.deserialize_any(BitsVisitor)
+    }
+}
+
+struct BitsVisitor;
+
+impl Visitor<'_> for BitsVisitor {
+    type Value = Bits;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a 32-bit integer")
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> result::Result<Self::Value, E> {
+        match i32::try_from(v) {
+            Ok(v) => Ok(Bits(v as u32)),
+            Err(_) => Err(E::invalid_value(Unexpected::Signed(v), &self)),
+        }
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> result::Result<Self::Value, E> {
+        match u32::try_from(v) {
+            Ok(v) => Ok(Bits(v)),
+            Err(_) => Err(E::invalid_value(Unexpected::Unsigned(v), &self)),
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> result::Result<Self::Value, E> {
+        if let Ok(v) = v.parse::<u64>() {
+            self.visit_u64(v)
+        } else if let Ok(v) = v.parse::<i64>() {
+            self.visit_i64(v)
+        } else {
+            Err(E::invalid_value(Unexpected::Str(v), &self))
+        }
+    }
+}
+
+/// A `bool` that is stored as an integer.
+struct Flag(bool);
+
+impl<'de> Deserialize<'de> for Flag {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+        deserializer.deserialize_any(FlagVisitor)
+    }
+}
+
+struct FlagVisitor;
+
+impl Visitor<'_> for FlagVisitor {
+    type Value = Flag;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a boolean or an integer")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> result::Result<Self::Value, E> {
+        Ok(Flag(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> result::Result<Self::Value, E> {
+        Ok(Flag(v != 0))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> result::Result<Self::Value, E> {
+        Ok(Flag(v != 0))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> result::Result<Self::Value, E> {
+        match v {
+            "1" => Ok(Flag(true)),
+            "0" => Ok(Flag(false)),
+            _ => Err(E::invalid_value(Unexpected::Str(v), &self)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(*b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32([]), 0);
+    }
+}
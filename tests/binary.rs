@@ -0,0 +1,284 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use vdflex::binary::{from_slice, to_vec, BinaryFormatter};
+use vdflex::ser::Formatter;
+use vdflex::{Error, KeyValues, Result, Value};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Shortcut {
+    appid: u32,
+    #[serde(rename = "AppName")]
+    app_name: String,
+    #[serde(rename = "IsHidden")]
+    is_hidden: bool,
+    #[serde(rename = "LastPlayTime")]
+    last_play_time: u32,
+    tags: BTreeMap<String, String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Shortcuts {
+    shortcuts: BTreeMap<String, Shortcut>,
+}
+
+#[test]
+fn shortcuts_round_trip() -> Result<()> {
+    let shortcuts = Shortcuts {
+        shortcuts: BTreeMap::from([(
+            String::from("0"),
+            Shortcut {
+                appid: 0x8000_1234,
+                app_name: String::from("Game"),
+                is_hidden: true,
+                last_play_time: 1_700_000_000,
+                tags: BTreeMap::from([(String::from("0"), String::from("favorite"))]),
+            },
+        )]),
+    };
+
+    let mut expected = Vec::new();
+    expected.extend(b"\x00shortcuts\0\x000\0");
+    expected.extend(b"\x02appid\0");
+    expected.extend(0x8000_1234u32.to_le_bytes());
+    expected.extend(b"\x01AppName\0Game\0");
+    expected.extend(b"\x02IsHidden\0\x01\0\0\0");
+    expected.extend(b"\x02LastPlayTime\0");
+    expected.extend(1_700_000_000u32.to_le_bytes());
+    expected.extend(b"\x00tags\0\x010\0favorite\0\x08");
+    expected.extend(b"\x08\x08\x08");
+
+    let bytes = to_vec(&shortcuts)?;
+    assert_eq!(bytes, expected);
+    assert_eq!(from_slice::<Shortcuts>(&bytes)?, shortcuts);
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Numbers {
+    byte: u8,
+    int: i32,
+    long: i64,
+    ulong: u64,
+    float: f32,
+    double: f64,
+    text: String,
+}
+
+#[test]
+fn numbers_keep_their_types() -> Result<()> {
+    let numbers = Numbers {
+        byte: 7,
+        int: -2,
+        long: -3,
+        ulong: u64::MAX,
+        float: 1.5,
+        double: 0.25,
+        text: String::from("-4"),
+    };
+    let bytes = to_vec(&numbers)?;
+    assert_eq!(bytes[0], 0x02);
+    assert_eq!(from_slice::<Numbers>(&bytes)?, numbers);
+
+    // Strings are parsed if a number is expected, and numbers are formatted if a string is.
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Swapped {
+        text: i32,
+        int: String,
+        long: String,
+    }
+    assert_eq!(
+        from_slice::<Swapped>(&bytes)?,
+        Swapped {
+            text: -4,
+            int: String::from("-2"),
+            long: String::from("-3"),
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn read_other_types() -> Result<()> {
+    let mut bytes = Vec::new();
+    bytes.extend(b"\x05wide\0");
+    bytes.extend("h\u{e9}".encode_utf16().flat_map(u16::to_le_bytes));
+    bytes.extend(b"\0\0");
+    bytes.extend(b"\x06color\0\x01\x02\x03\x04");
+    bytes.extend(b"\x04ptr\0\x10\0\0\0");
+    bytes.extend(b"\x08");
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Other {
+        wide: String,
+        color: u32,
+        ptr: u32,
+    }
+    assert_eq!(
+        from_slice::<Other>(&bytes)?,
+        Other {
+            wide: String::from("h\u{e9}"),
+            color: 0x0403_0201,
+            ptr: 0x10,
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn repeated_keys() -> Result<()> {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Repeated {
+        tag: Vec<String>,
+        last: i32,
+    }
+    let bytes = b"\x01tag\0a\0\x02last\0\x01\0\0\0\x01tag\0b\0\x02last\0\x02\0\0\0\x08";
+    assert_eq!(
+        from_slice::<Repeated>(bytes)?,
+        Repeated {
+            tag: vec![String::from("a"), String::from("b")],
+            last: 2,
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn binary_errors() {
+    let invalid = |bytes: &[u8]| match from_slice::<BTreeMap<String, String>>(bytes) {
+        Err(Error::InvalidBinary { offset, .. }) => offset,
+        other => panic!("expected invalid binary error, got {other:?}"),
+    };
+    assert_eq!(invalid(b""), 0);
+    assert_eq!(invalid(b"\x01key\0value"), 5);
+    assert_eq!(invalid(b"\x01key\0value\0"), 11);
+    assert_eq!(invalid(b"\x09key\0value\0\x08"), 0);
+    assert_eq!(invalid(b"\x01key\0\xff\0\x08"), 5);
+    assert_eq!(invalid(b"\x08\x08"), 1);
+
+    assert!(to_vec(&BTreeMap::from([("key", "null\0")])).is_err());
+    assert!(to_vec(&"root string").is_err());
+    assert!(BinaryFormatter::new().end_object(&mut Vec::new()).is_err());
+
+    let nested = |depth: usize| {
+        let mut bytes = b"\x00a\0".repeat(depth);
+        bytes.extend(b"\x08".repeat(depth + 1));
+        bytes
+    };
+    assert!(from_slice::<KeyValues>(&nested(128)).is_ok());
+    assert_eq!(invalid(&nested(129)), 128 * 3);
+    match from_slice::<KeyValues>(&nested(200_000)) {
+        Err(Error::InvalidBinary { reason, .. }) => assert_eq!(reason, "nested too deeply"),
+        other => panic!("expected invalid binary error, got {other:?}"),
+    }
+}
+
+#[test]
+fn integer_conversions() {
+    #[derive(Debug, Deserialize)]
+    struct Entry<T> {
+        a: T,
+    }
+
+    let int32 = |v: i32| [b"\x02a\0".as_slice(), &v.to_le_bytes(), b"\x08"].concat();
+    let int64 = |v: i64| [b"\x0Aa\0".as_slice(), &v.to_le_bytes(), b"\x08"].concat();
+    let uint64 = |v: u64| [b"\x07a\0".as_slice(), &v.to_le_bytes(), b"\x08"].concat();
+
+    // The bits of signed entries are reinterpreted for unsigned types of the same size.
+    assert_eq!(from_slice::<Entry<u32>>(&int32(-1)).unwrap().a, u32::MAX);
+    assert_eq!(from_slice::<Entry<u64>>(&int64(-1)).unwrap().a, u64::MAX);
+
+    // Other conversions need the value to fit.
+    assert_eq!(from_slice::<Entry<u64>>(&int32(5)).unwrap().a, 5);
+    assert_eq!(from_slice::<Entry<i8>>(&uint64(5)).unwrap().a, 5);
+    assert!(from_slice::<Entry<u64>>(&int32(-1)).is_err());
+    assert!(from_slice::<Entry<i64>>(&uint64(u64::MAX)).is_err());
+    assert!(from_slice::<Entry<u8>>(&int32(256)).is_err());
+}
+
+#[test]
+fn enums_round_trip() -> Result<()> {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Launch {
+        Default,
+        Exe(String),
+        Args(i32, String),
+        Window { width: u32, height: u32 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Wrapper {
+        launch: Vec<Launch>,
+        single: Launch,
+    }
+
+    let wrapper = Wrapper {
+        launch: vec![
+            Launch::Default,
+            Launch::Exe(String::from("game.exe")),
+            Launch::Args(1, String::from("-novid")),
+            Launch::Window {
+                width: 1280,
+                height: 720,
+            },
+        ],
+        single: Launch::Args(2, String::from("-console")),
+    };
+    let bytes = to_vec(&wrapper)?;
+    assert_eq!(from_slice::<Wrapper>(&bytes)?, wrapper);
+
+    Ok(())
+}
+
+#[test]
+fn values_keep_their_types() -> Result<()> {
+    let mut bytes = Vec::new();
+    bytes.extend(b"\x00shortcut\0");
+    bytes.extend(b"\x02appid\0\x34\x12\0\x80");
+    bytes.extend(b"\x01AppName\0Game\0");
+    bytes.extend(b"\x02LastPlayTime\0\x00\xf1\x53\x65");
+    bytes.extend(b"\x03scale\0\0\0\xc0\x3f");
+    bytes.extend(b"\x04ptr\0\x10\0\0\0");
+    bytes.extend(b"\x06color\0\xff\x80\0\x40");
+    bytes.extend(b"\x07size\0\x01\0\0\0\0\0\0\x80");
+    bytes.extend(b"\x0Aoffset\0\xfe\xff\xff\xff\xff\xff\xff\xff");
+    bytes.extend(b"\x08\x08");
+
+    let kv: KeyValues = from_slice(&bytes)?;
+    let Value::Object(shortcut) = &kv.root["shortcut"][0] else {
+        panic!("expected object");
+    };
+    assert_eq!(shortcut["appid"], [Value::Int32(0x8000_1234u32 as i32)]);
+    assert_eq!(shortcut["AppName"], [Value::String(String::from("Game"))]);
+    assert_eq!(shortcut["LastPlayTime"], [Value::Int32(1_700_000_000)]);
+    assert_eq!(shortcut["scale"], [Value::Float32(1.5)]);
+    assert_eq!(shortcut["ptr"], [Value::Pointer(0x10)]);
+    assert_eq!(shortcut["color"], [Value::Color([0xff, 0x80, 0, 0x40])]);
+    assert_eq!(shortcut["size"], [Value::UInt64(0x8000_0000_0000_0001)]);
+    assert_eq!(shortcut["offset"], [Value::Int64(-2)]);
+    assert_eq!(from_slice::<KeyValues>(&to_vec(&kv)?)?, kv);
+    #[cfg(feature = "preserve_order")]
+    assert_eq!(to_vec(&kv)?, bytes);
+
+    // Text has no types, so typed values are written as strings.
+    let mut expected = BTreeMap::from([
+        ("appid", "-2147478988"),
+        ("AppName", "Game"),
+        ("LastPlayTime", "1700000000"),
+        ("scale", "1.5"),
+        ("ptr", "16"),
+        ("color", "255 128 0 64"),
+        ("size", "9223372036854775809"),
+        ("offset", "-2"),
+    ]);
+    let text: BTreeMap<String, BTreeMap<String, String>> =
+        vdflex::from_str(&vdflex::to_string(&kv)?)?;
+    for (key, value) in &text["shortcut"] {
+        assert_eq!(expected.remove(key.as_str()), Some(value.as_str()), "{key}");
+    }
+    assert!(expected.is_empty());
+
+    // Floats are compared by their bits, so every value equals itself.
+    assert_eq!(Value::Float32(f32::NAN), Value::Float32(f32::NAN));
+    assert_ne!(Value::Float32(0.0), Value::Float32(-0.0));
+    Ok(())
+}
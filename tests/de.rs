@@ -0,0 +1,1507 @@
+use indoc::indoc;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use vdflex::de::{
+    from_str, from_str_at, from_str_at_with_opts, from_str_with_opts, kv_from_str,
+    kv_from_str_with_opts, parse_with_diagnostics, DeserializeOpts, Diagnostic, DuplicateKeys,
+    Event, Reader, Severity,
+};
+use vdflex::ser::{kv_to_string, kv_to_string_pretty, FormatOpts, PrettyFormatter, Quoting};
+use vdflex::{Entries, Error, KeyValues, ObjectExt, Result, Value};
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct UnitStruct;
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct NewTypeStruct(i32);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Struct {
+    c: char,
+    i: i32,
+    s: String,
+    b: bool,
+}
+
+#[test]
+fn deserialize_root_level_primitives() -> Result<()> {
+    assert!(!from_str::<bool>("0")?);
+    assert!(from_str::<bool>("1")?);
+    assert_eq!(from_str::<u8>("17")?, 17);
+    assert_eq!(from_str::<i16>("362")?, 362);
+    assert_eq!(from_str::<i32>("-843217")?, -843217);
+    assert_eq!(from_str::<f32>("3.1415927")?, std::f32::consts::PI);
+    assert_eq!(from_str::<u64>("18446744073709551615")?, u64::MAX);
+    assert_eq!(from_str::<char>("q")?, 'q');
+    assert_eq!(from_str::<char>(r#""\t""#)?, '\t');
+    assert_eq!(from_str::<String>("simple")?, "simple");
+    assert_eq!(from_str::<String>("\"Hello, world!\"")?, "Hello, world!");
+
+    Ok(())
+}
+
+/// Unwraps the error returned by `result`, without its location.
+fn inner_err<T: std::fmt::Debug>(result: Result<T>) -> Error {
+    match result.unwrap_err() {
+        Error::Located { inner, .. } => *inner,
+        err => err,
+    }
+}
+
+#[test]
+fn deserialize_invalid_primitives() {
+    assert!(matches!(inner_err(from_str::<bool>("2")), Error::Serde(_)));
+    assert!(matches!(inner_err(from_str::<u8>("256")), Error::Serde(_)));
+    assert!(matches!(
+        inner_err(from_str::<i32>("twelve")),
+        Error::Serde(_)
+    ));
+    assert!(matches!(inner_err(from_str::<char>("ab")), Error::Serde(_)));
+}
+
+#[test]
+fn deserialize_unit() -> Result<()> {
+    from_str::<()>("\"\"")?;
+    assert_eq!(from_str::<UnitStruct>("\"\"")?, UnitStruct);
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_new_type_struct() -> Result<()> {
+    assert_eq!(from_str::<NewTypeStruct>("100")?, NewTypeStruct(100));
+    assert_eq!(
+        kv_from_str::<NewTypeStruct>("NewTypeStruct 100")?,
+        (String::from("NewTypeStruct"), NewTypeStruct(100))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_struct() -> Result<()> {
+    let expected = Struct {
+        c: 'X',
+        i: -123,
+        s: String::from("Test data"),
+        b: true,
+    };
+
+    let s = from_str::<Struct>(indoc! {r#"
+        "c" "X"
+        "i" "-123"
+        "s" "Test data"
+        "b" "1"
+    "#})?;
+    assert_eq!(s, expected);
+
+    let (key, s) = kv_from_str::<Struct>(indoc! {r#"
+        data {
+            c X
+            i -123
+            s "Test data"
+            b 1
+        }
+    "#})?;
+    assert_eq!(key, "data");
+    assert_eq!(s, expected);
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_option() -> Result<()> {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Optional {
+        present: Option<i32>,
+        missing: Option<i32>,
+    }
+
+    assert_eq!(
+        from_str::<Optional>("present 42")?,
+        Optional {
+            present: Some(42),
+            missing: None
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_sequence() -> Result<()> {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Numbers {
+        nums: Vec<f64>,
+        pair: (bool, String),
+    }
+
+    let numbers = from_str::<Numbers>(indoc! {r#"
+        "nums" "1"
+        "nums" "2"
+        "pair" "1"
+        "nums" "3"
+        "pair" "Greetings, traveler."
+    "#})?;
+    assert_eq!(
+        numbers,
+        Numbers {
+            nums: vec![1.0, 2.0, 3.0],
+            pair: (true, String::from("Greetings, traveler.")),
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_typed_scalars() -> Result<()> {
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Settings {
+        volume: f32,
+        fullscreen: bool,
+        max_players: u8,
+        offset: i64,
+        separator: char,
+    }
+
+    let input = indoc! {r#"
+        "Volume" "0.75"
+        "Fullscreen" "1"
+        "MaxPlayers" "24"
+        "Offset" "-9000000000"
+        "Separator" ";"
+    "#};
+    assert_eq!(
+        from_str::<Settings>(input)?,
+        Settings {
+            volume: 0.75,
+            fullscreen: true,
+            max_players: 24,
+            offset: -9_000_000_000,
+            separator: ';',
+        }
+    );
+
+    // `true` and `false` are only accepted when enabled
+    assert!(matches!(
+        inner_err(from_str::<bool>("true")),
+        Error::Serde(_)
+    ));
+    let opts = DeserializeOpts {
+        bool_literals: true,
+        ..Default::default()
+    };
+    assert!(from_str_with_opts::<bool>("TRUE", opts.clone())?);
+    assert!(!from_str_with_opts::<bool>("false", opts.clone())?);
+    assert!(from_str_with_opts::<bool>("1", opts)?);
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_lenient_numbers() -> Result<()> {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Weapon {
+        damage: i32,
+        clip: u8,
+        spread: f32,
+        rate: f64,
+        automatic: bool,
+        silenced: bool,
+    }
+
+    let input = indoc! {r#"
+        "damage" "12abc"
+        "clip" "1.5"
+        "spread" " 0.25deg"
+        "rate" ""
+        "automatic" "2"
+        "silenced" "no"
+    "#};
+    assert!(matches!(
+        from_str::<Weapon>(input).unwrap_err().inner(),
+        Error::Serde(_)
+    ));
+
+    let opts = DeserializeOpts {
+        lenient_numbers: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        from_str_with_opts::<Weapon>(input, opts.clone())?,
+        Weapon {
+            damage: 12,
+            clip: 1,
+            spread: 0.25,
+            rate: 0.0,
+            automatic: true,
+            silenced: false,
+        }
+    );
+    assert_eq!(from_str_with_opts::<u8>("1000", opts.clone())?, u8::MAX);
+    assert_eq!(from_str_with_opts::<i32>("-7e2", opts.clone())?, -7);
+    assert_eq!(from_str_with_opts::<f64>("-7e2", opts)?, -700.0);
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_typed_keys() -> Result<()> {
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+    struct AppId(u32);
+
+    let input = indoc! {r#"
+        "440" "Team Fortress 2"
+        "730" "Counter-Strike 2"
+    "#};
+    let apps: BTreeMap<AppId, String> = from_str(input)?;
+    assert_eq!(apps[&AppId(440)], "Team Fortress 2");
+    assert_eq!(apps[&AppId(730)], "Counter-Strike 2");
+
+    let flags: HashMap<bool, char> = from_str(
+        "1 y
+0 n",
+    )?;
+    assert_eq!(flags, HashMap::from([(true, 'y'), (false, 'n')]));
+
+    let err = from_str::<BTreeMap<u32, String>>(
+        "440 tf
+abc xyz",
+    )
+    .unwrap_err();
+    assert!(matches!(err.inner(), Error::Serde(_)));
+    assert_eq!(err.path(), Some("abc"));
+    assert_eq!(err.position().map(|pos| pos.line), Some(2));
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_repeated_keys() -> Result<()> {
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct SearchPaths {
+        game: Vec<String>,
+        #[serde(rename = "Mod")]
+        mods: Vec<String>,
+        #[serde(default)]
+        platform: Vec<String>,
+        gamebin: Option<Vec<String>>,
+        addons: Option<Vec<String>>,
+    }
+
+    let paths = from_str::<SearchPaths>(indoc! {r#"
+        "Game" "tf/custom/*"
+        "Mod" "tf"
+        "Game" "tf"
+        "Gamebin" "tf/bin"
+        "Game" "hl2"
+    "#})?;
+    assert_eq!(
+        paths,
+        SearchPaths {
+            game: vec![
+                String::from("tf/custom/*"),
+                String::from("tf"),
+                String::from("hl2")
+            ],
+            mods: vec![String::from("tf")],
+            platform: vec![],
+            gamebin: Some(vec![String::from("tf/bin")]),
+            addons: None,
+        }
+    );
+
+    // A missing sequence is only allowed with `#[serde(default)]`.
+    let err = from_str::<SearchPaths>("Mod tf").unwrap_err();
+    assert!(matches!(err.inner(), Error::Serde(msg) if msg.contains("Game")));
+
+    let map = from_str::<HashMap<String, Vec<i32>>>(
+        "a 1
+b 2
+a 3",
+    )?;
+    assert_eq!(map["a"], [1, 3]);
+    assert_eq!(map["b"], [2]);
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_map() -> Result<()> {
+    let map = from_str::<HashMap<String, String>>(indoc! {r#"
+        $basetexture water/water_still
+        $surfaceprop water
+        $fogcolor "{5 5 51}"
+    "#})?;
+
+    assert_eq!(map.len(), 3);
+    assert_eq!(map["$basetexture"], "water/water_still");
+    assert_eq!(map["$surfaceprop"], "water");
+    assert_eq!(map["$fogcolor"], "{5 5 51}");
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_key_values() -> Result<()> {
+    let vmt = from_str::<KeyValues>(indoc! {r#"
+        LightmappedGeneric
+        {
+            $basetexture water/water_still
+            Proxies
+            {
+                AnimatedTexture
+                {
+                    animatedTextureVar $basetexture
+                    animatedTextureFrameRate 10
+                }
+            }
+            $surfaceprop water
+            $surfaceprop glass
+        }
+    "#})?;
+
+    let Value::Object(material) = &vmt.root["LightmappedGeneric"][0] else {
+        panic!("expected object");
+    };
+    assert_eq!(
+        material["$basetexture"],
+        vec![Value::String(String::from("water/water_still"))]
+    );
+    assert_eq!(
+        material["$surfaceprop"],
+        vec![
+            Value::String(String::from("water")),
+            Value::String(String::from("glass"))
+        ]
+    );
+
+    let Value::Object(proxies) = &material["Proxies"][0] else {
+        panic!("expected object");
+    };
+    let Value::Object(animated_texture) = &proxies["AnimatedTexture"][0] else {
+        panic!("expected object");
+    };
+    assert_eq!(animated_texture.len(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_escapes_and_comments() -> Result<()> {
+    let map = from_str::<BTreeMap<String, String>>(indoc! {r#"
+        // A comment at the start of the file
+        "quoted" "say \"KABLOOIE\"; +explode" // a trailing comment
+        unquoted coast\\shingle_01
+        "path" "materials\models\props" // unknown escapes are kept
+        "dir" "C:\Program Files\Steam\"
+        "multi" "line\nvalue\twith tab"
+        "url" "http://example.com"
+        "empty" ""
+    "#})?;
+
+    assert_eq!(map["quoted"], "say \"KABLOOIE\"; +explode");
+    assert_eq!(map["unquoted"], "coast\\shingle_01");
+    assert_eq!(map["path"], "materials\\models\\props");
+    assert_eq!(map["dir"], "C:\\Program Files\\Steam\\");
+    assert_eq!(map["multi"], "line\nvalue\twith tab");
+    assert_eq!(map["url"], "http://example.com");
+    assert_eq!(map["empty"], "");
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_conditionals_are_skipped() -> Result<()> {
+    let map = from_str::<HashMap<String, Vec<String>>>(indoc! {r##"
+        "#include" [$WINDOWS] "sourcemods/{MODNAME}.vdf"
+        "Resolution" "[1920,1080]" [$X360]
+    "##})?;
+
+    assert_eq!(map["#include"], vec!["sourcemods/{MODNAME}.vdf"]);
+    assert_eq!(map["Resolution"], vec!["[1920,1080]"]);
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_conditionals_are_evaluated() -> Result<()> {
+    let input = indoc! {r#"
+        "Platform" [$WIN32] "windows"
+        "Platform" "linux" [$LINUX]
+        "Platform" "mac" [$OSX]
+        "Launcher" [!$WIN32 && ($LINUX || $OSX)]
+        {
+            "Script" "run.sh"
+        }
+        "Launcher" [$WIN32]
+        {
+            "Script" "run.bat"
+        }
+        "Console" { "Script" "run.elf" } [$X360]
+        "Common" "always"
+    "#};
+
+    let symbols = |symbols: &[&str]| DeserializeOpts {
+        symbols: Some(symbols.iter().map(|s| s.to_string()).collect()),
+        ..Default::default()
+    };
+
+    let linux: HashMap<String, Vec<Value>> = from_str_with_opts(input, symbols(&["$linux"]))?;
+    assert_eq!(linux["Platform"], [Value::String(String::from("linux"))]);
+    assert_eq!(linux["Launcher"].len(), 1);
+    let Value::Object(launcher) = &linux["Launcher"][0] else {
+        panic!("expected object");
+    };
+    assert_eq!(launcher["Script"], [Value::String(String::from("run.sh"))]);
+    assert!(!linux.contains_key("Console"));
+    assert_eq!(linux["Common"], [Value::String(String::from("always"))]);
+
+    let none: HashMap<String, Vec<Value>> = from_str_with_opts(input, symbols(&[]))?;
+    assert!(!none.contains_key("Platform"));
+    assert!(!none.contains_key("Launcher"));
+    assert!(none.contains_key("Common"));
+
+    // Without a symbol set, every entry is kept
+    let all: HashMap<String, Vec<Value>> = from_str(input)?;
+    assert_eq!(all["Platform"].len(), 3);
+    assert_eq!(all["Launcher"].len(), 2);
+    assert_eq!(all["Console"].len(), 1);
+
+    let err = from_str_with_opts::<KeyValues>("key [$A &] value", symbols(&[])).unwrap_err();
+    assert!(matches!(err.inner(), Error::InvalidCondition { .. }));
+    assert_eq!(err.position().map(|pos| pos.column), Some(5));
+
+    let input = format!("key value [{}$A]", "!".repeat(200_000));
+    let err = from_str_with_opts::<KeyValues>(&input, symbols(&[])).unwrap_err();
+    assert!(matches!(
+        err.inner(),
+        Error::InvalidCondition {
+            reason: "nested too deeply",
+            ..
+        }
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_duplicate_keys() -> Result<()> {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config {
+        volume: i32,
+        name: String,
+        #[serde(default)]
+        plugins: Vec<String>,
+    }
+
+    let input = indoc! {r#"
+        "volume" "10"
+        "name" "default"
+        "plugins" "a"
+        "volume" "20"
+        "plugins" "b"
+        "volume" "30"
+    "#};
+    let policy = |duplicate_keys| DeserializeOpts {
+        duplicate_keys,
+        ..Default::default()
+    };
+
+    let err = from_str::<Config>(input).unwrap_err();
+    match err.inner() {
+        Error::DuplicateKey { key, first, second } => {
+            assert_eq!(key, "volume");
+            assert_eq!((first.line, second.line), (1, 4));
+        }
+        other => panic!("expected DuplicateKey, got {other:?}"),
+    }
+    assert_eq!(err.path(), Some("volume"));
+
+    let config: Config = from_str_with_opts(input, policy(DuplicateKeys::FirstWins))?;
+    assert_eq!(config.volume, 10);
+    assert_eq!(config.plugins, ["a", "b"]);
+
+    let config: Config = from_str_with_opts(input, policy(DuplicateKeys::LastWins))?;
+    assert_eq!(config.volume, 30);
+    assert_eq!(config.plugins, ["a", "b"]);
+
+    let map: HashMap<String, String> = from_str_with_opts(input, policy(DuplicateKeys::LastWins))?;
+    assert_eq!(map["volume"], "30");
+    assert_eq!(map["plugins"], "b");
+
+    // Collected values are only accepted by types that accept sequences
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    // Untagged variants only accept numeric strings as strings if numbers are not inferred.
+    let opts = DeserializeOpts {
+        infer_numbers: false,
+        ..policy(DuplicateKeys::Collect)
+    };
+    let map: HashMap<String, OneOrMany> = from_str_with_opts(input, opts)?;
+    assert_eq!(map["name"], OneOrMany::One(String::from("default")));
+    assert_eq!(
+        map["volume"],
+        OneOrMany::Many(vec![
+            String::from("10"),
+            String::from("20"),
+            String::from("30")
+        ])
+    );
+    let err = from_str_with_opts::<Config>(input, policy(DuplicateKeys::Collect)).unwrap_err();
+    assert!(matches!(err.inner(), Error::Serde(_)));
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_case_insensitive_keys() -> Result<()> {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct App {
+        #[serde(rename = "AppID")]
+        app_id: u32,
+        #[serde(rename = "Name")]
+        name: String,
+        #[serde(rename = "name")]
+        lowercase_name: Option<String>,
+        #[serde(rename = "Tags", default)]
+        tags: Vec<String>,
+    }
+
+    let input = indoc! {r#"
+        "APPID" "440"
+        "NAME" "Team Fortress 2"
+        "tags" "Shooter"
+        "TAGS" "Multiplayer"
+    "#};
+    let opts = DeserializeOpts {
+        case_insensitive: true,
+        ..Default::default()
+    };
+
+    assert!(matches!(
+        from_str::<App>(input).unwrap_err().inner(),
+        Error::Serde(_)
+    ));
+    assert_eq!(
+        from_str_with_opts::<App>(input, opts.clone())?,
+        App {
+            app_id: 440,
+            name: String::from("Team Fortress 2"),
+            lowercase_name: None,
+            tags: vec![String::from("Shooter"), String::from("Multiplayer")],
+        }
+    );
+
+    // Exact matches are preferred
+    let app: App = from_str_with_opts(
+        r#""appid" "440" "Name" "Upper" "name" "lower""#,
+        opts.clone(),
+    )?;
+    assert_eq!(app.name, "Upper");
+    assert_eq!(app.lowercase_name.as_deref(), Some("lower"));
+
+    // Keys that differ only in case are repeats of the same key
+    let input = r#""$BaseTexture" "a" "$basetexture" "b""#;
+    let err = from_str_with_opts::<HashMap<String, String>>(input, opts.clone()).unwrap_err();
+    assert!(matches!(err.inner(), Error::DuplicateKey { key, .. } if key == "$BaseTexture"));
+
+    let map: HashMap<String, String> = from_str_with_opts(
+        input,
+        DeserializeOpts {
+            duplicate_keys: DuplicateKeys::LastWins,
+            ..opts
+        },
+    )?;
+    assert_eq!(
+        map,
+        HashMap::from([(String::from("$BaseTexture"), String::from("b"))])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn object_lookups_ignore_case() -> Result<()> {
+    let mut kv: KeyValues = from_str(r#""$BaseTexture" "gravel01" "$surfaceprop" "gravel""#)?;
+
+    assert!(kv.root.contains_key_ignore_case("$basetexture"));
+    assert!(!kv.root.contains_key_ignore_case("$bumpmap"));
+    assert_eq!(
+        kv.root.get_key_value_ignore_case("$BASETEXTURE"),
+        Some((
+            &String::from("$BaseTexture"),
+            &vec![Value::String(String::from("gravel01"))]
+        ))
+    );
+
+    kv.root
+        .get_ignore_case_mut("$SurfaceProp")
+        .expect("key exists")
+        .push(Value::String(String::from("dirt")));
+    assert_eq!(
+        kv.root.get_ignore_case("$surfaceprop").map(Vec::len),
+        Some(2)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_at_path() -> Result<()> {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct FileMapping {
+        #[serde(rename = "LocalPath")]
+        local_path: String,
+    }
+
+    let input = indoc! {r#"
+        "AppBuild"
+        {
+            "Depots"
+            {
+                "1234"
+                {
+                    "FileMapping" { "LocalPath" "*" }
+                    "FileMapping" { "LocalPath" "bin/*" }
+                    "FileMapping" { "Recursive" "1" }
+                }
+            }
+        }
+        "Broken" { "never" "parsed
+    "#};
+
+    assert_eq!(
+        from_str_at::<FileMapping>("AppBuild.Depots.1234.FileMapping", input)?,
+        FileMapping {
+            local_path: String::from("*")
+        }
+    );
+    assert_eq!(
+        from_str_at::<&str>("AppBuild.Depots.1234.FileMapping[1].LocalPath", input)?,
+        "bin/*"
+    );
+    assert_eq!(
+        from_str_at::<Vec<&str>>("AppBuild.Depots.1234.FileMapping[0]", "")
+            .unwrap_err()
+            .inner()
+            .to_string(),
+        "nothing found at `AppBuild`"
+    );
+
+    for (path, missing) in [
+        ("AppBuild.Depot", "AppBuild.Depot"),
+        (
+            "AppBuild.Depots.1234.FileMapping[3]",
+            "AppBuild.Depots.1234.FileMapping[3]",
+        ),
+        (
+            "AppBuild.Depots.1234.FileMapping.LocalPath.x",
+            "AppBuild.Depots.1234.FileMapping.LocalPath.x",
+        ),
+    ] {
+        match from_str_at::<KeyValues>(path, input) {
+            Err(Error::PathNotFound(actual)) => assert_eq!(actual, missing),
+            other => panic!("expected PathNotFound for {path}, got {other:?}"),
+        }
+    }
+
+    let err = from_str_at::<FileMapping>("AppBuild.Depots.1234.FileMapping[2]", input).unwrap_err();
+    assert_eq!(err.path(), Some("AppBuild.Depots.1234.FileMapping[2]"));
+    assert_eq!(err.position().map(|pos| pos.line), Some(9));
+
+    // Paths follow the same rules as deserialization.
+    let opts = DeserializeOpts {
+        case_insensitive: true,
+        symbols: Some(Default::default()),
+        ..Default::default()
+    };
+    let input = r#"Root { Key [$X360] "console" key "pc" }"#;
+    assert_eq!(
+        from_str_at_with_opts::<&str>("root.KEY", input, opts)?,
+        "pc"
+    );
+    assert_eq!(from_str_at::<&str>("Root.Key", input)?, "console");
+
+    Ok(())
+}
+
+#[test]
+fn ignored_keys_are_skipped() -> Result<()> {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Known {
+        known: i32,
+    }
+
+    let input = r#"known 1 unknown { a b } unknown 2 unknown { c { d e } }"#;
+    assert_eq!(from_str::<Known>(input)?, Known { known: 1 });
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_syntax_errors() {
+    assert!(matches!(
+        inner_err(from_str::<KeyValues>("key { nested value")),
+        Error::Eof
+    ));
+    assert!(matches!(
+        inner_err(from_str::<KeyValues>("key value orphan")),
+        Error::Eof
+    ));
+    assert!(matches!(
+        inner_err(from_str::<KeyValues>("key \"value")),
+        Error::UnterminatedString
+    ));
+    assert!(matches!(
+        inner_err(from_str::<KeyValues>("key [$WIN32 value")),
+        Error::UnterminatedConditional
+    ));
+    assert!(matches!(
+        inner_err(from_str::<KeyValues>("key value }")),
+        Error::UnexpectedToken { .. }
+    ));
+    assert!(matches!(
+        inner_err(from_str::<KeyValues>("{ key value }")),
+        Error::UnexpectedToken { .. }
+    ));
+}
+
+#[test]
+fn deserialize_deeply_nested() -> Result<()> {
+    let nested = |depth: usize| format!("{}x y{}", "a {".repeat(depth), " }".repeat(depth));
+
+    from_str::<KeyValues>(&nested(128))?;
+    let path = format!("{}x", "a.".repeat(128));
+    assert_eq!(from_str_at::<String>(&path, &nested(128))?, "y");
+
+    for input in [nested(129), nested(20_000)] {
+        let err = from_str::<KeyValues>(&input).unwrap_err();
+        assert!(matches!(err.inner(), Error::NestedTooDeeply(128)));
+        assert_eq!(err.position().map(|pos| pos.column), Some(3 * 129));
+    }
+    assert!(matches!(
+        inner_err(from_str_at::<String>("a.a.x", &nested(20_000))),
+        Error::NestedTooDeeply(128)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_error_locations() {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    #[allow(dead_code)]
+    struct AppBuild {
+        depots: BTreeMap<u32, Depot>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    #[allow(dead_code)]
+    struct Depot {
+        file_mapping: Vec<FileMapping>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    #[allow(dead_code)]
+    struct FileMapping {
+        local_path: String,
+        recursive: Option<bool>,
+    }
+
+    let input = indoc! {r#"
+        "AppBuild"
+        {
+            "Depots"
+            {
+                "1234"
+                {
+                    "FileMapping" { "LocalPath" "*" }
+                    "FileMapping" { "LocalPath" "bin/*" }
+                    "FileMapping" { "LocalPath" "data/*" "Recursive" "yes" }
+                }
+            }
+        }
+    "#};
+
+    let err = kv_from_str::<AppBuild>(input).unwrap_err();
+    assert_eq!(
+        err.path(),
+        Some("AppBuild.Depots.1234.FileMapping[2].Recursive")
+    );
+    let position = err.position().unwrap();
+    assert_eq!((position.line, position.column), (9, 62));
+    assert_eq!(&input[position.offset..position.offset + 5], "\"yes\"");
+    assert!(matches!(err.inner(), Error::Serde(_)));
+    assert!(err
+        .to_string()
+        .ends_with("at line 9, column 62 (`AppBuild.Depots.1234.FileMapping[2].Recursive`)"));
+
+    // Missing fields are reported at the object that is missing them.
+    let err = kv_from_str::<AppBuild>("AppBuild { Depots { 1 { FileMapping {} } } }").unwrap_err();
+    assert_eq!(err.path(), Some("AppBuild.Depots.1.FileMapping[0]"));
+    assert_eq!(err.position().map(|pos| pos.offset), Some(36));
+
+    // Syntax errors are reported at the offending token.
+    let err = from_str::<KeyValues>("key\n{\n    nested \"value\n}").unwrap_err();
+    assert!(matches!(err.inner(), Error::UnterminatedString));
+    let position = err.position().unwrap();
+    assert_eq!((position.line, position.column), (3, 12));
+
+    let err = from_str::<HashMap<String, i32>>("first 1\nsecond two").unwrap_err();
+    assert_eq!(err.path(), Some("second"));
+    assert_eq!(err.position().map(|pos| pos.line), Some(2));
+
+    // Duplicate keys are reported at the second occurrence.
+    let err = from_str::<Struct>("c X\ni 1\ns text\nb 1\ni 2").unwrap_err();
+    assert_eq!(err.path(), Some("i"));
+    assert_eq!(err.position().map(|pos| pos.line), Some(5));
+}
+
+#[test]
+fn deserialize_multiple_root_keys() -> Result<()> {
+    let text = "first 1\nsecond 2";
+    assert!(matches!(
+        kv_from_str::<String>(text),
+        Err(Error::MultipleRootKeys)
+    ));
+
+    let map = from_str::<HashMap<String, i32>>(text)?;
+    assert_eq!(map["first"], 1);
+    assert_eq!(map["second"], 2);
+
+    Ok(())
+}
+
+#[test]
+fn round_trip() -> Result<()> {
+    let s = Struct {
+        c: '"',
+        i: i32::MIN,
+        s: String::from("Tab\tseparated\\ \"text\"\n"),
+        b: false,
+    };
+
+    let text = kv_to_string("Struct", &s)?;
+    assert_eq!(kv_from_str::<Struct>(&text)?, (String::from("Struct"), s));
+
+    let s = Struct {
+        c: '{',
+        i: 0,
+        s: String::from("[not a conditional]"),
+        b: true,
+    };
+
+    let text = kv_to_string_pretty(
+        "Struct",
+        &s,
+        PrettyFormatter::new(FormatOpts {
+            quote_keys: Quoting::WhenRequired,
+            quote_values: Quoting::WhenRequired,
+            ..Default::default()
+        }),
+    )?;
+    assert_eq!(kv_from_str::<Struct>(&text)?, (String::from("Struct"), s));
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_enum() -> Result<()> {
+    #[allow(clippy::enum_variant_names)]
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Enum {
+        UnitVariant,
+        NewTypeVariant(String),
+        TupleVariant(bool, String),
+        StructVariant { c: char, i: i32 },
+    }
+
+    for value in [
+        Enum::UnitVariant,
+        Enum::NewTypeVariant(String::from("inner")),
+        Enum::TupleVariant(true, String::from("data")),
+        Enum::StructVariant { c: 'y', i: 2000 },
+    ] {
+        let text = kv_to_string("Variant", &value)?;
+        assert_eq!(
+            kv_from_str::<Enum>(&text)?,
+            (String::from("Variant"), value)
+        );
+    }
+
+    // Unit variants may also be written as an object with an empty value.
+    for input in [
+        "UnitVariant",
+        r#"{ "UnitVariant" "" }"#,
+        r#"{ "UnitVariant" {} }"#,
+    ] {
+        assert_eq!(
+            kv_from_str::<Enum>(&format!("Variant {input}"))?.1,
+            Enum::UnitVariant
+        );
+    }
+    assert!(matches!(
+        inner_err(kv_from_str::<Enum>(r#"Variant { "UnitVariant" "1" }"#)),
+        Error::Serde(_)
+    ));
+
+    let err = kv_from_str::<Enum>(r#"Variant { "Missing" "x" }"#).unwrap_err();
+    assert_eq!(err.path(), Some("Variant.Missing"));
+    assert!(matches!(err.inner(), Error::Serde(msg)
+        if msg.contains("`Missing`") && msg.contains("`UnitVariant`") && msg.contains("`StructVariant`")));
+
+    let err = kv_from_str::<Enum>(r#"Variant { NewTypeVariant a StructVariant {} }"#).unwrap_err();
+    assert!(matches!(err.inner(), Error::Serde(_)));
+    assert_eq!(err.position().map(|pos| pos.offset), Some(27));
+    assert!(matches!(
+        inner_err(kv_from_str::<Enum>("Variant {}")),
+        Error::Serde(_)
+    ));
+
+    let opts = DeserializeOpts {
+        case_insensitive: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        from_str_with_opts::<HashMap<String, Vec<Enum>>>(
+            r#"v unitvariant v { structvariant { C z I 1 } }"#,
+            opts
+        )?["v"],
+        [Enum::UnitVariant, Enum::StructVariant { c: 'z', i: 1 }]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_flatten() -> Result<()> {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Material {
+        #[serde(rename = "$basetexture")]
+        base_texture: String,
+        #[serde(rename = "$alpha")]
+        alpha: Option<f32>,
+        #[serde(flatten)]
+        params: HashMap<String, Value>,
+    }
+
+    let input = indoc! {r#"
+        "VertexLitGeneric"
+        {
+            "$basetexture" "models/props/crate"
+            "$alpha" "0.5"
+            "$envmap" "env_cubemap"
+            "$phong" "1"
+        }
+    "#};
+    let (shader, material) = kv_from_str::<Material>(input)?;
+    assert_eq!(shader, "VertexLitGeneric");
+    assert_eq!(
+        material,
+        Material {
+            base_texture: String::from("models/props/crate"),
+            alpha: Some(0.5),
+            params: HashMap::from([
+                (
+                    String::from("$envmap"),
+                    Value::String(String::from("env_cubemap"))
+                ),
+                (String::from("$phong"), Value::String(String::from("1"))),
+            ]),
+        }
+    );
+
+    // Flattening into strings needs numbers to be left as strings.
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct StringParams {
+        #[serde(flatten)]
+        params: HashMap<String, String>,
+    }
+
+    let opts = DeserializeOpts {
+        infer_numbers: false,
+        ..Default::default()
+    };
+    let (_, material) = kv_from_str_with_opts::<StringParams>(input, opts.clone())?;
+    assert_eq!(material.params["$phong"], "1");
+    assert!(kv_from_str::<StringParams>(input).is_err());
+
+    // Numbers in flattened structs must be inferred from their strings.
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Common {
+        count: u32,
+        scale: f64,
+        label: String,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Entity {
+        classname: String,
+        #[serde(flatten)]
+        common: Common,
+    }
+
+    let input = "classname prop_physics count 3 scale 1.5 label crate";
+    assert!(matches!(
+        inner_err(from_str_with_opts::<Entity>(input, opts)),
+        Error::Serde(_)
+    ));
+    assert_eq!(
+        from_str::<Entity>(input)?,
+        Entity {
+            classname: String::from("prop_physics"),
+            common: Common {
+                count: 3,
+                scale: 1.5,
+                label: String::from("crate"),
+            },
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_untagged() -> Result<()> {
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(untagged)]
+    enum Setting {
+        Int(i64),
+        Float(f64),
+        Text(String),
+        Object(BTreeMap<String, String>),
+    }
+
+    let input = indoc! {r#"
+        "a" "12"
+        "b" "-1.5"
+        "c" "007"
+        "d" "text"
+        "e" { "x" "y" }
+    "#};
+
+    let settings: BTreeMap<String, Setting> = from_str(input)?;
+    assert_eq!(settings["a"], Setting::Int(12));
+    assert_eq!(settings["b"], Setting::Float(-1.5));
+    assert_eq!(settings["c"], Setting::Text(String::from("007")));
+    assert_eq!(settings["d"], Setting::Text(String::from("text")));
+    assert_eq!(
+        settings["e"],
+        Setting::Object(BTreeMap::from([(String::from("x"), String::from("y"))]))
+    );
+
+    let opts = DeserializeOpts {
+        infer_numbers: false,
+        ..Default::default()
+    };
+    let settings: BTreeMap<String, Setting> = from_str_with_opts(input, opts.clone())?;
+    assert_eq!(settings["a"], Setting::Text(String::from("12")));
+
+    // Inferred numbers are turned back into the same strings.
+    assert_eq!(
+        from_str::<KeyValues>(input)?,
+        from_str_with_opts::<KeyValues>(input, opts)?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn entries_keep_document_order() -> Result<()> {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum WorldChild {
+        Solid {
+            id: u32,
+            material: String,
+        },
+        Hidden(Entries<WorldChild>),
+        Group {
+            id: u32,
+        },
+        Mapversion(u32),
+        Marker,
+        #[serde(other, skip_serializing)]
+        Unknown,
+    }
+
+    let input = indoc! {r#"
+        "world"
+        {
+            "mapversion" "12"
+            "solid" { "id" "1" "material" "DEV/DEV_BLENDMEASURE" }
+            "hidden"
+            {
+                "solid" { "id" "3" "material" "TOOLS/TOOLSNODRAW" }
+            }
+            "solid" { "id" "2" "material" "BRICK/BRICKFLOOR001A" }
+            "group" { "id" "4" }
+            "editor" { "color" "0 255 0" }
+            "marker" ""
+        }
+    "#};
+    let (key, world) = kv_from_str::<Entries<WorldChild>>(input)?;
+    assert_eq!(key, "world");
+    assert_eq!(
+        world,
+        Entries(vec![
+            WorldChild::Mapversion(12),
+            WorldChild::Solid {
+                id: 1,
+                material: String::from("DEV/DEV_BLENDMEASURE")
+            },
+            WorldChild::Hidden(Entries(vec![WorldChild::Solid {
+                id: 3,
+                material: String::from("TOOLS/TOOLSNODRAW")
+            }])),
+            WorldChild::Solid {
+                id: 2,
+                material: String::from("BRICK/BRICKFLOOR001A")
+            },
+            WorldChild::Group { id: 4 },
+            WorldChild::Unknown,
+            WorldChild::Marker,
+        ])
+    );
+
+    let mut world = world;
+    world.retain(|child| *child != WorldChild::Unknown);
+    let text = kv_to_string("world", &world)?;
+    assert_eq!(kv_from_str(&text)?, (String::from("world"), world));
+
+    let err = kv_to_string("world", &Entries(vec![String::from("solid")])).unwrap_err();
+    assert!(matches!(err, Error::Serde(msg) if msg.contains("enum variants")));
+
+    #[derive(Serialize)]
+    enum Pair {
+        Pair(u8, u8),
+    }
+
+    let err = kv_to_string("world", &Entries(vec![Pair::Pair(1, 2)])).unwrap_err();
+    assert!(matches!(err, Error::Serde(msg) if msg.contains("tuple variants")));
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_borrowed() -> Result<()> {
+    #[derive(Debug, Deserialize)]
+    struct Borrowed<'a> {
+        name: &'a str,
+        #[serde(borrow)]
+        plain: Cow<'a, str>,
+        #[serde(borrow)]
+        escaped: Cow<'a, str>,
+        #[serde(borrow)]
+        keys: HashMap<&'a str, &'a str>,
+    }
+
+    let input = indoc! {r#"
+        "name" "Boots"
+        "plain" "no escapes here"
+        "escaped" "line\nbreak"
+        "keys"
+        {
+            "$basetexture" "coast/shingle_01"
+        }
+    "#};
+
+    let borrowed = from_str::<Borrowed>(input)?;
+    assert_eq!(borrowed.name, "Boots");
+    assert!(matches!(borrowed.plain, Cow::Borrowed("no escapes here")));
+    assert!(matches!(borrowed.escaped, Cow::Owned(ref s) if s == "line\nbreak"));
+    assert_eq!(borrowed.keys["$basetexture"], "coast/shingle_01");
+
+    let (key, name) = kv_from_str::<&str>("\"Cat\" \"Boots\"")?;
+    assert_eq!((key.as_str(), name), ("Cat", "Boots"));
+
+    match inner_err(kv_from_str::<&str>(r#""Cat" "\"Boots\"""#)) {
+        Error::BorrowedEscapedString(s) => assert_eq!(s, "\"Boots\""),
+        other => panic!("expected BorrowedEscapedString, got {other:?}"),
+    }
+
+    match inner_err(from_str::<HashMap<&str, String>>(r#""say \"hi\"" "value""#)) {
+        Error::BorrowedEscapedString(s) => assert_eq!(s, "say \"hi\""),
+        other => panic!("expected BorrowedEscapedString, got {other:?}"),
+    }
+
+    // Only strings that had to be unescaped, and only visitors that ask for a borrowed string,
+    // are reported as unborrowable.
+    #[derive(Debug)]
+    struct Lowercase;
+
+    impl<'de> Deserialize<'de> for Lowercase {
+        fn deserialize<D: serde::Deserializer<'de>>(
+            deserializer: D,
+        ) -> std::result::Result<Self, D::Error> {
+            struct LowercaseVisitor;
+
+            impl serde::de::Visitor<'_> for LowercaseVisitor {
+                type Value = Lowercase;
+
+                fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    f.write_str("a lowercase string")
+                }
+
+                fn visit_str<E: serde::de::Error>(
+                    self,
+                    v: &str,
+                ) -> std::result::Result<Lowercase, E> {
+                    if v.chars().all(|c| c.is_ascii_lowercase()) {
+                        Ok(Lowercase)
+                    } else {
+                        Err(E::invalid_type(serde::de::Unexpected::Str(v), &self))
+                    }
+                }
+            }
+
+            deserializer.deserialize_str(LowercaseVisitor)
+        }
+    }
+
+    assert!(matches!(
+        inner_err(kv_from_str::<Lowercase>("key UPPER")),
+        Error::Serde(_)
+    ));
+    assert!(matches!(
+        inner_err(kv_from_str::<Lowercase>(r#"key "UP\"PER""#)),
+        Error::Serde(_)
+    ));
+
+    // Visitors that produce a `&'static str` without borrowing from the input accept any string.
+    fn interned<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<&'static str, D::Error> {
+        struct InternVisitor;
+
+        impl serde::de::Visitor<'_> for InternVisitor {
+            type Value = &'static str;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a quote")
+            }
+
+            fn visit_str<E: serde::de::Error>(
+                self,
+                v: &str,
+            ) -> std::result::Result<&'static str, E> {
+                match v {
+                    "\"" => Ok("quote"),
+                    _ => Err(E::invalid_value(serde::de::Unexpected::Str(v), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_str(InternVisitor)
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Interned {
+        #[serde(deserialize_with = "interned")]
+        name: &'static str,
+    }
+
+    assert_eq!(from_str::<Interned>(r#"name "\"""#)?.name, "quote");
+
+    #[derive(Debug, Deserialize, PartialEq, Eq, Hash)]
+    struct UnitKey;
+
+    assert!(matches!(
+        inner_err(from_str::<HashMap<UnitKey, String>>(r#""a\"b" value"#)),
+        Error::Serde(_)
+    ));
+
+    Ok(())
+}
+
+/// Returns the severity, line and message of each diagnostic.
+fn summarize(diagnostics: &[Diagnostic]) -> Vec<(Severity, usize, &str)> {
+    diagnostics
+        .iter()
+        .map(|d| (d.severity, d.span.start.line, d.message.as_str()))
+        .collect()
+}
+
+#[test]
+fn parse_with_diagnostics_valid_input() -> Result<()> {
+    let input = indoc! {r#"
+        "Cat"
+        {
+            "Name" "Boots" [$WIN32]
+            "Toys" { "Toy" "Mouse" }
+        }
+    "#};
+
+    let (kv, diagnostics) = parse_with_diagnostics(input);
+    assert_eq!(diagnostics, []);
+    assert_eq!(kv, from_str::<KeyValues>(input)?);
+
+    Ok(())
+}
+
+#[test]
+fn parse_with_diagnostics_recovers() {
+    let (kv, diagnostics) = parse_with_diagnostics(indoc! {r#"
+        "Cats"
+        {
+            "Cat"
+            {
+                "Name" "Archie
+                "Age" "2"
+            }
+        }
+        }
+        "Dogs"
+        {
+            "Dog"
+            {
+                "Name"
+                "Age" "6"
+                "IsGoodDog"
+            }
+            "Dog" [$X360
+            {
+                "Name" "Lucy"
+    "#});
+
+    assert_eq!(
+        summarize(&diagnostics),
+        [
+            (Severity::Error, 5, "unterminated string (missing `\"`)"),
+            (Severity::Error, 9, "unmatched `}`"),
+            (Severity::Error, 14, "key `Name` has no value"),
+            (Severity::Error, 16, "key `IsGoodDog` has no value"),
+            (
+                Severity::Warning,
+                18,
+                "unterminated conditional tag was ignored"
+            ),
+            (Severity::Error, 19, "object is never closed (missing `}`)"),
+            (Severity::Error, 11, "object is never closed (missing `}`)"),
+        ]
+    );
+
+    let expected = from_str::<KeyValues>(indoc! {r#"
+        "Cats"
+        {
+            "Cat"
+            {
+                "Name" "Archie"
+                "Age" "2"
+            }
+        }
+        "Dogs"
+        {
+            "Dog"
+            {
+                "Age" "6"
+            }
+            "Dog"
+            {
+                "Name" "Lucy"
+            }
+        }
+    "#})
+    .unwrap();
+    assert_eq!(kv, expected);
+}
+
+#[test]
+fn parse_with_diagnostics_stray_tokens() {
+    let (kv, diagnostics) = parse_with_diagnostics("} { a b } [$WIN32] key value key2");
+
+    assert_eq!(
+        summarize(&diagnostics),
+        [
+            (Severity::Error, 1, "unmatched `}`"),
+            (Severity::Error, 1, "expected a key, but found `{`"),
+            (
+                Severity::Warning,
+                1,
+                "conditional tag has no key and was ignored"
+            ),
+            (Severity::Error, 1, "key `key2` has no value"),
+        ]
+    );
+    assert_eq!(kv, from_str::<KeyValues>("key value").unwrap());
+}
+
+#[test]
+fn parse_with_diagnostics_deeply_nested() {
+    let input = format!("{}x y{} after 1", "a {".repeat(200), " }".repeat(200));
+    let (kv, diagnostics) = parse_with_diagnostics(&input);
+
+    assert_eq!(
+        summarize(&diagnostics),
+        [(
+            Severity::Error,
+            1,
+            "objects are nested more than 128 levels deep (skipped)"
+        )]
+    );
+    assert_eq!(diagnostics[0].span.start.column, 3 * 129);
+
+    // The object that is too deep is dropped, and parsing continues after it.
+    let expected = format!("{}{} after 1", "a {".repeat(128), " }".repeat(128));
+    assert_eq!(kv, from_str::<KeyValues>(&expected).unwrap());
+}
+
+#[test]
+fn read_events_with_spans() -> Result<()> {
+    let input = indoc! {r#"
+        "AppBuild"
+        {
+            "Desc" "Escaped \"quotes\"" // build description
+            "Depots" { "1234" "depot.vdf" }
+        }
+    "#};
+
+    let mut depth = 0;
+    let mut max_depth = 0;
+    let mut spans = Vec::new();
+    for event in Reader::new(input) {
+        let (event, span) = event?;
+        match event {
+            Event::BeginObject => depth += 1,
+            Event::EndObject => depth -= 1,
+            Event::Value(ref value) if value.contains('"') => {
+                assert_eq!(value, "Escaped \"quotes\"");
+            }
+            _ => {}
+        }
+        max_depth = max_depth.max(depth);
+        spans.push(&input[span.range()]);
+    }
+
+    assert_eq!((depth, max_depth), (0, 2));
+    assert_eq!(
+        spans,
+        [
+            "\"AppBuild\"",
+            "{",
+            "\"Desc\"",
+            "\"Escaped \\\"quotes\\\"\"",
+            "// build description",
+            "\"Depots\"",
+            "{",
+            "\"1234\"",
+            "\"depot.vdf\"",
+            "}",
+            "}",
+        ]
+    );
+    Ok(())
+}
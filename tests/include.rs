@@ -0,0 +1,280 @@
+use indoc::indoc;
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::{fs, io};
+use vdflex::de::DeserializeOpts;
+use vdflex::include::{FileResolver, LoadOpts, Loader, MemoryResolver, Resolver};
+use vdflex::{from_str, Error, KeyValues, Result};
+
+fn kv(s: &str) -> KeyValues {
+    from_str(s).unwrap()
+}
+
+#[test]
+fn include_appends_entries() -> Result<()> {
+    let resolver = MemoryResolver::from_iter([
+        (
+            "gameinfo.txt",
+            indoc! {r##"
+                #include "mods/a.vdf"
+                "Game" "Half-Life 2"
+                "#include" "mods/b.vdf"
+            "##},
+        ),
+        ("mods/a.vdf", r#""Mod" "A""#),
+        ("mods/b.vdf", r#""Mod" "B" #include "../common.vdf""#),
+        ("common.vdf", r#""Game" "Common""#),
+    ]);
+
+    let loaded = Loader::new(resolver).load("gameinfo.txt")?;
+    assert_eq!(
+        loaded,
+        kv(indoc! {r#"
+            "Game" "Half-Life 2"
+            "Mod" "A"
+            "Mod" "B"
+            "Game" "Common"
+        "#})
+    );
+
+    Ok(())
+}
+
+#[test]
+fn base_fills_in_missing_keys() -> Result<()> {
+    let resolver = MemoryResolver::from_iter([
+        (
+            "resource/ui/hud.res",
+            indoc! {r#"
+                #base "hud_base.res"
+                #base "hud_fallback.res"
+                "Resource/UI/Hud.res"
+                {
+                    "HudHealth"
+                    {
+                        "xpos" "10"
+                    }
+                }
+            "#},
+        ),
+        (
+            "resource/ui/hud_base.res",
+            indoc! {r#"
+                "Resource/UI/Hud.res"
+                {
+                    "HudHealth"
+                    {
+                        "xpos" "0"
+                        "ypos" "0"
+                    }
+                    "HudAmmo"
+                    {
+                        "visible" "1"
+                    }
+                }
+            "#},
+        ),
+        (
+            "resource/ui/hud_fallback.res",
+            indoc! {r#"
+                "Resource/UI/Hud.res"
+                {
+                    "HudHealth"
+                    {
+                        "ypos" "480"
+                        "wide" "100"
+                    }
+                }
+            "#},
+        ),
+    ]);
+
+    let loaded = Loader::new(resolver).load("resource/ui/hud.res")?;
+    assert_eq!(
+        loaded,
+        kv(indoc! {r#"
+            "Resource/UI/Hud.res"
+            {
+                "HudHealth"
+                {
+                    "xpos" "10"
+                    "ypos" "0"
+                    "wide" "100"
+                }
+                "HudAmmo"
+                {
+                    "visible" "1"
+                }
+            }
+        "#})
+    );
+
+    Ok(())
+}
+
+#[test]
+fn directives_ignore_case() -> Result<()> {
+    let resolver = MemoryResolver::from_iter([
+        (
+            "panel.res",
+            indoc! {r##"
+                #Base "base.res"
+                "#INCLUDE" "extra.res"
+                "Panel" { "wide" "100" }
+            "##},
+        ),
+        ("base.res", r#""Panel" { "tall" "50" }"#),
+        ("extra.res", r#""Extra" "1""#),
+    ]);
+
+    let loaded = Loader::new(resolver).load("panel.res")?;
+    assert_eq!(
+        loaded,
+        kv(r#""Panel" { "wide" "100" "tall" "50" } "Extra" "1""#)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn directives_respect_conditionals() -> Result<()> {
+    let resolver = MemoryResolver::from_iter([
+        (
+            "main.vdf",
+            indoc! {r#"
+                #include "windows.vdf" [$WIN32]
+                #include "linux.vdf" [$LINUX]
+            "#},
+        ),
+        ("windows.vdf", r#""Binary" "game.exe""#),
+        ("linux.vdf", r#""Binary" "game.sh""#),
+    ]);
+
+    let opts = LoadOpts {
+        deserialize: DeserializeOpts {
+            symbols: Some(HashSet::from([String::from("$LINUX")])),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let loaded = Loader::with_opts(resolver, opts).load("main.vdf")?;
+    assert_eq!(loaded, kv(r#""Binary" "game.sh""#));
+
+    Ok(())
+}
+
+#[test]
+fn include_errors() {
+    let resolver = MemoryResolver::from_iter([
+        ("a.vdf", "#include b.vdf"),
+        ("b.vdf", "#base a.vdf"),
+        ("escape.vdf", "#include ../../etc/passwd"),
+        ("missing.vdf", "#include nowhere.vdf"),
+        ("syntax.vdf", "#include broken.vdf"),
+        ("broken.vdf", "key\n{\n    value"),
+        ("deep.vdf", "#include deep2.vdf"),
+        ("deep2.vdf", "#include deep3.vdf"),
+        ("deep3.vdf", "key value"),
+    ]);
+    let loader = Loader::new(&resolver);
+
+    let err = loader.load("a.vdf").unwrap_err();
+    assert!(matches!(err.inner(), Error::IncludeCycle(path) if path == "a.vdf"));
+    assert_eq!(err.file(), Some("b.vdf"));
+
+    let err = loader.load("escape.vdf").unwrap_err();
+    assert!(matches!(err.inner(), Error::PathOutsideRoot(_)));
+    assert_eq!(err.file(), Some("escape.vdf"));
+
+    let err = loader.load("missing.vdf").unwrap_err();
+    assert!(matches!(err.inner(), Error::Io(_)));
+    assert_eq!(err.file(), Some("nowhere.vdf"));
+
+    let err = loader.load("syntax.vdf").unwrap_err();
+    assert!(matches!(err.inner(), Error::Eof));
+    assert_eq!(err.file(), Some("broken.vdf"));
+    assert_eq!(err.position().map(|pos| pos.line), Some(3));
+
+    let shallow = Loader::with_opts(
+        &resolver,
+        LoadOpts {
+            max_depth: 2,
+            ..Default::default()
+        },
+    );
+    assert!(matches!(
+        shallow.load("deep.vdf").unwrap_err().inner(),
+        Error::IncludeTooDeep(2)
+    ));
+    assert!(shallow.load("deep2.vdf").is_ok());
+}
+
+#[test]
+fn repeated_includes_are_limited() {
+    struct CountingResolver {
+        inner: MemoryResolver,
+        reads: Cell<usize>,
+    }
+
+    impl Resolver for CountingResolver {
+        fn read(&self, path: &str) -> io::Result<String> {
+            self.reads.set(self.reads.get() + 1);
+            self.inner.read(path)
+        }
+    }
+
+    // Each level includes the next one 8 times, which would be 8^15 loads without a limit.
+    let mut inner = MemoryResolver::new();
+    for level in 0..15 {
+        let directive = format!("#include \"{}.vdf\"\n", level + 1);
+        inner.insert(format!("{level}.vdf"), directive.repeat(8));
+    }
+    inner.insert("15.vdf", r#""Leaf" "1""#);
+
+    let resolver = CountingResolver {
+        inner,
+        reads: Cell::new(0),
+    };
+    let err = Loader::new(&resolver).load("0.vdf").unwrap_err();
+    assert!(matches!(err.inner(), Error::TooManyFiles(1024)));
+    assert_eq!(resolver.reads.get(), 1024);
+
+    let limited = Loader::with_opts(
+        &resolver,
+        LoadOpts {
+            max_files: 3,
+            ..Default::default()
+        },
+    );
+    assert!(matches!(
+        limited.load("14.vdf").unwrap_err().inner(),
+        Error::TooManyFiles(3)
+    ));
+    assert!(limited.load("15.vdf").is_ok());
+}
+
+#[test]
+fn file_resolver() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("vdflex-include-{}", std::process::id()));
+    fs::create_dir_all(dir.join("root/scripts"))?;
+    fs::write(dir.join("secret.vdf"), r#""Password" "hunter2""#)?;
+    fs::write(
+        dir.join("root/scripts/main.vdf"),
+        r#"#include "shared.vdf" "Name" "Main""#,
+    )?;
+    fs::write(dir.join("root/scripts/shared.vdf"), r#""Shared" "1""#)?;
+    fs::write(dir.join("root/escape.vdf"), r#"#include "../secret.vdf""#)?;
+
+    let loader = Loader::new(FileResolver::new(dir.join("root")));
+    let loaded = loader.load("scripts/main.vdf");
+    let escaped = loader.load("escape.vdf");
+    fs::remove_dir_all(&dir)?;
+
+    assert_eq!(loaded?, kv(r#""Name" "Main" "Shared" "1""#));
+    assert!(matches!(
+        escaped.unwrap_err().inner(),
+        Error::PathOutsideRoot(_)
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,388 @@
+use indoc::indoc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use vdflex::kv3::{
+    self, Deserializer, Flag, Flagged, FormatOpts, Header, Object, PrettyFormatter, Tag, Value,
+};
+use vdflex::ser::BraceStyle;
+use vdflex::{Error, Result};
+
+const HEADER: &str = "<!-- kv3 encoding:text:version{e21c7f3c-8a33-41c5-9977-a76d3a32aa0d} \
+    format:generic:version{7412167c-06e9-4698-aff2-e63eb59037e7} -->";
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Particle {
+    name: String,
+    enabled: bool,
+    count: u32,
+    radius: f64,
+    parent: Option<String>,
+    material: Flagged<String>,
+    sounds: Vec<String>,
+    children: Vec<Child>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Child {
+    #[serde(rename = "m_ChildRef")]
+    child_ref: String,
+    delay: f64,
+}
+
+fn particle() -> Particle {
+    Particle {
+        name: String::from("Explosion"),
+        enabled: true,
+        count: 3,
+        radius: 64.0,
+        parent: None,
+        material: Flagged::new(Flag::Resource, String::from("materials/fire.vmat")),
+        sounds: vec![String::from("Weapon.Explode")],
+        children: vec![
+            Child {
+                child_ref: String::from("smoke.vpcf"),
+                delay: 0.5,
+            },
+            Child {
+                child_ref: String::from("sparks.vpcf"),
+                delay: 0.0,
+            },
+        ],
+    }
+}
+
+#[test]
+fn serialize_document() -> Result<()> {
+    let expected = format!(
+        "{HEADER}\n{}",
+        indoc! {r#"
+            {
+            	name = "Explosion"
+            	enabled = true
+            	count = 3
+            	radius = 64.0
+            	parent = null
+            	material = resource:"materials/fire.vmat"
+            	sounds =
+            	[
+            		"Weapon.Explode",
+            	]
+            	children =
+            	[
+            		{
+            			m_ChildRef = "smoke.vpcf"
+            			delay = 0.5
+            		},
+            		{
+            			m_ChildRef = "sparks.vpcf"
+            			delay = 0.0
+            		},
+            	]
+            }
+        "#}
+    );
+    assert_eq!(kv3::to_string(&particle())?, expected);
+    assert_eq!(kv3::from_str::<Particle>(&expected)?, particle());
+    Ok(())
+}
+
+#[test]
+fn serialize_pretty() -> Result<()> {
+    let opts = FormatOpts {
+        indent: String::from("  "),
+        brace_style: BraceStyle::KAndR,
+        multiline_strings: false,
+    };
+
+    let mut object = Object::new();
+    object.insert(String::from("empty"), Value::Array(Vec::new()));
+    object.insert(
+        String::from("key with spaces"),
+        Value::Object(Object::new()),
+    );
+    object.insert(String::from("text"), Value::String(String::from("a\nb")));
+    let text = kv3::to_string_pretty(&Value::Object(object), PrettyFormatter::new(opts))?;
+
+    let body = text.split_once('\n').unwrap().1;
+    assert_eq!(
+        body,
+        indoc! {r#"
+            {
+              empty = []
+              "key with spaces" = {}
+              text = "a\nb"
+            }
+        "#}
+    );
+    Ok(())
+}
+
+#[test]
+fn deserialize_syntax() -> Result<()> {
+    let input = indoc! {r#"
+        <!-- kv3 encoding:text:version{e21c7f3c-8a33-41c5-9977-a76d3a32aa0d} format:vpcf26:version{26288658-411e-4f14-b698-2e1e5d00dec6} -->
+        {
+            // A line comment
+            # Another line comment
+            int = -12 /* a block
+                         comment */
+            big = 18446744073709551615
+            double = 1.5e3
+            "quoted key" = "say \"hi\""
+            multiline = """
+        first line
+            second line
+        """
+            flagged = soundevent:"Explosion.Large"
+            blob = #[ 00 01
+                      fe FF ]
+            nested = [ [1, 2], [], { a = null }, ]
+        }
+    "#};
+
+    let mut deserializer = Deserializer::from_str(input);
+    let header = deserializer.header()?.cloned().unwrap();
+    assert_eq!(
+        header.format,
+        Tag::new("vpcf26", "26288658-411e-4f14-b698-2e1e5d00dec6")
+    );
+    assert_eq!(header.encoding, Header::default().encoding);
+
+    let Value::Object(root) = Value::deserialize(&mut deserializer)? else {
+        panic!("expected object");
+    };
+    deserializer.end()?;
+
+    assert_eq!(root["int"], Value::Int(-12));
+    assert_eq!(root["big"], Value::UInt(u64::MAX));
+    assert_eq!(root["double"], Value::Double(1500.0));
+    assert_eq!(
+        root["quoted key"],
+        Value::String(String::from("say \"hi\""))
+    );
+    assert_eq!(
+        root["multiline"],
+        Value::String(String::from("first line\n    second line"))
+    );
+    assert_eq!(
+        root["flagged"],
+        Value::Flagged(
+            Flag::SoundEvent,
+            Box::new(Value::String(String::from("Explosion.Large")))
+        )
+    );
+    assert_eq!(root["blob"], Value::Binary(vec![0x00, 0x01, 0xFE, 0xFF]));
+    assert_eq!(
+        root["nested"],
+        Value::Array(vec![
+            Value::Array(vec![Value::Int(1), Value::Int(2)]),
+            Value::Array(Vec::new()),
+            Value::Object(Object::from_iter([(String::from("a"), Value::Null)])),
+        ])
+    );
+
+    // Documents without a header are also accepted.
+    assert_eq!(kv3::from_str::<Vec<bool>>("[true, false]")?, [true, false]);
+    Ok(())
+}
+
+#[test]
+fn value_round_trip() -> Result<()> {
+    let input = format!(
+        "{HEADER}\n{}",
+        indoc! {r#"
+            {
+            	a = null
+            	b = false
+            	c = -1
+            	d = 18446744073709551615
+            	e = 0.25
+            	f = """
+            line 1
+            line 2
+            """
+            	g = #[ 00 7F ]
+            	h =
+            	[
+            		1,
+            		"two",
+            	]
+            	i = resource_name:"maps/test.vmap"
+            	j =
+            	{}
+            }
+        "#}
+    );
+
+    let value: Value = kv3::from_str(&input)?;
+    assert_eq!(kv3::to_string(&value)?, input);
+    Ok(())
+}
+
+#[test]
+fn flags_are_optional() -> Result<()> {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Material {
+        shader: String,
+        texture: Option<String>,
+    }
+
+    let material: Material =
+        kv3::from_str(r#"{ shader = "csgo_simple.vfx" texture = resource:"dev.vtex" }"#)?;
+    assert_eq!(material.shader, "csgo_simple.vfx");
+    assert_eq!(material.texture.as_deref(), Some("dev.vtex"));
+    assert_eq!(kv3::from_str::<i32>("resource:5")?, 5);
+    assert_eq!(
+        kv3::from_str::<Vec<bool>>("panorama:[true, subclass:false]")?,
+        [true, false]
+    );
+
+    // Flagged values can also be read as enums whose variants are named after the flags.
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Reference {
+        Resource(String),
+        #[serde(rename = "soundevent")]
+        SoundEvent(String),
+    }
+
+    let references: Vec<Reference> =
+        kv3::from_str(r#"[resource:"a.vmdl", soundevent:"Door.Open"]"#)?;
+    assert_eq!(
+        references,
+        [
+            Reference::Resource(String::from("a.vmdl")),
+            Reference::SoundEvent(String::from("Door.Open"))
+        ]
+    );
+
+    assert!(kv3::from_str::<Flagged<String>>(r#""plain""#).is_err());
+    Ok(())
+}
+
+#[test]
+fn enums() -> Result<()> {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Line(i32, i32),
+        Rect { w: i32, h: i32 },
+    }
+
+    let shapes = vec![
+        Shape::Point,
+        Shape::Circle(1.5),
+        Shape::Line(1, 2),
+        Shape::Rect { w: 3, h: 4 },
+    ];
+    let text = kv3::to_string(&shapes)?;
+    assert!(text.contains("\"Point\","));
+    assert!(text.contains("Circle = 1.5"));
+    assert_eq!(kv3::from_str::<Vec<Shape>>(&text)?, shapes);
+
+    let keys = BTreeMap::from([(1u32, Shape::Point), (20, Shape::Circle(0.0))]);
+    let text = kv3::to_string(&keys)?;
+    assert!(text.contains("\"1\" = \"Point\""));
+    assert_eq!(kv3::from_str::<BTreeMap<u32, Shape>>(&text)?, keys);
+    Ok(())
+}
+
+#[test]
+fn deeply_nested() -> Result<()> {
+    let arrays = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+    let objects = |depth: usize| {
+        let outer = depth - 1;
+        format!("{}{{}}{}", "{ a = ".repeat(outer), "}".repeat(outer))
+    };
+
+    kv3::from_str::<Value>(&arrays(128))?;
+    kv3::from_str::<Value>(&objects(128))?;
+
+    for (input, column) in [
+        (arrays(129), 129),
+        (arrays(200_000), 129),
+        (objects(129), 6 * 128 + 1),
+        (objects(200_000), 6 * 128 + 1),
+    ] {
+        let err = kv3::from_str::<Value>(&input).unwrap_err();
+        assert!(matches!(err.inner(), Error::NestedTooDeeply(128)));
+        assert_eq!(
+            err.position().map(|pos| (pos.line, pos.column)),
+            Some((1, column))
+        );
+    }
+
+    // Enums written as objects count towards the limit too.
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    enum Tree {
+        Node(Box<Tree>),
+        Leaf,
+    }
+    let input = format!(
+        "{}\"Leaf\"{}",
+        "{ Node = ".repeat(200_000),
+        "}".repeat(200_000)
+    );
+    assert!(matches!(
+        kv3::from_str::<Tree>(&input).unwrap_err().inner(),
+        Error::NestedTooDeeply(128)
+    ));
+    Ok(())
+}
+
+#[test]
+fn errors() {
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Settings {
+        volume: f64,
+        names: Vec<String>,
+    }
+
+    let located = |input: &str| match kv3::from_str::<Settings>(input) {
+        Err(err) => (
+            err.position().map(|pos| (pos.line, pos.column)),
+            err.path().map(String::from),
+            err.to_string(),
+        ),
+        Ok(settings) => panic!("expected an error, got {settings:?}"),
+    };
+
+    let (position, path, _) = located("{\n  volume = 1.0\n  names = [\"a\", 2]\n}");
+    assert_eq!(position, Some((3, 17)));
+    assert_eq!(path.as_deref(), Some("names[1]"));
+
+    let (position, _, message) = located("{ volume = 1.0 names = [\"a\" \"b\"] }");
+    assert_eq!(position, Some((1, 29)));
+    assert!(message.contains("expected `,` or `]`"), "{message}");
+
+    let (position, _, message) = located("{ volume: 1.0 }");
+    assert_eq!(position, Some((1, 9)));
+    assert!(message.contains("expected `=`"), "{message}");
+
+    let (_, _, message) = located("{ volume = 1.0 names = [] } extra");
+    assert!(
+        message.contains("expected the end of the input"),
+        "{message}"
+    );
+
+    assert!(matches!(
+        kv3::from_str::<Value>("<!-- kv2 -->\n{}")
+            .unwrap_err()
+            .inner(),
+        Error::UnexpectedToken { .. }
+    ));
+    assert!(matches!(
+        kv3::from_str::<Value>(r#"{ a = "unterminated }"#)
+            .unwrap_err()
+            .inner(),
+        Error::UnterminatedString
+    ));
+    assert!(matches!(
+        kv3::from_str::<Value>("{ a = [1, 2").unwrap_err().inner(),
+        Error::Eof
+    ));
+    assert!(kv3::to_string(&f64::NAN).is_err());
+}
@@ -0,0 +1,363 @@
+use serde::{Deserialize, Serialize};
+use vdflex::steam::appinfo::{self, AppHeader, AppInfo};
+use vdflex::steam::packageinfo::{self, PackageHeader, PackageInfo};
+use vdflex::steam::shortcuts::{self, Shortcut};
+use vdflex::{Error, KeyValues, Result, Value};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Data {
+    appinfo: Info,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Info {
+    appid: u32,
+    common: Common,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Common {
+    name: String,
+}
+
+fn data(appid: u32, name: &str) -> Data {
+    Data {
+        appinfo: Info {
+            appid,
+            common: Common {
+                name: String::from(name),
+            },
+        },
+    }
+}
+
+fn hex(hash: &[u8]) -> String {
+    hash.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// The binary KeyValues of `data(10, "Counter-Strike")`.
+const CS_DATA: &[u8] =
+    b"\x00appinfo\0\x02appid\0\x0a\0\0\0\x00common\0\x01name\0Counter-Strike\0\x08\x08\x08";
+
+/// The SHA-1 hashes of `data(10, "Counter-Strike")` as binary KeyValues and as text.
+const CS_BINARY_SHA1: &str = "197bc6be0be351e986848f8ec63db67b86ba7eb9";
+const CS_TEXT_SHA1: &str = "c5cfecc03ae5ff79e83062f87ff4da4128f6e77e";
+
+#[test]
+fn read_appinfo() -> Result<()> {
+    let mut file = Vec::new();
+    file.extend(0x0756_4428u32.to_le_bytes());
+    file.extend(1u32.to_le_bytes());
+    file.extend(10u32.to_le_bytes());
+    file.extend((60 + CS_DATA.len() as u32).to_le_bytes());
+    file.extend(2u32.to_le_bytes());
+    file.extend(1_700_000_000u32.to_le_bytes());
+    file.extend(0x1234u64.to_le_bytes());
+    file.extend([0xAA; 20]);
+    file.extend(99u32.to_le_bytes());
+    file.extend([0xBB; 20]);
+    file.extend(CS_DATA);
+    file.extend(0u32.to_le_bytes());
+
+    let appinfo = AppInfo::from_slice(&file)?;
+    assert_eq!(appinfo.version(), appinfo::Version::V28);
+    assert_eq!(appinfo.universe(), 1);
+
+    let apps = appinfo.apps().collect::<Result<Vec<_>>>()?;
+    assert_eq!(apps.len(), 1);
+    assert_eq!(
+        apps[0].header,
+        AppHeader {
+            appid: 10,
+            info_state: 2,
+            last_updated: 1_700_000_000,
+            pics_token: 0x1234,
+            text_sha1: [0xAA; 20],
+            change_number: 99,
+            binary_sha1: Some([0xBB; 20]),
+        }
+    );
+    assert_eq!(apps[0].data(), CS_DATA);
+    assert_eq!(apps[0].deserialize::<Data>()?, data(10, "Counter-Strike"));
+    Ok(())
+}
+
+#[test]
+fn write_appinfo() -> Result<()> {
+    let header = AppHeader {
+        appid: 10,
+        change_number: 99,
+        text_sha1: [0xFF; 20],
+        ..Default::default()
+    };
+
+    for version in [
+        appinfo::Version::V27,
+        appinfo::Version::V28,
+        appinfo::Version::V29,
+    ] {
+        let mut writer = appinfo::Writer::new(Vec::new(), version, 1);
+        writer.write_app(&header, &data(10, "Counter-Strike"))?;
+        writer.write_app(
+            &AppHeader {
+                appid: 20,
+                ..header.clone()
+            },
+            &data(20, "Team Fortress Classic"),
+        )?;
+        let file = writer.finish()?;
+
+        let appinfo = AppInfo::from_slice(&file)?;
+        assert_eq!(appinfo.version(), version);
+        let apps = appinfo.apps().collect::<Result<Vec<_>>>()?;
+        assert_eq!(apps.len(), 2);
+
+        // The checksums are computed when the file is written.
+        let cs = &apps[0].header;
+        assert_eq!(hex(&cs.text_sha1), CS_TEXT_SHA1);
+        match version {
+            appinfo::Version::V27 => assert_eq!(cs.binary_sha1, None),
+            _ => assert_eq!(
+                cs.binary_sha1.map(|h| hex(&h)).as_deref(),
+                Some(CS_BINARY_SHA1)
+            ),
+        }
+        assert_eq!(cs.change_number, 99);
+
+        assert_eq!(apps[0].deserialize::<Data>()?, data(10, "Counter-Strike"));
+        assert_eq!(
+            apps[1].deserialize::<Data>()?,
+            data(20, "Team Fortress Classic")
+        );
+        assert_eq!(apps[1].header.appid, 20);
+
+        let kv: KeyValues = apps[1].deserialize()?;
+        let Value::Object(info) = &kv.root["appinfo"][0] else {
+            panic!("expected object");
+        };
+        assert_eq!(info["appid"], [Value::Int32(20)]);
+
+        // Version 29 stores each key once, in the key table.
+        let count = file.windows(6).filter(|w| w == b"appid\0").count();
+        match version {
+            appinfo::Version::V29 => {
+                assert_eq!(count, 1);
+                assert_ne!(apps[0].data(), CS_DATA);
+            }
+            _ => {
+                assert_eq!(count, 2);
+                assert_eq!(apps[0].data(), CS_DATA);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn appinfo_errors() -> Result<()> {
+    let invalid = |result: Result<_>| match result {
+        Err(Error::InvalidBinary { offset, .. }) => offset,
+        other => panic!("expected invalid binary error, got {other:?}"),
+    };
+
+    assert_eq!(
+        invalid(AppInfo::from_slice(b"VDF\x07\x01\0\0\0").map(|_| ())),
+        0
+    );
+    assert_eq!(
+        invalid(AppInfo::from_slice(b"\x29\x44\x56\x07\x01\0\0\0\xff\0\0\0\0\0\0\0").map(|_| ())),
+        8
+    );
+
+    let header = AppHeader {
+        appid: 10,
+        ..Default::default()
+    };
+    let mut writer = appinfo::Writer::new(Vec::new(), appinfo::Version::V29, 1);
+    assert!(matches!(
+        writer.write_app(&AppHeader::default(), &data(0, "")),
+        Err(Error::Io(_))
+    ));
+    writer.write_app(&header, &data(10, "Counter-Strike"))?;
+    let mut file = writer.finish()?;
+
+    // Point the first key at a missing entry of the key table.
+    let key = 16 + 8 + 60 + 1;
+    file[key] = 0x7F;
+    let appinfo = AppInfo::from_slice(&file)?;
+    let app = appinfo.apps().next().unwrap()?;
+    assert_eq!(invalid(app.deserialize::<Data>().map(|_| ())), key);
+
+    // Truncate the entry.
+    let mut writer = appinfo::Writer::new(Vec::new(), appinfo::Version::V28, 1);
+    writer.write_app(&header, &data(10, "Counter-Strike"))?;
+    let file = writer.finish()?;
+    let appinfo = AppInfo::from_slice(&file[..40])?;
+    let mut apps = appinfo.apps();
+    assert_eq!(invalid(apps.next().unwrap().map(|_| ())), 16);
+    assert!(apps.next().is_none());
+    Ok(())
+}
+
+#[test]
+fn packageinfo_round_trip() -> Result<()> {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Package {
+        packageid: u32,
+        billingtype: u32,
+        appids: Vec<u32>,
+    }
+
+    let package = |packageid| Package {
+        packageid,
+        billingtype: 10,
+        appids: vec![10, 80],
+    };
+
+    for version in [packageinfo::Version::V27, packageinfo::Version::V28] {
+        let mut writer = packageinfo::Writer::new(Vec::new(), version, 1);
+        for packageid in [0, 7] {
+            let header = PackageHeader {
+                packageid,
+                change_number: 5,
+                pics_token: Some(packageid.into()),
+                ..Default::default()
+            };
+            writer.write_package(&header, &package(packageid))?;
+        }
+        let file = writer.finish()?;
+        assert_eq!(file[file.len() - 4..], [0xFF; 4]);
+
+        let packages = PackageInfo::from_slice(&file)?;
+        assert_eq!(packages.version(), version);
+        assert_eq!(packages.universe(), 1);
+        let read = packages.packages().collect::<Result<Vec<_>>>()?;
+        assert_eq!(read.len(), 2);
+        for (entry, packageid) in read.iter().zip([0, 7]) {
+            assert_eq!(entry.header.packageid, packageid);
+            assert_eq!(entry.header.change_number, 5);
+            let pics_token = match version {
+                packageinfo::Version::V27 => None,
+                packageinfo::Version::V28 => Some(packageid.into()),
+            };
+            assert_eq!(entry.header.pics_token, pics_token);
+            assert_eq!(entry.deserialize::<Package>()?, package(packageid));
+        }
+    }
+
+    // A truncated package is an error, even though entries have no size.
+    let mut writer = packageinfo::Writer::new(Vec::new(), packageinfo::Version::V27, 1);
+    writer.write_package(&PackageHeader::default(), &package(0))?;
+    let file = writer.finish()?;
+    let mut packages = PackageInfo::from_slice(&file[..file.len() - 6])?.packages();
+    assert!(matches!(
+        packages.next(),
+        Some(Err(Error::InvalidBinary { .. }))
+    ));
+    assert!(packages.next().is_none());
+    Ok(())
+}
+
+/// A `shortcuts.vdf` file like the ones Steam writes, with one hidden shortcut.
+const SHORTCUTS: &[u8] = b"\x00shortcuts\0\
+    \x000\0\
+        \x02appid\0\xe1\x6d\xb4\xbb\
+        \x01AppName\0My Game\0\
+        \x01Exe\0\"C:\\Games\\Game\\game.exe\"\0\
+        \x01StartDir\0\"C:\\Games\\Game\\\"\0\
+        \x01icon\0\0\
+        \x01ShortcutPath\0\0\
+        \x01LaunchOptions\0-windowed\0\
+        \x02IsHidden\0\x01\0\0\0\
+        \x02AllowDesktopConfig\0\x01\0\0\0\
+        \x02AllowOverlay\0\x00\0\0\0\
+        \x02OpenVR\0\x00\0\0\0\
+        \x02LastPlayTime\0\x00\xf1\x53\x65\
+        \x01FlatpakAppID\0\0\
+        \x00tags\0\
+            \x010\0Favorites\0\
+            \x011\0Racing\0\
+        \x08\
+    \x08\
+\x08\
+\x08";
+
+#[test]
+fn read_shortcuts() -> Result<()> {
+    let list = shortcuts::from_slice(SHORTCUTS)?;
+    assert_eq!(list.len(), 1);
+
+    let shortcut = &list[0];
+    // The appid is stored as a negative int32.
+    assert_eq!(shortcut.appid, 0xBBB4_6DE1);
+    assert_eq!(
+        shortcut.appid,
+        shortcuts::appid(&shortcut.exe, &shortcut.app_name)
+    );
+    assert_eq!(shortcut.app_name, "My Game");
+    assert_eq!(shortcut.exe, r#""C:\Games\Game\game.exe""#);
+    assert_eq!(shortcut.start_dir, r#""C:\Games\Game\""#);
+    assert_eq!(shortcut.launch_options, "-windowed");
+    assert!(shortcut.is_hidden);
+    assert!(shortcut.allow_desktop_config);
+    assert!(!shortcut.allow_overlay);
+    assert_eq!(shortcut.last_play_time, 1_700_000_000);
+    assert_eq!(shortcut.tags, ["Favorites", "Racing"]);
+    assert_eq!(shortcut.extra.len(), 1);
+    assert_eq!(shortcut.extra["FlatpakAppID"], Value::String(String::new()));
+
+    assert_eq!(shortcuts::from_reader(SHORTCUTS)?, list);
+    Ok(())
+}
+
+#[test]
+fn write_shortcuts() -> Result<()> {
+    let list = shortcuts::from_slice(SHORTCUTS)?;
+    assert_eq!(shortcuts::to_vec(&list)?, SHORTCUTS);
+
+    let mut added = Shortcut::new("Other Game", "/usr/bin/other-game");
+    added.tags.push(String::from("Linux"));
+    let list = vec![list[0].clone(), added];
+    let bytes = shortcuts::to_vec(&list)?;
+    assert_eq!(shortcuts::from_slice(&bytes)?, list);
+
+    // Steam writes flags and IDs as int32 values.
+    let kv: KeyValues = vdflex::binary::from_slice(&bytes)?;
+    let Value::Object(entries) = &kv.root["shortcuts"][0] else {
+        panic!("expected object");
+    };
+    let Value::Object(other) = &entries["1"][0] else {
+        panic!("expected object");
+    };
+    assert_eq!(
+        other["appid"],
+        [Value::Int32(
+            shortcuts::appid("/usr/bin/other-game", "Other Game") as i32
+        )]
+    );
+    assert_eq!(other["AllowOverlay"], [Value::Int32(1)]);
+    assert_eq!(other["IsHidden"], [Value::Int32(0)]);
+
+    let path = std::env::temp_dir().join(format!("vdflex-shortcuts-{}.vdf", std::process::id()));
+    shortcuts::save(&path, &list)?;
+    let loaded = shortcuts::load(&path);
+    std::fs::remove_file(&path)?;
+    assert_eq!(loaded?, list);
+    Ok(())
+}
+
+#[test]
+fn shortcuts_errors() {
+    assert!(matches!(
+        shortcuts::from_slice(b"\x00other\0\x08\x08"),
+        Err(Error::Serde(_))
+    ));
+    assert!(matches!(
+        shortcuts::from_slice(b"\x00shortcuts\0\x00first\0\x08\x08\x08"),
+        Err(Error::Serde(_))
+    ));
+    assert!(matches!(
+        shortcuts::from_slice(b"\x00shortcuts\0\x00"),
+        Err(Error::InvalidBinary { .. })
+    ));
+}